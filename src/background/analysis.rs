@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use image::{DynamicImage, RgbImage};
+
+use crate::color::Color;
+
+// Size of the thumbnail the analysis runs on
+static ANALYSIS_SIZE: u32 = 64;
+// The thumbnail is cut in REGIONS x REGIONS regions to find the busiest one
+static REGIONS: u32 = 4;
+// Number of dominant colors kept
+static DOMINANT_COLORS: usize = 4;
+
+// Number of pixels of a bucket of colors with the sums of their channels
+type Bucket = (u32, [u32; 3]);
+
+// What the background looks like, to pick the colors drawn straight over it
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    pub average: Color,
+    // Average color under the outgoing messages, Telegram aligns them on the right of the chat
+    pub out_average: Color,
+    // Average relative luminance of the pixels
+    pub luminance: f32,
+    // Standard deviation of the relative luminance in the busiest region, 0 for a flat one
+    pub busyness: f32,
+    // Darkest and lightest colors of the busiest region
+    pub busiest_extremes: [Color; 2],
+    // Most common colors first
+    pub dominant: Vec<Color>,
+}
+
+impl Analysis {
+    // The colors an overlay can end up over
+    pub fn samples(&self) -> Vec<Color> {
+        let mut samples = vec![self.average.clone()];
+        samples.extend(self.busiest_extremes.iter().cloned());
+        samples.extend(self.dominant.iter().cloned());
+        samples
+    }
+}
+
+fn pixel_color(pixel: &image::Rgb<u8>) -> Color {
+    Color::from_rgb_int(pixel.0[0], pixel.0[1], pixel.0[2]).unwrap()
+}
+
+// Colors grouped in buckets of 4 bits per channel, with the average color of each one, the biggest
+// buckets first
+fn color_buckets(thumbnail: &RgbImage) -> Vec<(u32, Color)> {
+    let mut buckets: HashMap<[u8; 3], Bucket> = HashMap::new();
+    for pixel in thumbnail.pixels() {
+        let bucket = buckets.entry(pixel.0.map(|channel| channel >> 4)).or_insert((0, [0; 3]));
+        bucket.0 += 1;
+        for (sum, channel) in bucket.1.iter_mut().zip(pixel.0) {
+            *sum += channel as u32;
+        }
+    }
+
+    // Ties are broken on the bucket so the result doesn't depend on the HashMap order
+    let mut buckets: Vec<([u8; 3], Bucket)> = buckets.into_iter().collect();
+    buckets.sort_by_key(|(bucket, (count, _))| std::cmp::Reverse((*count, *bucket)));
+
+    buckets.into_iter()
+        .map(|(_, (count, sums))| {
+            let average = sums.map(|sum| (sum / count) as u8);
+            (count, Color::from_rgb_int(average[0], average[1], average[2]).unwrap())
+        })
+        .collect()
+}
+
+// Most common color of the image, colors are grouped in buckets of 4 bits per channel and the
+// pixels of the biggest bucket are averaged
+pub fn dominant_color(image: &DynamicImage) -> Color {
+    let thumbnail = image.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_rgb8();
+
+    let (_, color) = color_buckets(&thumbnail).into_iter().next()
+        .expect("Can not get the dominant color of an empty image");
+    color
+}
+
+pub fn analyse(image: &DynamicImage) -> Analysis {
+    let thumbnail = image.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_rgb8();
+    let colors: Vec<Color> = thumbnail.pixels().map(pixel_color).collect();
+    let luminance = colors.iter().map(|color| color.get_relative_luminance()).sum::<f32>() / colors.len() as f32;
+    let right_half: Vec<Color> = thumbnail.enumerate_pixels()
+        .filter(|(x, _, _)| *x >= thumbnail.width() / 2)
+        .map(|(_, _, pixel)| pixel_color(pixel))
+        .collect();
+
+    // The region where the luminance varies the most is the hardest to draw readable text over
+    let (width, height) = thumbnail.dimensions();
+    let (region_width, region_height) = (width.div_ceil(REGIONS), height.div_ceil(REGIONS));
+    let mut busiest: Option<(f32, Vec<Color>)> = None;
+    for region_y in (0..height).step_by(region_height as usize) {
+        for region_x in (0..width).step_by(region_width as usize) {
+            let region: Vec<Color> = (region_y..(region_y + region_height).min(height))
+                .flat_map(|y| (region_x..(region_x + region_width).min(width)).map(move |x| (x, y)))
+                .map(|(x, y)| pixel_color(thumbnail.get_pixel(x, y)))
+                .collect();
+
+            let luminances: Vec<f32> = region.iter().map(|color| color.get_relative_luminance()).collect();
+            let mean = luminances.iter().sum::<f32>() / luminances.len() as f32;
+            let deviation = (luminances.iter().map(|value| (value - mean).powi(2)).sum::<f32>() / luminances.len() as f32).sqrt();
+            // The first busiest region is kept on ties
+            if busiest.as_ref().is_none_or(|(busiest, _)| deviation > *busiest) {
+                busiest = Some((deviation, region));
+            }
+        }
+    }
+    let (busyness, region) = busiest.expect("Can not analyse an empty image");
+
+    let by_luminance = |a: &&Color, b: &&Color| a.get_relative_luminance().total_cmp(&b.get_relative_luminance());
+    let darkest = region.iter().min_by(by_luminance).unwrap().clone();
+    let lightest = region.iter().max_by(by_luminance).unwrap().clone();
+
+    Analysis {
+        average: crate::color::average_color(colors),
+        out_average: crate::color::average_color(right_half),
+        luminance,
+        busyness,
+        busiest_extremes: [darkest, lightest],
+        dominant: color_buckets(&thumbnail).into_iter().take(DOMINANT_COLORS).map(|(_, color)| color).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_outgoing_messages_are_over_the_right_half() {
+        // White on the left, black on the right
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(200, 100, |x, _| if x < 100 { image::Rgb([255; 3]) } else { image::Rgb([0; 3]) }));
+        let analysis = analyse(&image);
+
+        assert_eq!(analysis.out_average.to_hex(), "000000ff");
+        assert_eq!(analysis.average.to_hex(), "808080ff");
+    }
+}
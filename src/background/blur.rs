@@ -1,12 +1,151 @@
+// Get the blur radius in pixels from the blur percentage
+fn blur_radius(image: &image::DynamicImage, percentage: f32) -> f32 {
+    // INFO: `/ 15.0` is a bit of a magick number that I felt was just right
+    (std::cmp::min(image.width(), image.height()) as f32 / 15.0) * percentage
+}
+
+// The blurs work on RGB8 buffers, convert anything else (RGBA, 16 bits, grayscale...) first
+pub fn to_rgb8(image: &mut image::DynamicImage) {
+    if image.as_rgb8().is_none() {
+        *image = image::DynamicImage::ImageRgb8(image.to_rgb8());
+    }
+}
+
 pub fn blur_image(image: &mut image::DynamicImage, percentage: f32) -> Result<(), blurslice::SliceSizeError> {
+    to_rgb8(image);
+
     let width = image.width() as usize;
     let height = image.height() as usize;
-
-    // INFO: `/ 15.0` is a bit of a magick number that I felt was just right
-    let blur_radius = (std::cmp::min(width, height) as f32 / 15.0) * percentage;
+    let blur_radius = blur_radius(image, percentage);
 
     let rgb_image = image.as_mut_rgb8().unwrap();
 
     let samples = rgb_image.as_flat_samples_mut();
     blurslice::gaussian_blur_bytes::<3>(samples.samples, width, height, blur_radius)
 }
+
+pub fn box_blur_image(image: &mut image::DynamicImage, percentage: f32) {
+    blur_lines(image, percentage, box_blur_line);
+}
+
+pub fn stack_blur_image(image: &mut image::DynamicImage, percentage: f32) {
+    blur_lines(image, percentage, stack_blur_line);
+}
+
+// Run a separable blur on every row then every column
+fn blur_lines(image: &mut image::DynamicImage, percentage: f32, blur_line: fn(&mut [u8], usize, usize, usize, usize)) {
+    to_rgb8(image);
+
+    let radius = blur_radius(image, percentage).round() as usize;
+    if radius == 0 {
+        return;
+    }
+
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+    let data = image.as_mut_rgb8().unwrap().as_mut();
+
+    for y in 0..height {
+        blur_line(data, y * width * 3, 3, width, radius);
+    }
+    for x in 0..width {
+        blur_line(data, x * 3, width * 3, height, radius);
+    }
+}
+
+// Read the `len` RGB pixels of a line starting at `start`, `stride` bytes apart
+fn read_line(data: &[u8], start: usize, stride: usize, len: usize) -> Vec<[i64; 3]> {
+    (0..len).map(|i| {
+        let pos = start + i * stride;
+        [data[pos] as i64, data[pos + 1] as i64, data[pos + 2] as i64]
+    }).collect()
+}
+
+// Add `factor` times `pixel` to `sums`
+fn accumulate(sums: &mut [i64; 3], pixel: [i64; 3], factor: i64) {
+    for (sum, value) in sums.iter_mut().zip(pixel) {
+        *sum += value * factor;
+    }
+}
+
+fn write_pixel(data: &mut [u8], pos: usize, sums: &[i64; 3], divisor: i64) {
+    for channel in 0..3 {
+        data[pos + channel] = (sums[channel] / divisor).clamp(0, 255) as u8;
+    }
+}
+
+// Every pixel in the radius has the same weight
+fn box_blur_line(data: &mut [u8], start: usize, stride: usize, len: usize, radius: usize) {
+    let line = read_line(data, start, stride, len);
+    // Edges are extended
+    let pixel = |i: isize| line[i.clamp(0, len as isize - 1) as usize];
+    let radius = radius as isize;
+
+    let mut sum = [0_i64; 3];
+    for i in -radius..=radius {
+        accumulate(&mut sum, pixel(i), 1);
+    }
+
+    for i in 0..len as isize {
+        write_pixel(data, start + i as usize * stride, &sum, 2 * radius as i64 + 1);
+
+        accumulate(&mut sum, pixel(i + radius + 1), 1);
+        accumulate(&mut sum, pixel(i - radius), -1);
+    }
+}
+
+// Pixels are weighted by `radius + 1 - distance`, the sum is updated with the incoming and
+// outgoing halves of the kernel so the cost does not depend on the radius
+fn stack_blur_line(data: &mut [u8], start: usize, stride: usize, len: usize, radius: usize) {
+    let line = read_line(data, start, stride, len);
+    // Edges are extended
+    let pixel = |i: isize| line[i.clamp(0, len as isize - 1) as usize];
+    let radius = radius as isize;
+
+    let mut sum = [0_i64; 3];
+    let mut sum_in = [0_i64; 3];
+    let mut sum_out = [0_i64; 3];
+    for i in -radius..=radius {
+        accumulate(&mut sum, pixel(i), (radius + 1 - i.abs()) as i64);
+    }
+    for i in 1..=(radius + 1) {
+        accumulate(&mut sum_in, pixel(i), 1);
+    }
+    for i in -radius..=0 {
+        accumulate(&mut sum_out, pixel(i), 1);
+    }
+
+    let divisor = (radius as i64 + 1) * (radius as i64 + 1);
+    for i in 0..len as isize {
+        write_pixel(data, start + i as usize * stride, &sum, divisor);
+
+        accumulate(&mut sum, sum_in, 1);
+        accumulate(&mut sum, sum_out, -1);
+        accumulate(&mut sum_in, pixel(i + radius + 2), 1);
+        accumulate(&mut sum_in, pixel(i + 1), -1);
+        accumulate(&mut sum_out, pixel(i + 1), 1);
+        accumulate(&mut sum_out, pixel(i - radius), -1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blurs_keep_a_flat_image_flat() {
+        let blurs: [fn(&mut image::DynamicImage, f32); 3] = [
+            |image, blur| blur_image(image, blur).unwrap(),
+            box_blur_image,
+            stack_blur_image,
+        ];
+
+        for blur in blurs {
+            let mut image = image::DynamicImage::ImageRgba16(image::ImageBuffer::from_pixel(40, 24, image::Rgba([30000, 40000, 50000, 65535])));
+            blur(&mut image, 1.0);
+
+            let expected = image::Rgb([117, 156, 195]);
+            assert!(image.as_rgb8().unwrap().pixels().all(|pixel| *pixel == expected));
+        }
+    }
+}
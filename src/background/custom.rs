@@ -1,7 +1,12 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::path;
+use std::rc::Rc;
+
+use crate::color::Color;
 
 // Check if the background file exists
-pub fn use_custom_background(tmp_dir: &std::path::PathBuf, blur: f32, background_path: &str) {
+pub fn use_custom_background(tmp_dir: &std::path::Path, processing: &super::Processing, palette: &HashMap<String, Rc<RefCell<Color>>>, images: &mut super::ImageCache, background_path: &str) {
 
     let path;
     if background_path.is_empty() {
@@ -16,5 +21,5 @@ pub fn use_custom_background(tmp_dir: &std::path::PathBuf, blur: f32, background
         }
     }
 
-    super::copy_background(&tmp_dir, blur, path.to_str().unwrap());
+    super::copy_background(tmp_dir, processing, palette, images, path.to_str().unwrap());
 }
@@ -0,0 +1,304 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use image::{DynamicImage, GenericImageView};
+
+use crate::color::Color;
+
+// An effect applied to image backgrounds, effects are chained in order
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Effect {
+    // Blurs with a strength relative to the image size, like --blur
+    Gaussian(f32),
+    Box(f32),
+    Stack(f32),
+    // Size of the blocks in pixels
+    Pixelate(u32),
+    // Strength from 0 (unchanged) to 1 (grayscale)
+    Desaturate(f32),
+    // Move the pixels towards the palette background, only ever darkening/lightening them,
+    // strength from 0 (unchanged) to 1
+    Darken(f32),
+    Lighten(f32),
+    // Colorize with the palette, duotone goes from color0 to the given accent index
+    Duotone(usize),
+    GradientMap,
+    // Nearest palette color, with dithering or not
+    Posterize(bool),
+}
+
+impl FromStr for Effect {
+    type Err = String;
+
+    // Parse an effect formatted as NAME:VALUE, eg. 'gaussian:0.1' or 'pixelate:8'
+    // The value is optional for gradient-map and posterize
+    fn from_str(effect: &str) -> Result<Self, Self::Err> {
+        let (name, value) = effect.split_once(':').unwrap_or((effect, ""));
+        if value.is_empty() && !matches!(name, "gradient-map" | "posterize") {
+            return Err(format!("'{effect}' is not formatted as NAME:VALUE"));
+        }
+
+        let strength = || -> Result<f32, String> {
+            let strength: f32 = value.parse().map_err(|_| format!("Invalid strength '{value}' for the {name} effect"))?;
+            if !(0.0..=1.0).contains(&strength) {
+                return Err(format!("The {name} effect strength must be between 0 and 1"));
+            }
+            Ok(strength)
+        };
+        let blur = || -> Result<f32, String> {
+            let blur: f32 = value.parse().map_err(|_| format!("Invalid blur '{value}' for the {name} effect"))?;
+            if blur < 0.0 {
+                return Err(format!("The {name} effect blur can not be negative"));
+            }
+            Ok(blur)
+        };
+
+        match name {
+            "gaussian" => Ok(Effect::Gaussian(blur()?)),
+            "box" => Ok(Effect::Box(blur()?)),
+            "stack" => Ok(Effect::Stack(blur()?)),
+            "pixelate" => {
+                let size: u32 = value.parse().map_err(|_| format!("Invalid block size '{value}' for the pixelate effect"))?;
+                if size == 0 {
+                    return Err(String::from("The pixelate effect block size can not be 0"));
+                }
+                Ok(Effect::Pixelate(size))
+            }
+            "desaturate" => Ok(Effect::Desaturate(strength()?)),
+            "darken" => Ok(Effect::Darken(strength()?)),
+            "lighten" => Ok(Effect::Lighten(strength()?)),
+            "duotone" => {
+                let accent: usize = value.parse().map_err(|_| format!("Invalid palette index '{value}' for the duotone effect"))?;
+                Ok(Effect::Duotone(accent))
+            }
+            "gradient-map" => Ok(Effect::GradientMap),
+            "posterize" => {
+                match value {
+                    "" => Ok(Effect::Posterize(false)),
+                    "dither" => Ok(Effect::Posterize(true)),
+                    _ => Err(format!("Invalid value '{value}' for the posterize effect, only 'dither' is allowed")),
+                }
+            }
+            _ => Err(format!("Unknown effect '{name}', expected one of gaussian, box, stack, pixelate, desaturate, darken, lighten, duotone, gradient-map, posterize")),
+        }
+    }
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Effect::Gaussian(blur) => write!(f, "gaussian:{blur}"),
+            Effect::Box(blur) => write!(f, "box:{blur}"),
+            Effect::Stack(blur) => write!(f, "stack:{blur}"),
+            Effect::Pixelate(size) => write!(f, "pixelate:{size}"),
+            Effect::Desaturate(strength) => write!(f, "desaturate:{strength}"),
+            Effect::Darken(strength) => write!(f, "darken:{strength}"),
+            Effect::Lighten(strength) => write!(f, "lighten:{strength}"),
+            Effect::Duotone(accent) => write!(f, "duotone:{accent}"),
+            Effect::GradientMap => write!(f, "gradient-map"),
+            Effect::Posterize(false) => write!(f, "posterize"),
+            Effect::Posterize(true) => write!(f, "posterize:dither"),
+        }
+    }
+}
+
+impl TryFrom<String> for Effect {
+    type Error = String;
+
+    fn try_from(effect: String) -> Result<Self, Self::Error> {
+        effect.parse()
+    }
+}
+
+impl From<Effect> for String {
+    fn from(effect: Effect) -> Self {
+        effect.to_string()
+    }
+}
+
+// - palette is used by the tints, its color0 is what darken and lighten move towards
+pub fn apply_effects(image: &mut DynamicImage, effects: &[Effect], palette: &HashMap<String, Rc<RefCell<Color>>>) {
+    let palette_bg = palette["color0"].borrow().clone();
+
+    for effect in effects {
+        match effect {
+            Effect::Gaussian(blur) => {
+                super::blur::blur_image(image, *blur).expect("Unexpected error, could not blur the image");
+            }
+            Effect::Box(blur) => super::blur::box_blur_image(image, *blur),
+            Effect::Stack(blur) => super::blur::stack_blur_image(image, *blur),
+            Effect::Pixelate(size) => pixelate(image, *size),
+            Effect::Desaturate(strength) => desaturate(image, *strength),
+            Effect::Darken(strength) => move_towards(image, &palette_bg, *strength, true),
+            Effect::Lighten(strength) => move_towards(image, &palette_bg, *strength, false),
+            Effect::Duotone(accent) => super::tint::duotone(image, palette, *accent),
+            Effect::GradientMap => super::tint::gradient_map(image, palette),
+            Effect::Posterize(dither) => super::tint::posterize(image, palette, *dither),
+        }
+    }
+}
+
+fn pixelate(image: &mut DynamicImage, size: u32) {
+    let (width, height) = image.dimensions();
+
+    // Averaging on the way down, blocks on the way up
+    let small = image.resize_exact((width / size).max(1), (height / size).max(1), image::imageops::FilterType::Triangle);
+    *image = small.resize_exact(width, height, image::imageops::FilterType::Nearest);
+}
+
+fn desaturate(image: &mut DynamicImage, strength: f32) {
+    super::blur::to_rgb8(image);
+
+    for pixel in image.as_mut_rgb8().unwrap().pixels_mut() {
+        let luma = 0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32;
+        for channel in pixel.0.iter_mut() {
+            *channel = (*channel as f32 + (luma - *channel as f32) * strength).round() as u8;
+        }
+    }
+}
+
+// Move the pixels towards the target in linear light, so that they keep to the line between their
+// color and the target. Darken only moves the pixels lighter than the target and lighten the
+// darker ones
+fn move_towards(image: &mut DynamicImage, target: &Color, strength: f32, darken: bool) {
+    super::blur::to_rgb8(image);
+
+    let target_luma = target.get_relative_luminance();
+    for pixel in image.as_mut_rgb8().unwrap().pixels_mut() {
+        let color = Color::from_rgb_int(pixel[0], pixel[1], pixel[2]).unwrap();
+        let luma = color.get_relative_luminance();
+        if (luma > target_luma) != darken || luma == target_luma {
+            continue;
+        }
+
+        let moved = color.mix_linear(target, strength);
+        let moved_luma = moved.written().get_relative_luminance();
+        // Rounding to 8 bits can't move a pixel the other way
+        if (moved_luma > luma) == darken && moved_luma != luma {
+            continue;
+        }
+        *pixel = image::Rgb([moved.u8_red(), moved.u8_green(), moved.u8_blue()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::{fixtures, Mode};
+
+    // The image in every pixel format the image crate decodes to
+    fn formats(image: DynamicImage) -> Vec<DynamicImage> {
+        vec![
+            DynamicImage::ImageLuma8(image.to_luma8()),
+            DynamicImage::ImageLumaA8(image.to_luma_alpha8()),
+            DynamicImage::ImageRgb8(image.to_rgb8()),
+            image.clone(),
+            DynamicImage::ImageLuma16(image.to_luma16()),
+            DynamicImage::ImageLumaA16(image.to_luma_alpha16()),
+            DynamicImage::ImageRgb16(image.to_rgb16()),
+            DynamicImage::ImageRgba16(image.to_rgba16()),
+            DynamicImage::ImageRgb32F(image.to_rgb32f()),
+            DynamicImage::ImageRgba32F(image.to_rgba32f()),
+        ]
+    }
+
+    // A small gradient with some transparency
+    fn gradient() -> DynamicImage {
+        DynamicImage::ImageRgba8(image::RgbaImage::from_fn(48, 32, |x, y| image::Rgba([(x * 5) as u8, (y * 7) as u8, ((x + y) * 3) as u8, 128 + x as u8])))
+    }
+
+    // Black and white squares of 2 pixels
+    fn checkerboard() -> DynamicImage {
+        DynamicImage::ImageRgba8(image::RgbaImage::from_fn(48, 32, |x, y| image::Rgba([if (x / 2 + y / 2) % 2 == 0 { 0 } else { 255 }; 4])))
+    }
+
+    // The effect applied to the image, in 8 bits RGB to compare the pixels
+    fn applied(image: &DynamicImage, effect: &str) -> image::RgbImage {
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+        let mut image = image.clone();
+        apply_effects(&mut image, &[effect.parse().unwrap()], &palette);
+        image.to_rgb8()
+    }
+
+    fn luminance(pixel: &image::Rgb<u8>) -> f32 {
+        Color::from_rgb_int(pixel[0], pixel[1], pixel[2]).unwrap().get_relative_luminance()
+    }
+
+    fn luminance_variance(image: &image::RgbImage) -> f32 {
+        let values: Vec<f32> = image.pixels().map(luminance).collect();
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        values.iter().map(|value| (value - mean).powi(2)).sum::<f32>() / values.len() as f32
+    }
+
+    #[test]
+    fn every_effect_runs_on_every_pixel_format() {
+        for effect in [
+            "gaussian:0.5", "box:0.5", "stack:0.5", "pixelate:4", "desaturate:0.5", "darken:0.5",
+            "lighten:0.5", "duotone:4", "gradient-map", "posterize", "posterize:dither",
+        ] {
+            for original in formats(gradient()) {
+                assert_eq!(applied(&original, effect).dimensions(), (48, 32), "{effect} on {:?}", original.color());
+            }
+        }
+    }
+
+    #[test]
+    fn every_effect_does_its_job_on_every_pixel_format() {
+        for original in formats(gradient()) {
+            let format = original.color();
+            let pixels = original.to_rgb8();
+
+            assert!(applied(&original, "desaturate:1").pixels().all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]), "{format:?}");
+
+            for (effect, darken) in [("darken:0.5", true), ("lighten:0.5", false), ("darken:1", true), ("lighten:1", false)] {
+                let moved = applied(&original, effect);
+                assert_ne!(moved, pixels, "{effect} on {format:?}");
+                for (before, after) in pixels.pixels().zip(moved.pixels()) {
+                    let (before, after) = (luminance(before), luminance(after));
+                    assert!(if darken { after <= before } else { after >= before }, "{effect} on {format:?}");
+                }
+            }
+
+            let pixelated = applied(&original, "pixelate:4");
+            for (x, y, pixel) in pixelated.enumerate_pixels() {
+                assert_eq!(pixel, pixelated.get_pixel(x / 4 * 4, y / 4 * 4), "{format:?}");
+            }
+        }
+
+        for original in formats(checkerboard()) {
+            let variance = luminance_variance(&original.to_rgb8());
+            for effect in ["gaussian:0.5", "box:0.5", "stack:0.5"] {
+                assert!(luminance_variance(&applied(&original, effect)) < variance * 0.9, "{effect} on {:?}", original.color());
+            }
+        }
+    }
+
+    #[test]
+    fn darken_and_lighten_move_towards_the_background() {
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+        let background = palette["color0"].borrow().to_hex();
+        let red = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(4, 4, image::Rgb([255, 0, 0])));
+        let black = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(4, 4, image::Rgb([0, 0, 0])));
+
+        // All the way, the pixels on their side become the background, the others are left alone
+        let pixel = |image: &image::RgbImage| Color::from_rgb_int(image[(0, 0)][0], image[(0, 0)][1], image[(0, 0)][2]).unwrap().to_hex();
+        assert_eq!(pixel(&applied(&red, "darken:1")), background);
+        assert_eq!(pixel(&applied(&black, "lighten:1")), background);
+        assert_eq!(pixel(&applied(&black, "darken:1")), "000000ff");
+
+        // Halfway, the red is on the line to the background rather than a darker red
+        let [red, green, blue] = applied(&red, "darken:0.5")[(0, 0)].0;
+        assert!(green > 0 && blue > 0 && red < 255, "{red} {green} {blue}");
+    }
+
+    #[test]
+    fn effects_round_trip_through_their_text_form() {
+        for effect in ["gaussian:0.1", "pixelate:8", "duotone:4", "gradient-map", "posterize:dither"] {
+            assert_eq!(effect.parse::<Effect>().unwrap().to_string(), effect);
+        }
+    }
+}
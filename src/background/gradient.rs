@@ -0,0 +1,97 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use image::{DynamicImage, RgbImage};
+
+use crate::color::Color;
+
+// Color positions of Telegram's 4 colors gradient wallpapers
+static FOUR_POINT_POSITIONS: [(f32, f32); 4] = [(0.80, 0.10), (0.35, 0.25), (0.20, 0.90), (0.65, 0.75)];
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Gradient {
+    // Along the angle
+    Linear,
+    // From the center to the corners
+    Radial,
+    // Telegram style, each color is pulled towards its own point
+    FourPoint,
+}
+
+// - roles are the palette indexes of the gradient colors, in order
+// - angle is in degrees for linear gradients, 0 goes from left to right and 90 from top to bottom
+pub fn use_gradient_image(tmp_dir: &std::path::Path, palette: &HashMap<String, Rc<RefCell<Color>>>, gradient: Gradient, roles: &[usize], angle: f32, resolution: (u32, u32), max_theme_size: Option<u64>) {
+    let colors = super::palette_roles(palette, roles);
+    let (width, height) = resolution;
+
+    let img = match gradient {
+        Gradient::Linear => {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            let extent = (width as f32 / 2.0) * cos.abs() + (height as f32 / 2.0) * sin.abs();
+
+            RgbImage::from_fn(width, height, |x, y| {
+                let (dx, dy) = (x as f32 + 0.5 - width as f32 / 2.0, y as f32 + 0.5 - height as f32 / 2.0);
+                let position = ((dx * cos + dy * sin) / extent + 1.0) / 2.0;
+                to_pixel(stops(&colors, position))
+            })
+        }
+        Gradient::Radial => {
+            let extent = ((width * width + height * height) as f32).sqrt() / 2.0;
+
+            RgbImage::from_fn(width, height, |x, y| {
+                let (dx, dy) = (x as f32 + 0.5 - width as f32 / 2.0, y as f32 + 0.5 - height as f32 / 2.0);
+                to_pixel(stops(&colors, (dx * dx + dy * dy).sqrt() / extent))
+            })
+        }
+        Gradient::FourPoint => {
+            RgbImage::from_fn(width, height, |x, y| {
+                let (px, py) = (x as f32 / width as f32, y as f32 / height as f32);
+
+                // Same weighting as Telegram's gradient generation
+                let mut total = 0.0;
+                let mut pixel = [0.0; 3];
+                for (index, (cx, cy)) in FOUR_POINT_POSITIONS.iter().enumerate() {
+                    let distance = ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+                    let weight = (0.9 - distance).max(0.0).powi(4);
+                    let color = colors[index % colors.len()];
+
+                    total += weight;
+                    for channel in 0..3 {
+                        pixel[channel] += color[channel] * weight;
+                    }
+                }
+
+                if total > 0.0 {
+                    pixel = pixel.map(|channel| channel / total);
+                }
+                to_pixel(pixel)
+            })
+        }
+    };
+
+    super::save_background(tmp_dir, &DynamicImage::ImageRgb8(img), max_theme_size);
+}
+
+// Color at `position` (0 to 1) of the evenly spaced stops
+fn stops(colors: &[[f32; 3]], position: f32) -> [f32; 3] {
+    if colors.len() == 1 {
+        return colors[0];
+    }
+
+    let position = position.clamp(0.0, 1.0) * (colors.len() - 1) as f32;
+    let index = (position.floor() as usize).min(colors.len() - 2);
+    let amount = position - index as f32;
+
+    let (from, to) = (colors[index], colors[index + 1]);
+    [
+        from[0] + (to[0] - from[0]) * amount,
+        from[1] + (to[1] - from[1]) * amount,
+        from[2] + (to[2] - from[2]) * amount,
+    ]
+}
+
+fn to_pixel(color: [f32; 3]) -> image::Rgb<u8> {
+    image::Rgb(color.map(|channel| channel.round().clamp(0.0, 255.0) as u8))
+}
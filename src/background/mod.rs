@@ -1,35 +1,111 @@
-use std::{cell::RefCell, rc::Rc, collections::HashMap};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use image::ImageBuffer;
+use image::{DynamicImage, ImageBuffer};
 
 use crate::color::Color;
 
 pub mod wal;
+pub mod analysis;
 pub mod custom;
 pub mod blur;
+pub mod effects;
+pub mod gradient;
+pub mod pattern;
 pub mod plain;
+pub mod resize;
+pub mod tint;
 
 pub static BG_FILENAME: &str = "background.jpg";
 
-pub fn copy_background(tmp_dir: &std::path::PathBuf, blur:f32, bg_str: &str) {
-    let mut tmp_bg = tmp_dir.clone();
-    tmp_bg.push(&BG_FILENAME);
+// Room left in the theme for the palette file when enforcing a maximum theme size
+static PALETTE_SIZE_RESERVE: u64 = 16 * 1024;
+// JPEG qualities tried in order until the background fits
+static JPEG_QUALITIES: [u8; 8] = [90, 80, 70, 60, 50, 40, 30, 20];
 
-    // INFO: Trying to make it an image to verify it's validity
-    let mut bg_img = image::open(&bg_str)
-                            .expect(format!("Could not recognize background file '{}' as an image", bg_str).as_str());
+// How an image background is processed before being put in the theme
+#[derive(Clone, Debug)]
+pub struct Processing {
+    pub resize: resize::Resize,
+    pub effects: Vec<effects::Effect>,
+    // Maximum size of the whole theme in bytes
+    pub max_theme_size: Option<u64>,
+}
+
+// Decoded images by path, a wallpaper used by several profiles is only decoded once
+#[derive(Default)]
+pub struct ImageCache {
+    images: HashMap<String, DynamicImage>,
+}
+
+impl ImageCache {
+    pub fn open(&mut self, path: &str) -> &DynamicImage {
+        self.images.entry(String::from(path)).or_insert_with(|| {
+            // INFO: Trying to make it an image to verify it's validity
+            image::open(path).unwrap_or_else(|_| panic!("Could not recognize background file '{}' as an image", path))
+        })
+    }
+}
+
+pub fn copy_background(tmp_dir: &std::path::Path, processing: &Processing, palette: &HashMap<String, Rc<RefCell<Color>>>, images: &mut ImageCache, bg_str: &str) {
+    // Work on a copy, the cached image is shared with the other profiles
+    let mut bg_img = images.open(bg_str).clone();
+
+    // Resize before the effects, blurring a smaller image is a lot faster
+    resize::resize_image(&mut bg_img, &processing.resize);
+
+    effects::apply_effects(&mut bg_img, &processing.effects, palette);
+
+    save_background(tmp_dir, &bg_img, processing.max_theme_size);
+}
+
+// Write the background to the tmp folder
+pub fn save_background(tmp_dir: &std::path::Path, image: &image::DynamicImage, max_theme_size: Option<u64>) {
+    let mut tmp_bg = tmp_dir.to_path_buf();
+    tmp_bg.push(BG_FILENAME);
+
+    let bg_bytes = encode_background(image, max_theme_size);
+    std::fs::write(&tmp_bg, bg_bytes).expect("Couldn't save the tmp background file to the tmp folder");
+}
+
+// Get the palette colors at the given indexes as 0-255 RGB values
+pub fn palette_roles(palette: &HashMap<String, Rc<RefCell<Color>>>, roles: &[usize]) -> Vec<[f32; 3]> {
+    if roles.is_empty() {
+        panic!("At least one palette color is needed to generate a background");
+    }
+
+    roles.iter().map(|role| {
+        let color = palette.get(format!("color{role}").as_str())
+            .unwrap_or_else(|| panic!("The background color 'color{role}' is not in the palette"))
+            .borrow();
+        [color.u8_red() as f32, color.u8_green() as f32, color.u8_blue() as f32]
+    }).collect()
+}
+
+// Encode as JPEG, lowering the quality step by step until the theme fits in max_theme_size
+fn encode_background(image: &image::DynamicImage, max_theme_size: Option<u64>) -> Vec<u8> {
+    let rgb_image = image::DynamicImage::ImageRgb8(image.to_rgb8());
+    let max_bg_size = max_theme_size.map(|size| size.saturating_sub(PALETTE_SIZE_RESERVE));
 
-    // Blur if needed
-    if blur > 0.0 {
-        blur::blur_image(&mut bg_img, blur).expect("Unexpected error, could not blur the image");
+    let mut bytes = Vec::new();
+    for quality in JPEG_QUALITIES {
+        bytes.clear();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, quality)
+            .encode_image(&rgb_image)
+            .expect("Unexpected error, could not encode the background as JPEG");
+
+        match max_bg_size {
+            Some(max_bg_size) if bytes.len() as u64 > max_bg_size => {}
+            _ => { return bytes; }
+        }
     }
 
-    bg_img.save_with_format(&tmp_bg, image::ImageFormat::Jpeg).expect("Couldn't save the tmp background file to the tmp folder");
+    eprintln!("WARNING! The background is still {} bytes at the lowest quality, the theme will be bigger than requested", bytes.len());
+    bytes
 }
 
-pub fn create_bg(tmp_dir: &std::path::PathBuf, color: &Color) {
-    let mut tmp_bg = tmp_dir.clone();
-    tmp_bg.push(&BG_FILENAME);
+pub fn create_bg(tmp_dir: &std::path::Path, color: &Color) {
+    let mut tmp_bg = tmp_dir.to_path_buf();
+    tmp_bg.push(BG_FILENAME);
 
     let color_u8 = [
         color.u8_red(),
@@ -41,5 +117,43 @@ pub fn create_bg(tmp_dir: &std::path::PathBuf, color: &Color) {
     });
 
     // Save image
-    img.save(&tmp_bg).expect(format!("Error writing plain background file to '{}'", tmp_bg.display()).as_str());
+    img.save(&tmp_bg).unwrap_or_else(|_| panic!("Error writing plain background file to '{}'", tmp_bg.display()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::{fixtures, Mode};
+
+    fn processing(effects: Vec<effects::Effect>) -> Processing {
+        Processing { resize: resize::Resize::default(), effects, max_theme_size: None }
+    }
+
+    #[test]
+    fn a_blur_does_not_leak_into_the_next_profile() {
+        let tmp_dir = crate::tmp_dir::create_tmp_dir(false);
+        let wallpaper = tmp_dir.join("wallpaper.png");
+        image::RgbImage::from_fn(64, 64, |x, y| if (x / 4 + y / 4) % 2 == 0 { image::Rgb([255, 255, 255]) } else { image::Rgb([0, 0, 0]) })
+            .save(&wallpaper)
+            .unwrap();
+        let wallpaper = wallpaper.to_str().unwrap();
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+
+        let background = |profile: &str, effects: Vec<effects::Effect>, images: &mut ImageCache| {
+            let profile_dir = tmp_dir.join(profile);
+            std::fs::create_dir(&profile_dir).unwrap();
+            copy_background(&profile_dir, &processing(effects), &palette, images, wallpaper);
+            std::fs::read(profile_dir.join(BG_FILENAME)).unwrap()
+        };
+
+        let alone = background("alone", vec![], &mut ImageCache::default());
+
+        // Both profiles use the same decoded wallpaper
+        let mut images = ImageCache::default();
+        let blurred = background("blurred", vec![effects::Effect::Gaussian(1.0)], &mut images);
+        let sharp = background("sharp", vec![], &mut images);
+
+        assert_ne!(blurred, alone);
+        assert_eq!(sharp, alone);
+    }
 }
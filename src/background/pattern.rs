@@ -0,0 +1,87 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use image::{DynamicImage, RgbImage};
+use rand::{Rng, SeedableRng};
+
+use crate::color::Color;
+
+// Fixed so the same palette always gives the same noise
+static NOISE_SEED: u64 = 0x5741_4c54;
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Pattern {
+    // Staggered dots
+    Dots,
+    // Diagonal stripes
+    Stripes,
+    // Grain of the foreground colors over the background
+    Noise,
+}
+
+// - roles are the palette indexes used, the first one is the background and the others are cycled
+// through for the pattern
+// - size is the spacing of the pattern in pixels
+pub fn use_pattern_image(tmp_dir: &std::path::Path, palette: &HashMap<String, Rc<RefCell<Color>>>, pattern: Pattern, roles: &[usize], resolution: (u32, u32), size: u32, max_theme_size: Option<u64>) {
+    let colors: Vec<image::Rgb<u8>> = super::palette_roles(palette, roles).iter()
+        .map(|color| image::Rgb(color.map(|channel| channel as u8)))
+        .collect();
+    let background = colors[0];
+    let foreground = if colors.len() > 1 { &colors[1..] } else { &colors[..] };
+
+    let (width, height) = resolution;
+    let size = size.max(2);
+
+    let img = match pattern {
+        Pattern::Dots => {
+            let radius = (size as f32 / 6.0).max(1.0);
+
+            RgbImage::from_fn(width, height, |x, y| {
+                let row = y / size;
+                // Every other row is shifted by half a cell
+                let shift = if row.is_multiple_of(2) { 0 } else { size / 2 };
+                let cell_x = (x + shift) % size;
+                let cell_y = y % size;
+
+                let (dx, dy) = (cell_x as f32 - size as f32 / 2.0, cell_y as f32 - size as f32 / 2.0);
+                if (dx * dx + dy * dy).sqrt() <= radius {
+                    let column = (x + shift) / size;
+                    foreground[((row + column) as usize) % foreground.len()]
+                }
+                else {
+                    background
+                }
+            })
+        }
+        Pattern::Stripes => {
+            RgbImage::from_fn(width, height, |x, y| {
+                let stripe = (x + y) / (size / 2);
+                if stripe.is_multiple_of(2) {
+                    background
+                }
+                else {
+                    foreground[((stripe / 2) as usize) % foreground.len()]
+                }
+            })
+        }
+        Pattern::Noise => {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(NOISE_SEED);
+
+            RgbImage::from_fn(width, height, |_, _| {
+                let color = foreground[rng.gen_range(0..foreground.len())];
+                // Squared so most of the pixels stay close to the background
+                let amount: f32 = rng.gen::<f32>().powi(2);
+
+                let mut pixel = background;
+                for (channel, target) in pixel.0.iter_mut().zip(color.0) {
+                    *channel = (*channel as f32 + (target as f32 - *channel as f32) * amount).round() as u8;
+                }
+                pixel
+            })
+        }
+    };
+
+    super::save_background(tmp_dir, &DynamicImage::ImageRgb8(img), max_theme_size);
+}
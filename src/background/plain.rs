@@ -1,25 +1,229 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
+use std::str::FromStr;
 
 use crate::color::Color;
-use crate::color::average_color;
+use crate::color::{average_color, oklab_average_color};
+use crate::telegram::colors::Bubbles;
 
-// - Set the palette parameter to use an average of the palette as the color
-// - Set the color_hex to use a color
-pub fn use_plain_image(tmp_dir: &std::path::PathBuf, palette: Option<&HashMap<String, Rc<RefCell<Color>>>>, color_hex: Option<&str>) {
-    let color;
+// How the plain background color is chosen
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum PlainColor {
+    // RGB average of all the palette entries
+    Average,
+    // A palette entry
+    Role(usize),
+    // color0 darkened by a percentage
+    Darken(u8),
+    // Average of the distinct palette colors in OKLab
+    Oklab,
+    // Most common color of the wallpaper
+    Dominant,
+    // The color giving the most contrast to the message bubbles
+    Contrast,
+    // A given color
+    Hex(String),
+}
+
+impl FromStr for PlainColor {
+    type Err = String;
+
+    // Parse 'average', 'role:INDEX', 'darken:PERCENTAGE', 'oklab', 'dominant', 'contrast' or a hex
+    // color starting with '#'
+    fn from_str(strategy: &str) -> Result<Self, Self::Err> {
+        if strategy.starts_with('#') {
+            Color::from_hex(strategy).ok_or_else(|| format!("'{strategy}' is not a valid hex color"))?;
+            return Ok(PlainColor::Hex(String::from(strategy)));
+        }
+
+        let (name, value) = strategy.split_once(':').unwrap_or((strategy, ""));
+        match (name, value) {
+            ("average", "") => Ok(PlainColor::Average),
+            ("role", index) => Ok(PlainColor::Role(crate::cli::parse_palette_index(index)?)),
+            ("darken", percentage) => {
+                let percentage: u8 = percentage.parse().ok().filter(|percentage| *percentage <= 100)
+                    .ok_or_else(|| format!("Invalid percentage '{percentage}' for the darken plain color"))?;
+                Ok(PlainColor::Darken(percentage))
+            }
+            ("oklab", "") => Ok(PlainColor::Oklab),
+            ("dominant", "") => Ok(PlainColor::Dominant),
+            ("contrast", "") => Ok(PlainColor::Contrast),
+            _ => Err(format!("Unknown plain color '{strategy}', expected average, role:INDEX, darken:PERCENTAGE, oklab, dominant, contrast or a #hex color")),
+        }
+    }
+}
+
+impl fmt::Display for PlainColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlainColor::Average => write!(f, "average"),
+            PlainColor::Role(index) => write!(f, "role:{index}"),
+            PlainColor::Darken(percentage) => write!(f, "darken:{percentage}"),
+            PlainColor::Oklab => write!(f, "oklab"),
+            PlainColor::Dominant => write!(f, "dominant"),
+            PlainColor::Contrast => write!(f, "contrast"),
+            PlainColor::Hex(hex) => write!(f, "{hex}"),
+        }
+    }
+}
+
+impl TryFrom<String> for PlainColor {
+    type Error = String;
+
+    fn try_from(strategy: String) -> Result<Self, Self::Error> {
+        strategy.parse()
+    }
+}
 
-    if palette.is_some() {
-        let colors = palette.unwrap().values().map(|col| col.borrow().clone()).clone().collect();
-        color = average_color(colors);
+impl From<PlainColor> for String {
+    fn from(strategy: PlainColor) -> Self {
+        strategy.to_string()
     }
-    else if color_hex.is_some() {
-        color = Color::from_hex(color_hex.unwrap()).expect(format!("Could not parse plain image color '{}'", color_hex.unwrap()).as_str());
+}
+
+// - wallpaper is the image used by the dominant strategy
+// - bubbles are the message bubbles the contrast strategy stands out from
+pub fn use_plain_image(tmp_dir: &std::path::Path, palette: &HashMap<String, Rc<RefCell<Color>>>, strategy: &PlainColor, wallpaper: Option<&image::DynamicImage>, bubbles: &Bubbles) {
+    let color = plain_color(palette, strategy, wallpaper, bubbles);
+
+    super::create_bg(tmp_dir, &color);
+}
+
+pub fn plain_color(palette: &HashMap<String, Rc<RefCell<Color>>>, strategy: &PlainColor, wallpaper: Option<&image::DynamicImage>, bubbles: &Bubbles) -> Color {
+    match strategy {
+        PlainColor::Average => {
+            // Summed by index, the float rounding must not depend on the HashMap order
+            average_color((0..palette.len()).map(|index| palette[format!("color{index}").as_str()].borrow().clone()).collect())
+        }
+        PlainColor::Role(index) => {
+            palette.get(format!("color{index}").as_str())
+                .unwrap_or_else(|| panic!("The plain background color 'color{index}' is not in the palette"))
+                .borrow().clone()
+        }
+        PlainColor::Darken(percentage) => {
+            palette["color0"].borrow().darker(*percentage).borrow().clone()
+        }
+        PlainColor::Oklab => {
+            oklab_average_color(distinct_colors(palette))
+        }
+        PlainColor::Dominant => {
+            super::analysis::dominant_color(wallpaper.expect("The dominant plain color needs a wallpaper"))
+        }
+        PlainColor::Contrast => {
+            most_contrasted(palette, bubbles)
+        }
+        PlainColor::Hex(hex) => {
+            Color::from_hex(hex).unwrap_or_else(|| panic!("Could not parse plain image color '{}'", hex))
+        }
     }
-    else {
-        panic!("Use either a palette or a color hex for plain image generation");
+}
+
+// The palette colors ordered by index, without the duplicates of wal8 palettes
+fn distinct_colors(palette: &HashMap<String, Rc<RefCell<Color>>>) -> Vec<Color> {
+    let mut colors: Vec<Color> = Vec::new();
+
+    for index in 0..palette.len() {
+        let color = palette[format!("color{index}").as_str()].borrow().clone();
+        if !colors.iter().any(|col| col.to_hex() == color.to_hex()) {
+            colors.push(color);
+        }
     }
 
-    super::create_bg(&tmp_dir, &color);
+    colors
+}
+
+// Among the palette colors and shades of color0, the one whose lowest contrast with the message
+// bubbles is the highest
+fn most_contrasted(palette: &HashMap<String, Rc<RefCell<Color>>>, bubbles: &Bubbles) -> Color {
+    let bubbles = crate::telegram::colors::message_bubbles(bubbles).map(|bubble| bubble.eval(palette, &HashMap::new()));
+
+    let mut candidates = distinct_colors(palette);
+    for percentage in (10..100).step_by(10) {
+        candidates.push(palette["color0"].borrow().darker(percentage).borrow().clone());
+        candidates.push(palette["color0"].borrow().lighter(percentage).borrow().clone());
+    }
+
+    let lowest_contrast = |color: &Color| -> f32 {
+        bubbles.iter()
+            .map(|bubble| color.get_contrast(bubble))
+            .fold(f32::MAX, f32::min)
+    };
+
+    candidates.into_iter()
+        .max_by(|a, b| lowest_contrast(a).total_cmp(&lowest_contrast(b)))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::{fixtures, Mode};
+
+    fn color(strategy: &str, wallpaper: Option<&image::DynamicImage>) -> Color {
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+        plain_color(&palette, &strategy.parse().unwrap(), wallpaper, &Bubbles::default())
+    }
+
+    #[test]
+    fn average_counts_every_entry() {
+        let colors = fixtures::DARK.iter().map(|hex| Color::from_hex(hex).unwrap()).collect();
+        assert_eq!(color("average", None).to_hex(), average_color(colors).to_hex());
+    }
+
+    #[test]
+    fn role_is_the_palette_entry() {
+        assert_eq!(color("role:4", None).to_hex(), "7aa2f7ff");
+    }
+
+    #[test]
+    fn darken_darkens_color0() {
+        let darkened = color("darken:50", None);
+        assert_eq!(darkened.to_hex(), Color::from_hex("1a1b26").unwrap().darker(50).borrow().to_hex());
+        assert!(darkened.get_relative_luminance() < Color::from_hex("1a1b26").unwrap().get_relative_luminance());
+    }
+
+    #[test]
+    fn oklab_ignores_the_repeated_entries() {
+        // The bright colors of the fixture repeat the normal ones, only 10 colors are distinct
+        let distinct: Vec<&str> = fixtures::DARK.iter().enumerate()
+            .filter(|(index, _)| !(9..=14).contains(index))
+            .map(|(_, hex)| *hex)
+            .collect();
+        let expected = oklab_average_color(distinct.iter().map(|hex| Color::from_hex(hex).unwrap()).collect());
+        assert_eq!(color("oklab", None).to_hex(), expected.to_hex());
+    }
+
+    #[test]
+    fn dominant_is_the_most_common_wallpaper_color() {
+        // Three quarters of a dark blue with a red stripe
+        let wallpaper = image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, _| {
+            if x < 16 { image::Rgb([200, 30, 30]) } else { image::Rgb([20, 40, 90]) }
+        }));
+        assert_eq!(color("dominant", Some(&wallpaper)).to_hex(), "14285aff");
+    }
+
+    #[test]
+    fn contrast_stands_out_from_the_bubbles_more_than_the_average() {
+        // The average of this palette is a mid grey, the bubbles barely stand out from it
+        let palette = fixtures::prepared(&fixtures::BALANCED, Mode::Dark);
+        let average = plain_color(&palette, &PlainColor::Average, None, &Bubbles::default());
+        let channels = [average.u8_red(), average.u8_green(), average.u8_blue()];
+        assert!(channels.iter().max().unwrap() - channels.iter().min().unwrap() <= 1, "{}", average.to_hex());
+
+        let lines = crate::telegram::colors::compute_telegram_colors(&palette, &crate::telegram::colors::Mapping::default());
+        let key = |name: &str| lines.iter().rev().find_map(|line| line.color.clone().filter(|_| line.name == name)).unwrap();
+        let bubbles = [key("msgInBg"), key("msgOutBg")];
+        let lowest_contrast = |color: &Color| bubbles.iter().map(|bubble| color.get_contrast(&bubble.over(color))).fold(f32::MAX, f32::min);
+
+        let chosen = plain_color(&palette, &PlainColor::Contrast, None, &Bubbles::default());
+        assert!(lowest_contrast(&chosen) > lowest_contrast(&average), "{} against {}", lowest_contrast(&chosen), lowest_contrast(&average));
+    }
+
+    #[test]
+    fn hex_is_taken_as_is() {
+        assert_eq!(color("#123456", None).to_hex(), "123456ff");
+    }
 }
@@ -0,0 +1,174 @@
+use image::{DynamicImage, GenericImageView};
+use image::imageops::FilterType;
+
+// Chat area aspect ratios used for cropping (width / height)
+static PORTRAIT_RATIO: f32 = 9.0 / 16.0;
+static LANDSCAPE_RATIO: f32 = 16.0 / 9.0;
+
+// Size of the thumbnail used to look for the most detailed region
+static DETAIL_THUMBNAIL_SIZE: u32 = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Filter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl From<Filter> for FilterType {
+    fn from(filter: Filter) -> Self {
+        match filter {
+            Filter::Nearest => FilterType::Nearest,
+            Filter::Triangle => FilterType::Triangle,
+            Filter::CatmullRom => FilterType::CatmullRom,
+            Filter::Gaussian => FilterType::Gaussian,
+            Filter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Crop {
+    Portrait,
+    Landscape,
+}
+
+impl Crop {
+    fn ratio(&self) -> f32 {
+        match self {
+            Crop::Portrait => PORTRAIT_RATIO,
+            Crop::Landscape => LANDSCAPE_RATIO,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CropFocus {
+    // Keep the center of the image
+    Center,
+    // Keep the region with the most edges
+    Detail,
+}
+
+#[derive(Clone, Debug)]
+pub struct Resize {
+    // Longest side allowed, the aspect ratio is kept
+    pub max_dimension: Option<u32>,
+    // Box the image has to fit in, the aspect ratio is kept
+    pub target_size: Option<(u32, u32)>,
+    pub filter: Filter,
+    pub crop: Option<Crop>,
+    pub crop_focus: CropFocus,
+}
+
+impl Default for Resize {
+    fn default() -> Self {
+        Resize {
+            max_dimension: None,
+            target_size: None,
+            filter: Filter::Lanczos3,
+            crop: None,
+            crop_focus: CropFocus::Center,
+        }
+    }
+}
+
+// Crop then downscale the image, images are never upscaled
+pub fn resize_image(image: &mut DynamicImage, resize: &Resize) {
+    if let Some(crop) = resize.crop {
+        crop_image(image, crop, resize.crop_focus);
+    }
+
+    let mut bounds = (u32::MAX, u32::MAX);
+    if let Some(max_dimension) = resize.max_dimension {
+        bounds = (max_dimension, max_dimension);
+    }
+    if let Some((width, height)) = resize.target_size {
+        bounds = (bounds.0.min(width), bounds.1.min(height));
+    }
+
+    if image.width() > bounds.0 || image.height() > bounds.1 {
+        *image = image.resize(bounds.0, bounds.1, resize.filter.into());
+    }
+}
+
+fn crop_image(image: &mut DynamicImage, crop: Crop, focus: CropFocus) {
+    let (width, height) = image.dimensions();
+    let ratio = crop.ratio();
+
+    // Crop along a single axis to reach the wanted ratio
+    let (crop_width, crop_height) = if (width as f32 / height as f32) > ratio {
+        (((height as f32 * ratio).round() as u32).clamp(1, width), height)
+    }
+    else {
+        (width, ((width as f32 / ratio).round() as u32).clamp(1, height))
+    };
+
+    if (crop_width, crop_height) == (width, height) {
+        return;
+    }
+
+    let (x, y) = match focus {
+        CropFocus::Center => ((width - crop_width) / 2, (height - crop_height) / 2),
+        CropFocus::Detail => detail_offset(image, crop_width, crop_height),
+    };
+
+    *image = image.crop_imm(x, y, crop_width, crop_height);
+}
+
+// Find the crop window offset that keeps the most edge energy, computed on a small grayscale
+// thumbnail to stay fast on huge wallpapers
+fn detail_offset(image: &DynamicImage, crop_width: u32, crop_height: u32) -> (u32, u32) {
+    let (width, height) = image.dimensions();
+    let thumbnail = image.thumbnail(DETAIL_THUMBNAIL_SIZE, DETAIL_THUMBNAIL_SIZE).to_luma8();
+    let (thumb_width, thumb_height) = thumbnail.dimensions();
+
+    // Edge energy projected on each axis
+    let mut columns = vec![0.0_f32; thumb_width as usize];
+    let mut rows = vec![0.0_f32; thumb_height as usize];
+    for y in 0..thumb_height.saturating_sub(1) {
+        for x in 0..thumb_width.saturating_sub(1) {
+            let pixel = thumbnail.get_pixel(x, y)[0] as f32;
+            let energy = (thumbnail.get_pixel(x + 1, y)[0] as f32 - pixel).abs() +
+                         (thumbnail.get_pixel(x, y + 1)[0] as f32 - pixel).abs();
+            columns[x as usize] += energy;
+            rows[y as usize] += energy;
+        }
+    }
+
+    if crop_width < width {
+        let window = ((crop_width as f32 / width as f32) * thumb_width as f32).round() as usize;
+        let offset = best_window(&columns, window.max(1));
+        let x = (offset as f32 * width as f32 / thumb_width as f32).round() as u32;
+        (x.min(width - crop_width), 0)
+    }
+    else {
+        let window = ((crop_height as f32 / height as f32) * thumb_height as f32).round() as usize;
+        let offset = best_window(&rows, window.max(1));
+        let y = (offset as f32 * height as f32 / thumb_height as f32).round() as u32;
+        (0, y.min(height - crop_height))
+    }
+}
+
+// Start index of the window with the highest sum
+fn best_window(values: &[f32], window: usize) -> usize {
+    if window >= values.len() {
+        return 0;
+    }
+
+    let mut sum: f32 = values[..window].iter().sum();
+    let mut best = (sum, 0);
+    for start in 1..=(values.len() - window) {
+        sum += values[start + window - 1] - values[start - 1];
+        if sum > best.0 {
+            best = (sum, start);
+        }
+    }
+
+    best.1
+}
@@ -0,0 +1,145 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use image::DynamicImage;
+
+use crate::color::Color;
+
+// Perceived brightness of a pixel from 0 to 1
+fn luma(pixel: [f32; 3]) -> f32 {
+    (0.2126 * pixel[0] + 0.7152 * pixel[1] + 0.0722 * pixel[2]) / 255.0
+}
+
+fn mix(from: [f32; 3], to: [f32; 3], amount: f32) -> [f32; 3] {
+    [
+        from[0] + (to[0] - from[0]) * amount,
+        from[1] + (to[1] - from[1]) * amount,
+        from[2] + (to[2] - from[2]) * amount,
+    ]
+}
+
+fn to_rgb(color: &Color) -> [f32; 3] {
+    [color.u8_red() as f32, color.u8_green() as f32, color.u8_blue() as f32]
+}
+
+// The palette colors ordered by index without duplicates (wal8 palettes have every color twice)
+fn unique_colors(palette: &HashMap<String, Rc<RefCell<Color>>>) -> Vec<[f32; 3]> {
+    let mut colors: Vec<[f32; 3]> = Vec::new();
+
+    for index in 0..palette.len() {
+        let color = to_rgb(&palette[format!("color{index}").as_str()].borrow());
+        if !colors.contains(&color) {
+            colors.push(color);
+        }
+    }
+
+    colors
+}
+
+// Dark pixels go to color0 and light pixels to the accent
+pub fn duotone(image: &mut DynamicImage, palette: &HashMap<String, Rc<RefCell<Color>>>, accent: usize) {
+    let accent = palette.get(format!("color{accent}").as_str())
+        .unwrap_or_else(|| panic!("The duotone accent 'color{accent}' is not in the palette"));
+    let dark = to_rgb(&palette["color0"].borrow());
+    let light = to_rgb(&accent.borrow());
+
+    map_pixels(image, |pixel| mix(dark, light, luma(pixel)));
+}
+
+// Map the pixels brightness through the palette sorted by luminance
+pub fn gradient_map(image: &mut DynamicImage, palette: &HashMap<String, Rc<RefCell<Color>>>) {
+    let mut colors = unique_colors(palette);
+    colors.sort_by(|a, b| luma(*a).total_cmp(&luma(*b)));
+
+    if colors.len() < 2 {
+        map_pixels(image, |_| colors[0]);
+        return;
+    }
+
+    let steps = (colors.len() - 1) as f32;
+    map_pixels(image, |pixel| {
+        let position = luma(pixel) * steps;
+        let index = (position.floor() as usize).min(colors.len() - 2);
+        mix(colors[index], colors[index + 1], position - index as f32)
+    });
+}
+
+// Replace every pixel with the nearest palette color, optionally diffusing the error
+// (Floyd-Steinberg) to keep the gradients
+pub fn posterize(image: &mut DynamicImage, palette: &HashMap<String, Rc<RefCell<Color>>>, dither: bool) {
+    super::blur::to_rgb8(image);
+
+    let colors = unique_colors(palette);
+    let nearest = |pixel: [f32; 3]| -> [f32; 3] {
+        let distance = |color: &[f32; 3]| {
+            // Weighted to roughly follow the eye sensitivity
+            2.0 * (color[0] - pixel[0]).powi(2) + 4.0 * (color[1] - pixel[1]).powi(2) + 3.0 * (color[2] - pixel[2]).powi(2)
+        };
+        *colors.iter().min_by(|a, b| distance(a).total_cmp(&distance(b))).unwrap()
+    };
+
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+    let rgb_image = image.as_mut_rgb8().unwrap();
+
+    let mut pixels: Vec<[f32; 3]> = rgb_image.pixels().map(|pixel| pixel.0.map(|channel| channel as f32)).collect();
+    for y in 0..height {
+        for x in 0..width {
+            let old = pixels[y * width + x];
+            let new = nearest(old);
+            pixels[y * width + x] = new;
+
+            if dither {
+                let error = [old[0] - new[0], old[1] - new[1], old[2] - new[2]];
+                let mut spread = |x: usize, y: usize, weight: f32| {
+                    if x < width && y < height {
+                        for (channel, error) in pixels[y * width + x].iter_mut().zip(error) {
+                            *channel += error * weight;
+                        }
+                    }
+                };
+                spread(x + 1, y, 7.0 / 16.0);
+                if x > 0 {
+                    spread(x - 1, y + 1, 3.0 / 16.0);
+                }
+                spread(x, y + 1, 5.0 / 16.0);
+                spread(x + 1, y + 1, 1.0 / 16.0);
+            }
+        }
+    }
+
+    for (pixel, new) in rgb_image.pixels_mut().zip(pixels) {
+        pixel.0 = new.map(|channel| channel.round() as u8);
+    }
+}
+
+fn map_pixels(image: &mut DynamicImage, map: impl Fn([f32; 3]) -> [f32; 3]) {
+    super::blur::to_rgb8(image);
+
+    for pixel in image.as_mut_rgb8().unwrap().pixels_mut() {
+        let mapped = map(pixel.0.map(|channel| channel as f32));
+        pixel.0 = mapped.map(|channel| channel.round().clamp(0.0, 255.0) as u8);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::{fixtures, Mode};
+
+    #[test]
+    fn posterize_only_uses_palette_colors() {
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+        let colors: Vec<[u8; 3]> = unique_colors(&palette).iter().map(|color| color.map(|channel| channel as u8)).collect();
+
+        for dither in [false, true] {
+            let mut image = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 48, |x, y| image::Rgb([(x * 4) as u8, (y * 5) as u8, 255 - (x * 2) as u8])));
+            posterize(&mut image, &palette, dither);
+
+            for pixel in image.as_rgb8().unwrap().pixels() {
+                assert!(colors.contains(&pixel.0), "{:?} is not a palette color (dither: {dither})", pixel.0);
+            }
+        }
+    }
+}
@@ -1,15 +1,36 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs;
 use std::path;
+use std::rc::Rc;
+
+use crate::color::Color;
 
 // Try to fetch the background image from 'wal' file in wal cache
-pub fn use_wal_background(tmp_dir: &std::path::PathBuf, blur: f32, wal_colors_path: Option<&str>) {
+pub fn use_wal_background(tmp_dir: &std::path::Path, processing: &super::Processing, palette: &HashMap<String, Rc<RefCell<Color>>>, images: &mut super::ImageCache, wal_colors_path: Option<&str>) {
+    super::copy_background(tmp_dir, processing, palette, images, wallpaper_path(wal_colors_path).as_str());
+}
+
+// Get the wallpaper path stored in the 'wal' file of the wal cache
+pub fn wallpaper_path(wal_colors_path: Option<&str>) -> String {
 
     let filepath;
-    if wal_colors_path.is_none() || wal_colors_path.unwrap().is_empty() {
+    if let Some(wal_colors_path) = wal_colors_path.filter(|path| !path.is_empty()) {
+        let mut path = path::PathBuf::new();
+        path.push(wal_colors_path);
+        path.push("wal");
+
+        if !path.exists() {
+            panic!("Wal's 'wal' file not found in path: {}", path.parent().unwrap().display());
+        }
+
+        filepath = String::from(path.to_str().expect("Unexpected error, colors filename is not UTF-8"));
+    }
+    else {
         // Try to get wal from the default location
         if let Ok(xdg_dirs) = xdg::BaseDirectories::new() {
             filepath = String::from(xdg_dirs.find_cache_file("wal/wal")
-                                            .expect(format!("Wal's 'wal' file not found in default location: {}", xdg_dirs.get_cache_home().display()).as_str()).to_str()
+                                            .unwrap_or_else(|| panic!("Wal's 'wal' file not found in default location: {}", xdg_dirs.get_cache_home().display())).to_str()
                                             .expect("Unexpected error, colors filename is not UTF-8"));
         }
         else {
@@ -23,21 +44,9 @@ pub fn use_wal_background(tmp_dir: &std::path::PathBuf, blur: f32, wal_colors_pa
             filepath = String::from(path.to_str().expect("Unexpected error, colors filename is not UTF-8"));
         };
     }
-    else
-    {
-        let mut path = path::PathBuf::new();
-        path.push(wal_colors_path.unwrap());
-        path.push("/wal");
-
-        if !path.exists() {
-            panic!("Wal's 'wal' file not found in path: {}", path.parent().unwrap().display());
-        }
-
-        filepath = String::from(path.to_str().expect("Unexpected error, colors filename is not UTF-8"));
-    }
 
     let wal_str = fs::read_to_string(filepath.clone())
-        .expect(format!("Unexpected error, Couldn't read the file {}", filepath).as_str());
+        .unwrap_or_else(|_| panic!("Unexpected error, Couldn't read the file {}", filepath));
 
     let path = path::Path::new(wal_str.as_str());
 
@@ -50,6 +59,6 @@ pub fn use_wal_background(tmp_dir: &std::path::PathBuf, blur: f32, wal_colors_pa
         }
     }
 
-    super::copy_background(tmp_dir, blur, path.to_str().unwrap());
+    String::from(path.to_str().unwrap())
 }
 
@@ -0,0 +1,169 @@
+use std::fmt;
+use std::str::FromStr;
+
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Options;
+use crate::export::ExportFormat;
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Background {
+    // The wallpaper wal was run with
+    Wal,
+    // An image given with --background-path
+    Custom,
+    // A single color
+    Plain,
+    // A gradient between palette colors
+    Gradient,
+    // A geometric pattern in palette colors
+    Pattern,
+}
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Generate a Telegram Desktop theme from a wal palette")]
+pub struct Args {
+    /// Config file to use instead of $XDG_CONFIG_HOME/wal-telegram/config.toml
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
+    /// Profile of the config file to build, can be repeated [default: all of them]
+    #[arg(long = "profile", global = true, value_delimiter = ',')]
+    pub profiles: Vec<String>,
+
+    /// Overwrite existing themes without asking
+    #[arg(short, long, conflicts_with = "no_clobber")]
+    pub yes: bool,
+
+    /// Never overwrite existing themes
+    #[arg(long)]
+    pub no_clobber: bool,
+
+    /// Print what would be generated without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Keep the temporary directory and its intermediate files for debugging
+    #[arg(long, global = true)]
+    pub keep_temp: bool,
+
+    #[command(flatten)]
+    pub options: Options,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Render a PNG mockup of Telegram Desktop with the theme instead of writing the theme
+    Preview {
+        /// PNG file or directory to write the mockup to [default: $XDG_CACHE_HOME/wal-telegram/]
+        output: Option<String>,
+    },
+    /// Export the colors of the theme to another format instead of writing the theme
+    Export {
+        /// Format of the export
+        #[arg(long, value_enum, default_value_t = ExportFormat::Html)]
+        format: ExportFormat,
+
+        /// File or directory to write the export to [default: $XDG_CACHE_HOME/wal-telegram/]
+        output: Option<String>,
+    },
+    /// Print how the color of a Telegram key is derived from the palette
+    Explain {
+        /// Key to explain, like windowBgOver
+        key: String,
+    },
+    /// Compare the keys of the theme with the ones Telegram Desktop defines
+    Check {
+        /// colors.palette of Telegram Desktop to check against [default: the bundled one]
+        #[arg(long)]
+        canonical: Option<String>,
+    },
+    /// Show what a theme was made from, for themes built with --metadata
+    Info {
+        /// Theme file to read
+        theme: String,
+    },
+    /// Remove the temporary directories left behind by previous runs
+    Clean {
+        /// Only remove the directories older than this many minutes
+        #[arg(long, default_value_t = 60)]
+        older_than: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the settings after merging the defaults, the config file, the profiles and the command line
+    Show,
+}
+
+// A WIDTHxHEIGHT size
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Size(pub u32, pub u32);
+
+impl FromStr for Size {
+    type Err = String;
+
+    fn from_str(size: &str) -> Result<Self, Self::Err> {
+        let (width, height) = size.split_once('x')
+            .ok_or_else(|| format!("'{size}' is not formatted as WIDTHxHEIGHT"))?;
+
+        let width: u32 = width.trim().parse().map_err(|_| format!("Invalid width in '{size}'"))?;
+        let height: u32 = height.trim().parse().map_err(|_| format!("Invalid height in '{size}'"))?;
+
+        if width == 0 || height == 0 {
+            return Err(format!("'{size}' must not have a null dimension"));
+        }
+
+        Ok(Size(width, height))
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.0, self.1)
+    }
+}
+
+impl TryFrom<String> for Size {
+    type Error = String;
+
+    fn try_from(size: String) -> Result<Self, Self::Error> {
+        size.parse()
+    }
+}
+
+impl From<Size> for String {
+    fn from(size: Size) -> Self {
+        size.to_string()
+    }
+}
+
+impl Args {
+    pub fn overwrite(&self) -> crate::output::Overwrite {
+        if self.yes {
+            return crate::output::Overwrite::Yes;
+        }
+        if self.no_clobber {
+            return crate::output::Overwrite::NoClobber;
+        }
+        crate::output::Overwrite::Ask
+    }
+}
+
+// Parse a palette index given as '4' or 'color4'
+pub fn parse_palette_index(index: &str) -> Result<usize, String> {
+    index.trim().trim_start_matches("color").parse()
+        .map_err(|_| format!("'{index}' is not a palette index"))
+}
@@ -1,15 +1,25 @@
 use std::cell::RefCell;
-use std::collections::{HashMap, BTreeMap, VecDeque};
-use std::fmt::{self, format};
+use std::collections::{btree_map, BTreeMap, HashMap};
+use std::fmt;
 use std::rc::Rc;
 
 // Get the value of the color point fixed for relative luminance calculation
 fn fixed_for_rel_luma(value: f32) -> f32 {
     if value <= 0.04045 {
-        return value/12.92;
+        value/12.92
     }
     else {
-        return ((value+0.055)/1.055).powf(2.4);
+        ((value+0.055)/1.055).powf(2.4)
+    }
+}
+
+// Inverse of fixed_for_rel_luma, from linear light back to sRGB
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value*12.92
+    }
+    else {
+        1.055*value.powf(1.0/2.4) - 0.055
     }
 }
 
@@ -31,7 +41,7 @@ impl fmt::Display for Color {
 impl Color {
     // Constructors
     pub fn new() -> Color {
-        return Color {red: 0.0, green: 0.0, blue: 0.0, alpha: 1.0, contrasts: Vec::new()};
+        Color {red: 0.0, green: 0.0, blue: 0.0, alpha: 1.0, contrasts: Vec::new()}
     }
 
     pub fn from_rgb_int(r: u8, g: u8, b: u8) -> Option<Color> {
@@ -41,84 +51,94 @@ impl Color {
         col.green = g as f32 / 255.0;
         col.blue = b as f32 / 255.0;
 
-        return Some(col);
-    }
-
-    pub fn from_rgba_int(r: u8, g: u8, b: u8, a: u8) -> Option<Color> {
-        let mut col = Color::from_rgb_int(r, g, b);
-
-        if col.as_ref().is_some() {
-            col.as_mut().unwrap().alpha = a as f32 / 255.0;
-        }
-
-        return col;
+        Some(col)
     }
 
     pub fn from_rgb_float(r: f32, g: f32, b: f32) -> Option<Color> {
-        return Some(Color{red: r, green: g, blue: b, alpha: 1.0, contrasts: Vec::new()});
-    }
-
-    pub fn from_rgba_float(r: f32, g: f32, b: f32, a: f32) -> Option<Color> {
-        let mut col = Color::from_rgb_float(r, g, b);
-
-        if col.as_ref().is_some() {
-            col.as_mut().unwrap().alpha = a;
-        }
-
-        return col;
+        Some(Color{red: r, green: g, blue: b, alpha: 1.0, contrasts: Vec::new()})
     }
 
     pub fn from_hex(hex: &str) -> Option<Color> {
-        let mut col: Color = Color::new();
-
         let hex = hex.trim_start_matches('#');
-        if hex.len() == 6 {
-            if let Ok(color) = u32::from_str_radix(&hex[0..2], 16) {
-                col.red = color as f32 / 255.0;
-            }
-            if let Ok(color) = u32::from_str_radix(&hex[2..4], 16) {
-                col.green = color as f32 / 255.0;
-            }
-            if let Ok(color) = u32::from_str_radix(&hex[4..6], 16) {
-                col.blue = color as f32 / 255.0;
-            }
-            return Some(col);
+        // Checked before slicing, a multibyte character would make the slices panic
+        if !matches!(hex.len(), 6 | 8) || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
         }
-        else if hex.len() == 9 {
-            if let Ok(color) = u32::from_str_radix(&hex[0..2], 16) {
-                col.red = color as f32 / 255.0;
-            }
-            if let Ok(color) = u32::from_str_radix(&hex[2..4], 16) {
-                col.green = color as f32 / 255.0;
-            }
-            if let Ok(color) = u32::from_str_radix(&hex[4..6], 16) {
-                col.blue = color as f32 / 255.0;
-            }
-            if let Ok(color) = u32::from_str_radix(&hex[6..8], 16) {
-                col.alpha = color as f32 / 255.0;
-            }
-            return Some(col);
+
+        let channel = |index: usize| u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).unwrap() as f32 / 255.0;
+        let mut col: Color = Color::new();
+        col.red = channel(0);
+        col.green = channel(1);
+        col.blue = channel(2);
+        if hex.len() == 8 {
+            col.alpha = channel(3);
         }
 
-        return None;
+        Some(col)
+    }
+
+    // From the perceptual OKLab space (lightness, a, b), out of gamut colors are clamped
+    pub fn from_oklab(lab: [f32; 3]) -> Option<Color> {
+        // Computed in f64 to keep the precision of the coefficients
+        let [l, a, b] = lab.map(|value| value as f64);
+
+        let l_ = (l + 0.3963377774*a + 0.2158037573*b).powi(3);
+        let m_ = (l - 0.1055613458*a - 0.0638541728*b).powi(3);
+        let s_ = (l - 0.0894841775*a - 1.2914855480*b).powi(3);
+
+        let to_srgb = |value: f64| linear_to_srgb(value as f32).clamp(0.0, 1.0);
+        Color::from_rgb_float(
+            to_srgb(4.0767416621*l_ - 3.3077115913*m_ + 0.2309699292*s_),
+            to_srgb(-1.2684380046*l_ + 2.6097574011*m_ - 0.3413193965*s_),
+            to_srgb(-0.0041960863*l_ - 0.7034186147*m_ + 1.7076147010*s_),
+        )
     }
 
     // Getters
     pub fn u8_red(&self) -> u8 {
-        return (self.red * 255.0).round() as u8;
+        (self.red * 255.0).round() as u8
     }
     pub fn u8_green(&self) -> u8 {
-        return (self.green * 255.0).round() as u8;
+        (self.green * 255.0).round() as u8
     }
     pub fn u8_blue(&self) -> u8 {
-        return (self.blue * 255.0).round() as u8;
+        (self.blue * 255.0).round() as u8
     }
     pub fn u8_alpha(&self) -> u8 {
-        return (self.alpha * 255.0).round() as u8;
+        (self.alpha * 255.0).round() as u8
     }
 
     pub fn to_hex(&self) -> String {
-        return format!( "{:02x}{:02x}{:02x}{:02x}", self.u8_red(), self.u8_green(), self.u8_blue(), self.u8_alpha());
+        format!( "{:02x}{:02x}{:02x}{:02x}", self.u8_red(), self.u8_green(), self.u8_blue(), self.u8_alpha())
+    }
+
+    // The color as written in the theme, rounded to 8 bits per channel
+    pub fn written(&self) -> Color {
+        Color::from_hex(&self.to_hex()).unwrap()
+    }
+
+    // Get the color in the perceptual OKLab space as [lightness, a, b], alpha is ignored
+    pub fn to_oklab(&self) -> [f32; 3] {
+        // Computed in f64 to keep the precision of the coefficients
+        let red = fixed_for_rel_luma(self.red) as f64;
+        let green = fixed_for_rel_luma(self.green) as f64;
+        let blue = fixed_for_rel_luma(self.blue) as f64;
+
+        let l = (0.4122214708*red + 0.5363325363*green + 0.0514459929*blue).cbrt();
+        let m = (0.2119034982*red + 0.6806995451*green + 0.1073969566*blue).cbrt();
+        let s = (0.0883024619*red + 0.2817188376*green + 0.6299787005*blue).cbrt();
+
+        [
+            (0.2104542553*l + 0.7936177850*m - 0.0040720468*s) as f32,
+            (1.9779984951*l - 2.4285922050*m + 0.4505937099*s) as f32,
+            (0.0259040371*l + 0.7827717662*m - 0.8086757660*s) as f32,
+        ]
+    }
+
+    // Lightness, chroma and hue in degrees of the OKLab color
+    pub fn to_oklch(&self) -> [f32; 3] {
+        let [lightness, a, b] = self.to_oklab();
+        [lightness, a.hypot(b), b.atan2(a).to_degrees().rem_euclid(360.0)]
     }
 
     // Setters
@@ -128,9 +148,9 @@ impl Color {
 
     // Compute the relative luminance of the color
     pub fn get_relative_luminance(&self) -> f32 {
-        return (0.2126*fixed_for_rel_luma(self.red) +
+        (0.2126*fixed_for_rel_luma(self.red) +
                 0.7152*fixed_for_rel_luma(self.green) +
-                0.0722*fixed_for_rel_luma(self.blue)) * self.alpha;
+                0.0722*fixed_for_rel_luma(self.blue)) * self.alpha
     }
 
     // Get the contrast between 'self' color and 'other' color
@@ -139,10 +159,10 @@ impl Color {
         let other_luma = other.get_relative_luminance();
 
         if our_luma > other_luma {
-            return (our_luma + 0.05) / (other_luma + 0.05);
+            (our_luma + 0.05) / (other_luma + 0.05)
         }
         else {
-            return (other_luma + 0.05) / (our_luma + 0.05);
+            (other_luma + 0.05) / (our_luma + 0.05)
         }
     }
 
@@ -151,7 +171,7 @@ impl Color {
         let perc = (percentage as f32) / 100.0;
 
         let darker_color_point = |c: f32| {
-            return (c - c * perc).clamp(0.0, 1.0);
+            (c - c * perc).clamp(0.0, 1.0)
         };
 
         let mut col = self.clone();
@@ -159,14 +179,14 @@ impl Color {
         col.green = darker_color_point(col.green);
         col.blue = darker_color_point(col.blue);
 
-        return Rc::new(RefCell::new(col));
+        Rc::new(RefCell::new(col))
     }
 
     pub fn lighter(&self, percentage: u8) -> Rc<RefCell<Color>> {
         let perc = (percentage as f32) / 100.0;
 
         let lighter_color_point = |c: f32| {
-            return (c + (1.0 - c) * perc).clamp(0.0, 1.0);
+            (c + (1.0 - c) * perc).clamp(0.0, 1.0)
         };
 
         let mut col = self.clone();
@@ -174,7 +194,7 @@ impl Color {
         col.green = lighter_color_point(col.green);
         col.blue = lighter_color_point(col.blue);
 
-        return Rc::new(RefCell::new(col));
+        Rc::new(RefCell::new(col))
     }
 
     pub fn alpha(&self, percentage: u8) -> Rc<RefCell<Color>> {
@@ -183,45 +203,129 @@ impl Color {
         let mut col = self.clone();
         col.alpha = perc;
 
-        return Rc::new(RefCell::new(col));
+        Rc::new(RefCell::new(col))
+    }
+
+    // Black or white, whichever contrasts the most with the color, the side of the lightness the
+    // color isn't on
+    pub fn other_extreme(&self) -> Color {
+        let (black, white) = (Color::from_rgb_int(0, 0, 0).unwrap(), Color::from_rgb_int(255, 255, 255).unwrap());
+        if self.get_contrast(&black) >= self.get_contrast(&white) {
+            return black;
+        }
+        white
+    }
+
+    // The color moved towards the other one by amount, from 0 to 1, in linear light where the
+    // luminance changes steadily
+    pub fn mix_linear(&self, other: &Color, amount: f32) -> Color {
+        let mix = |from: f32, to: f32| {
+            let from = fixed_for_rel_luma(from);
+            linear_to_srgb(from + (fixed_for_rel_luma(to) - from) * amount)
+        };
+        Color::from_rgb_float(mix(self.red, other.red), mix(self.green, other.green), mix(self.blue, other.blue)).unwrap()
+    }
+
+    // The opaque color seen when this color is drawn over the background
+    pub fn over(&self, background: &Color) -> Color {
+        let blend = |top: f32, bottom: f32| top * self.alpha + bottom * (1.0 - self.alpha);
+        Color::from_rgb_float(
+            blend(self.red, background.red), blend(self.green, background.green), blend(self.blue, background.blue)
+        ).unwrap()
+    }
+
+    // Get the least changed lighter or darker version of the color with at least `target` contrast
+    // against `background`, or the most contrasted one if the target can't be reached
+    // The contrast is the one of the colors as written in the theme, rounded to 8 bits per channel
+    pub fn with_min_contrast(&self, background: &Color, target: f32) -> Rc<RefCell<Color>> {
+        let background = background.written();
+        let reaches = |color: &Color| color.written().get_contrast(&background) >= target;
+        if reaches(self) {
+            return Rc::new(RefCell::new(self.clone()));
+        }
+
+        for percentage in 1..=100 {
+            for candidate in [self.lighter(percentage), self.darker(percentage)] {
+                if reaches(&candidate.borrow()) {
+                    return candidate;
+                }
+            }
+        }
+
+        let (lightest, darkest) = (self.lighter(100), self.darker(100));
+        if lightest.borrow().get_contrast(&background) >= darkest.borrow().get_contrast(&background) {
+            return lightest;
+        }
+        darkest
     }
 
     // Get the indexth contrast of the color starting from 0
     // Contrast called on a darkened or lightened will return the original
     // color's contrast
     pub fn contrast(&self, index: usize) -> Rc<RefCell<Color>> {
-        let c = self.contrasts.get(index);
-        if c.is_some() {
-            return c.unwrap().clone();
+        if let Some(c) = self.contrasts.get(index) {
+            c.clone()
         }
         else {
             eprintln!("WARNING! Contrast index out of scope, returning the same color");
-            return Rc::new(RefCell::new(self.clone()));
+            Rc::new(RefCell::new(self.clone()))
         }
     }
 }
 
-pub fn compute_contrasts(colors: &HashMap<String, Rc<RefCell<Color>>>) {
-    for index in 0..(colors.len() - 1) {
-        let mut contrasts_vec: VecDeque<Rc<RefCell<Color>>> = VecDeque::new();
-
-        let mut ordered_contrasts: BTreeMap<u32, Rc<RefCell<Color>>> = BTreeMap::new();
-
-        for (name_sec, col_sec) in colors.iter() {
-            if name_sec != format!("color{index}").as_str() {
-                let c = colors[format!("color{index}").as_str()].borrow().get_contrast(&(*col_sec.borrow()));
-                ordered_contrasts.insert((c * 1000000.0).floor() as u32, col_sec.clone());
+// The colors from the most to the least contrasted with color, colors with the same contrast only
+// appear once. Of those the lowest hex is kept, then the first given, so the order never depends
+// on the hash map
+fn by_contrast(color: &Color, others: Vec<Rc<RefCell<Color>>>) -> Vec<Rc<RefCell<Color>>> {
+    let mut ordered_contrasts: BTreeMap<u32, (String, Rc<RefCell<Color>>)> = BTreeMap::new();
+    for other in others {
+        let contrast = (color.get_contrast(&other.borrow()) * 1000000.0).floor() as u32;
+        let hex = other.borrow().to_hex();
+        match ordered_contrasts.entry(contrast) {
+            btree_map::Entry::Vacant(entry) => { entry.insert((hex, other)); }
+            btree_map::Entry::Occupied(mut entry) => {
+                if hex < entry.get().0 {
+                    entry.insert((hex, other));
+                }
             }
         }
+    }
 
-        for (_, ord_col) in ordered_contrasts {
-            contrasts_vec.push_front(ord_col);
-        }
+    ordered_contrasts.into_values().rev().map(|(_, other)| other).collect()
+}
+
+// Palette entries in the index order
+fn ordered_palette(colors: &HashMap<String, Rc<RefCell<Color>>>) -> Vec<(String, Rc<RefCell<Color>>)> {
+    (0..colors.len())
+        .map(|index| format!("color{index}"))
+        .map(|name| { let color = colors[name.as_str()].clone(); (name, color) })
+        .collect()
+}
+
+pub fn compute_contrasts(colors: &HashMap<String, Rc<RefCell<Color>>>) {
+    // Walk the colors by index so that equal contrasts always end up in the same order
+    let ordered = ordered_palette(colors);
 
-        colors[format!("color{index}").as_str()].borrow_mut().set_contrasts(Vec::from(contrasts_vec));
+    for (name, color) in &ordered {
+        let others = ordered.iter()
+            .filter(|(name_sec, _)| name_sec != name)
+            .map(|(_, col_sec)| col_sec.clone())
+            .collect();
+        let contrasts = by_contrast(&color.borrow(), others);
+
+        color.borrow_mut().set_contrasts(contrasts);
     }
 }
 
+// A color outside of the palette, with the palette entries as its contrasts
+pub fn with_palette_contrasts(color: &Color, colors: &HashMap<String, Rc<RefCell<Color>>>) -> Color {
+    let others = ordered_palette(colors).into_iter().map(|(_, other)| other).collect();
+
+    let mut ret_col = color.clone();
+    ret_col.set_contrasts(by_contrast(color, others));
+    ret_col
+}
+
 pub fn average_color(colors: Vec<Color>) -> Color {
     let mut ret_col: Color = Color::new();
 
@@ -237,5 +341,49 @@ pub fn average_color(colors: Vec<Color>) -> Color {
     ret_col.blue /= colors.len() as f32;
     ret_col.alpha /= colors.len() as f32;
 
-    return ret_col;
+    ret_col
+}
+
+// Average in OKLab, which stays closer to what the eye sees than the RGB average
+pub fn oklab_average_color(colors: Vec<Color>) -> Color {
+    let mut lab = [0.0; 3];
+
+    for color in &colors {
+        for (sum, value) in lab.iter_mut().zip(color.to_oklab()) {
+            *sum += value;
+        }
+    }
+
+    Color::from_oklab(lab.map(|sum| sum / colors.len() as f32)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_hex_reads_rgb_and_rgba() {
+        assert_eq!(Color::from_hex("#1a1b26").unwrap().to_hex(), "1a1b26ff");
+        assert_eq!(Color::from_hex("1a1b2680").unwrap().to_hex(), "1a1b2680");
+    }
+
+    #[test]
+    fn contrasts_skip_the_repeated_colors() {
+        let palette = crate::palette::fixtures::prepared(&crate::palette::fixtures::DARK, crate::palette::Mode::Dark);
+        let color0 = palette["color0"].borrow();
+        let contrasts: Vec<Rc<RefCell<Color>>> = (0..color0.contrasts.len()).map(|index| color0.contrast(index)).collect();
+
+        // The bright colors 9 to 14 repeat 1 to 6
+        assert_eq!(contrasts.len(), 9);
+        let hexes: Vec<String> = contrasts.iter().map(|color| color.borrow().to_hex()).collect();
+        assert!(hexes.iter().enumerate().all(|(index, hex)| !hexes[..index].contains(hex)));
+        assert!(contrasts.windows(2).all(|pair| color0.get_contrast(&pair[0].borrow()) > color0.get_contrast(&pair[1].borrow())));
+    }
+
+    #[test]
+    fn from_hex_rejects_invalid_colors() {
+        for hex in ["#zzzzzz", "#12345g", "#12345", "#1234567", "", "#", "#ééé", "#1a1b2é"] {
+            assert!(Color::from_hex(hex).is_none(), "'{hex}' should be rejected");
+        }
+    }
 }
@@ -0,0 +1,599 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::background::effects::Effect;
+use crate::background::gradient::Gradient;
+use crate::background::pattern::Pattern;
+use crate::background::plain::PlainColor;
+use crate::background::resize;
+use crate::cli::{Background, Size};
+use crate::color::Color;
+use crate::hooks::Hook;
+use crate::palette::{Mode, Palette};
+use crate::palette::adjust::Adjustment;
+use crate::telegram::colors::{Accent, BubbleColor, Bubbles, Overlays, StatusColor, StatusColors};
+
+pub static CONFIG_FILENAME: &str = "config.toml";
+
+// Settings shared by the config file and the command line, every key of the config file is the
+// long name of its flag
+#[derive(clap::Args, Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Options {
+    /// Theme file or directory to write the theme to, can be repeated [default: $XDG_CACHE_HOME/wal-telegram/]
+    #[arg(short, long)]
+    pub output: Option<Vec<String>>,
+
+    /// Where the palette comes from [default: wal]
+    #[arg(long, value_enum)]
+    pub palette: Option<Palette>,
+
+    /// wal cache directory or custom palette file
+    #[arg(long)]
+    pub palette_path: Option<String>,
+
+    /// Whether the theme background is dark or light, the palette roles are swapped to match [default: dark]
+    #[arg(long, value_enum)]
+    pub mode: Option<Mode>,
+
+    /// Where the background comes from [default: plain]
+    #[arg(long, value_enum)]
+    pub background: Option<Background>,
+
+    /// wal cache directory or custom background image
+    #[arg(long)]
+    pub background_path: Option<String>,
+
+    /// Gaussian blur strength of image backgrounds, 0 disables it [default: 0.1 without --effect]
+    #[arg(long)]
+    pub blur: Option<f32>,
+
+    /// Effect applied to image backgrounds after the blur, as NAME:VALUE, can be repeated to chain
+    /// them [gaussian:BLUR, box:BLUR, stack:BLUR, pixelate:SIZE, desaturate:STRENGTH,
+    /// darken:STRENGTH, lighten:STRENGTH, duotone:PALETTE_INDEX, gradient-map, posterize,
+    /// posterize:dither]
+    #[arg(long = "effect")]
+    pub effects: Option<Vec<Effect>>,
+
+    /// Longest side of the background in pixels
+    #[arg(long)]
+    pub max_dimension: Option<u32>,
+
+    /// Box the background has to fit in, as WIDTHxHEIGHT
+    #[arg(long)]
+    pub target_size: Option<Size>,
+
+    /// Filter used to downscale the background [default: lanczos3]
+    #[arg(long, value_enum)]
+    pub resize_filter: Option<resize::Filter>,
+
+    /// Crop the background to a chat aspect ratio
+    #[arg(long, value_enum)]
+    pub crop: Option<resize::Crop>,
+
+    /// Part of the background kept when cropping [default: center]
+    #[arg(long, value_enum)]
+    pub crop_focus: Option<resize::CropFocus>,
+
+    /// Maximum theme size in KiB, the background quality is lowered to fit
+    #[arg(long)]
+    pub max_theme_size: Option<u64>,
+
+    /// How the plain background color is chosen [average, role:INDEX, darken:PERCENTAGE, oklab,
+    /// dominant, contrast, #HEX] [default: average]
+    #[arg(long)]
+    pub plain_color: Option<PlainColor>,
+
+    /// Kind of gradient for the gradient background [default: linear]
+    #[arg(long, value_enum)]
+    pub gradient: Option<Gradient>,
+
+    /// Direction of linear gradients in degrees, 0 is left to right and 90 top to bottom [default: 90]
+    #[arg(long)]
+    pub gradient_angle: Option<f32>,
+
+    /// Kind of pattern for the pattern background [default: dots]
+    #[arg(long, value_enum)]
+    pub pattern: Option<Pattern>,
+
+    /// Spacing of the pattern in pixels [default: 24]
+    #[arg(long)]
+    pub pattern_size: Option<u32>,
+
+    /// Palette indexes used by generated backgrounds, eg. '0,4' or 'color0,color4', the first one is
+    /// the pattern background [default: 0,8 and 0,4,8,5 for four-point gradients]
+    #[arg(long, value_delimiter = ',', value_parser = crate::cli::parse_palette_index)]
+    pub background_colors: Option<Vec<usize>>,
+
+    /// Resolution of generated backgrounds, as WIDTHxHEIGHT [default: 1920x1080]
+    #[arg(long)]
+    pub resolution: Option<Size>,
+
+    /// Minimum contrast ratio of the main text colors against their background
+    #[arg(long)]
+    pub text_contrast: Option<f32>,
+
+    /// Minimum contrast ratio of the secondary text colors (dates, statuses...) against their background
+    #[arg(long)]
+    pub secondary_contrast: Option<f32>,
+
+    /// Palette index of the outgoing message bubbles, 'auto' picks the one standing out the most
+    /// from the background while keeping the text readable [default: 8]
+    #[arg(long)]
+    pub out_bubble: Option<BubbleColor>,
+
+    /// Opacity of the message bubbles over the background, in percent [default: 100]
+    #[arg(long)]
+    pub bubble_opacity: Option<u8>,
+
+    /// Pick the color and opacity of the service messages and the scroll down button, drawn
+    /// straight over the background, so that they stay readable over it [default: false]
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub adaptive_overlays: Option<bool>,
+
+    /// Accent of the active, link, selection and badge keys: saturated, contrast (with color0),
+    /// hue:DEGREES (OKLab), a palette index or a #RRGGBB color [default: picked by the mapping]
+    #[arg(long)]
+    pub accent: Option<Accent>,
+
+    /// Color of a status, instead of the palette entry with the closest hue, given as
+    /// STATUS=#RRGGBB with error, success, warning or info, can be repeated
+    #[arg(long = "status-color")]
+    pub status_colors: Option<Vec<StatusColor>>,
+
+    /// Adjustment of the whole palette in OKLab, as NAME:VALUE, can be repeated to chain them
+    /// [saturation:PERCENT, brightness:PERCENT, contrast:PERCENT, temperature:-100..100, hue:DEGREES]
+    #[arg(long = "adjust", allow_hyphen_values = true)]
+    pub adjustments: Option<Vec<Adjustment>>,
+
+    /// Leave the background and foreground colors (color0, color7, color8 and color15) out of the
+    /// palette adjustments [default: false]
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub keep_roles: Option<bool>,
+
+    /// High contrast preset: every text reaches 7:1 (WCAG AAA) against its background without
+    /// transparency, moving mid-tone backgrounds if needed, borders stand out and states don't
+    /// differ by their hue only [default: false]
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub high_contrast: Option<bool>,
+
+    /// Number of previous themes kept as timestamped backups next to the output [default: 0]
+    #[arg(long)]
+    pub backups: Option<usize>,
+
+    /// Add a wal-telegram.json entry to the theme recording what it was made from, the theme
+    /// changes at each run unless SOURCE_DATE_EPOCH is set [default: false]
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub metadata: Option<bool>,
+
+    /// Run after each theme is written, 'telegram' opens it in Telegram Desktop,
+    /// 'telegram-flatpak' in its Flatpak, anything else is a shell command given the theme path as
+    /// last argument, can be repeated
+    #[arg(long = "hook")]
+    pub hooks: Option<Vec<Hook>>,
+
+    // Telegram keys set to a fixed color, only in the config file
+    #[arg(skip)]
+    pub overrides: Option<BTreeMap<String, String>>,
+
+    // Named variants of the settings above, only in the config file
+    #[arg(skip)]
+    pub profiles: Option<BTreeMap<String, Options>>,
+}
+
+// Keep the values of `self` that `other` doesn't set
+macro_rules! merge_options {
+    ($self:ident, $other:ident, $($field:ident),*) => {
+        Options {
+            $($field: $other.$field.or($self.$field),)*
+        }
+    };
+}
+
+impl Options {
+    pub fn merge(self, other: Options) -> Options {
+        merge_options!(self, other,
+            output, palette, palette_path, mode, background, background_path, blur, effects,
+            max_dimension, target_size, resize_filter, crop, crop_focus, max_theme_size,
+            plain_color, gradient, gradient_angle, pattern, pattern_size, background_colors,
+            resolution, text_contrast, secondary_contrast, out_bubble, bubble_opacity, adaptive_overlays, accent,
+            status_colors, adjustments, keep_roles, high_contrast, backups, metadata, hooks, overrides, profiles)
+    }
+}
+
+// The effective settings, after applying the defaults
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    #[serde(skip)]
+    pub profile: Option<String>,
+    pub output: Vec<String>,
+    pub palette: Palette,
+    pub palette_path: Option<String>,
+    pub mode: Mode,
+    pub background: Background,
+    pub background_path: Option<String>,
+    pub blur: f32,
+    pub effects: Vec<Effect>,
+    pub max_dimension: Option<u32>,
+    pub target_size: Option<Size>,
+    pub resize_filter: resize::Filter,
+    pub crop: Option<resize::Crop>,
+    pub crop_focus: resize::CropFocus,
+    pub max_theme_size: Option<u64>,
+    pub plain_color: PlainColor,
+    pub gradient: Gradient,
+    pub gradient_angle: f32,
+    pub pattern: Pattern,
+    pub pattern_size: u32,
+    pub background_colors: Vec<usize>,
+    pub resolution: Size,
+    pub text_contrast: Option<f32>,
+    pub secondary_contrast: Option<f32>,
+    pub out_bubble: BubbleColor,
+    pub bubble_opacity: u8,
+    pub adaptive_overlays: bool,
+    pub accent: Option<Accent>,
+    pub status_colors: Vec<StatusColor>,
+    pub adjustments: Vec<Adjustment>,
+    pub keep_roles: bool,
+    pub high_contrast: bool,
+    pub backups: usize,
+    pub metadata: bool,
+    pub hooks: Vec<Hook>,
+    pub overrides: BTreeMap<String, String>,
+}
+
+impl TryFrom<Options> for Settings {
+    type Error = String;
+
+    // Apply the defaults and validate the values, errors name the offending key
+    fn try_from(options: Options) -> Result<Self, Self::Error> {
+        let effects = options.effects.unwrap_or_default();
+        // Only blur by default when no effect is asked for
+        let blur = options.blur.unwrap_or(if effects.is_empty() { 0.1 } else { 0.0 });
+        let background = options.background.unwrap_or(Background::Plain);
+        let gradient = options.gradient.unwrap_or(Gradient::Linear);
+
+        let background_colors = options.background_colors.unwrap_or_else(|| {
+            match (background, gradient) {
+                (Background::Gradient, Gradient::FourPoint) => vec![0, 4, 8, 5],
+                _ => vec![0, 8],
+            }
+        });
+
+        let settings = Settings {
+            profile: None,
+            output: options.output.unwrap_or_default(),
+            palette: options.palette.unwrap_or(Palette::Wal),
+            palette_path: options.palette_path,
+            mode: options.mode.unwrap_or(Mode::Dark),
+            background,
+            background_path: options.background_path,
+            blur,
+            effects,
+            max_dimension: options.max_dimension,
+            target_size: options.target_size,
+            resize_filter: options.resize_filter.unwrap_or(resize::Filter::Lanczos3),
+            crop: options.crop,
+            crop_focus: options.crop_focus.unwrap_or(resize::CropFocus::Center),
+            max_theme_size: options.max_theme_size,
+            plain_color: options.plain_color.unwrap_or(PlainColor::Average),
+            gradient,
+            gradient_angle: options.gradient_angle.unwrap_or(90.0),
+            pattern: options.pattern.unwrap_or(Pattern::Dots),
+            pattern_size: options.pattern_size.unwrap_or(24),
+            background_colors,
+            resolution: options.resolution.unwrap_or(Size(1920, 1080)),
+            text_contrast: options.text_contrast,
+            secondary_contrast: options.secondary_contrast,
+            out_bubble: options.out_bubble.unwrap_or(BubbleColor::Role(Bubbles::default().out_role)),
+            bubble_opacity: options.bubble_opacity.unwrap_or(100),
+            adaptive_overlays: options.adaptive_overlays.unwrap_or(false),
+            accent: options.accent,
+            status_colors: options.status_colors.unwrap_or_default(),
+            adjustments: options.adjustments.unwrap_or_default(),
+            keep_roles: options.keep_roles.unwrap_or(false),
+            high_contrast: options.high_contrast.unwrap_or(false),
+            backups: options.backups.unwrap_or(0),
+            metadata: options.metadata.unwrap_or(false),
+            hooks: options.hooks.unwrap_or_default(),
+            overrides: options.overrides.unwrap_or_default(),
+        };
+
+        settings.validate()?;
+        Ok(settings)
+    }
+}
+
+impl Settings {
+    fn validate(&self) -> Result<(), String> {
+        if self.blur < 0.0 {
+            return Err(format!("Invalid value for 'blur': {} can not be negative", self.blur));
+        }
+        if self.max_dimension == Some(0) {
+            return Err(String::from("Invalid value for 'max-dimension': it can not be 0"));
+        }
+        if let Some(size) = self.max_theme_size.filter(|size| size.checked_mul(1024).is_none()) {
+            return Err(format!("Invalid value for 'max-theme-size': {size} KiB is too large"));
+        }
+        if self.pattern_size < 2 {
+            return Err(format!("Invalid value for 'pattern-size': {} is smaller than 2", self.pattern_size));
+        }
+        if self.background_colors.is_empty() {
+            return Err(String::from("Invalid value for 'background-colors': at least one palette index is needed"));
+        }
+        if self.background == Background::Custom && self.background_path.is_none() {
+            return Err(String::from("Missing 'background-path': a custom background needs a path"));
+        }
+        if self.palette == Palette::Custom && self.palette_path.is_none() {
+            return Err(String::from("Missing 'palette-path': a custom palette needs a path"));
+        }
+        for (key, contrast) in [("text-contrast", self.text_contrast), ("secondary-contrast", self.secondary_contrast)] {
+            if let Some(contrast) = contrast {
+                if !(1.0..=21.0).contains(&contrast) {
+                    return Err(format!("Invalid value for '{key}': {contrast} is not a contrast ratio between 1 and 21"));
+                }
+            }
+        }
+        if self.bubble_opacity > 100 {
+            return Err(format!("Invalid value for 'bubble-opacity': {} is not a percentage", self.bubble_opacity));
+        }
+        for (key, hex) in &self.overrides {
+            if !hex.starts_with('#') || Color::from_hex(hex).is_none() {
+                return Err(format!("Invalid value for 'overrides.{key}': '{hex}' is not a #RRGGBB or #RRGGBBAA color"));
+            }
+        }
+
+        Ok(())
+    }
+
+    // The palette indices can only be checked once the palette is read, custom palettes can have
+    // any number of entries. Only the settings used by the background mode are checked
+    pub fn validate_palette(&self, palette_size: usize) -> Result<(), String> {
+        let check = |key: &str, index: usize| -> Result<(), String> {
+            if index >= palette_size {
+                return Err(format!("Invalid value for '{key}': color{index} is not in the palette, which has {palette_size} entries"));
+            }
+            Ok(())
+        };
+
+        if let Some(Accent::Role(index)) = self.accent {
+            check("accent", index)?;
+        }
+        if let BubbleColor::Role(index) = self.out_bubble {
+            check("out-bubble", index)?;
+        }
+        match self.background {
+            Background::Wal | Background::Custom => {
+                for effect in &self.effects {
+                    if let Effect::Duotone(index) = effect {
+                        check("effects", *index)?;
+                    }
+                }
+            }
+            Background::Plain => {
+                if let PlainColor::Role(index) = self.plain_color {
+                    check("plain-color", index)?;
+                }
+            }
+            Background::Gradient | Background::Pattern => {
+                for index in &self.background_colors {
+                    check("background-colors", *index)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn background_processing(&self) -> crate::background::Processing {
+        let mut effects = Vec::new();
+        if self.blur > 0.0 {
+            effects.push(Effect::Gaussian(self.blur));
+        }
+        effects.extend(self.effects.iter().cloned());
+
+        crate::background::Processing {
+            resize: resize::Resize {
+                max_dimension: self.max_dimension,
+                target_size: self.target_size.map(|size| (size.0, size.1)),
+                filter: self.resize_filter,
+                crop: self.crop,
+                crop_focus: self.crop_focus,
+            },
+            effects,
+            max_theme_size: self.max_theme_size.and_then(|size| size.checked_mul(1024)),
+        }
+    }
+
+    pub fn mapping(&self) -> crate::telegram::colors::Mapping {
+        // The high contrast preset only ever raises the targets
+        let raise = |target: Option<f32>| match self.high_contrast {
+            true => Some(target.unwrap_or(0.0).max(7.0)),
+            false => target,
+        };
+
+        crate::telegram::colors::Mapping {
+            overrides: self.overrides.iter()
+                .map(|(key, hex)| (key.clone(), Color::from_hex(hex).unwrap()))
+                .collect(),
+            text_contrast: raise(self.text_contrast),
+            secondary_contrast: raise(self.secondary_contrast),
+            bubbles: Bubbles {
+                // The automatic color is picked once the background is known
+                out_role: match self.out_bubble {
+                    BubbleColor::Role(index) => index,
+                    BubbleColor::Auto => Bubbles::default().out_role,
+                },
+                opacity: self.bubble_opacity,
+            },
+            // Picked once the background is known when adaptive
+            overlays: Overlays::default(),
+            // Picked once the palette is known
+            accent: None,
+            status: StatusColors::default(),
+            high_contrast: self.high_contrast,
+        }
+    }
+
+    // Wallpaper the plain colors are picked from, either background-path if it is an image or the
+    // wal one
+    pub fn wallpaper(&self) -> String {
+        if let Some(background_path) = self.background_path.as_deref() {
+            if path::Path::new(background_path).is_file() {
+                return String::from(background_path);
+            }
+        }
+
+        crate::background::wal::wallpaper_path(self.background_path.as_deref())
+    }
+}
+
+// Default config file location, if it exists
+fn default_config_path() -> Option<path::PathBuf> {
+    if let Ok(xdg_dirs) = xdg::BaseDirectories::with_prefix("wal-telegram") {
+        return xdg_dirs.find_config_file(CONFIG_FILENAME);
+    }
+
+    None
+}
+
+// Read the config file, a missing default config file is not an error
+pub fn read_config(config_path: Option<&str>) -> Result<Options, String> {
+    let path = match config_path {
+        Some(config_path) => path::PathBuf::from(config_path),
+        None => match default_config_path() {
+            Some(path) => path,
+            None => return Ok(Options::default()),
+        },
+    };
+
+    let contents = fs::read_to_string(&path)
+        .map_err(|err| format!("Couldn't read the config file '{}': {}", path.display(), err))?;
+
+    toml::from_str(&contents)
+        .map_err(|err| format!("Invalid config file '{}': {}", path.display(), err))
+}
+
+// Merge the config file with the command line options, the command line wins
+// - profiles are the profiles to build, all of them when empty
+pub fn load(config_path: Option<&str>, cli_options: Options, profiles: &[String]) -> Result<Vec<Settings>, String> {
+    let mut options = read_config(config_path)?;
+    let available = options.profiles.take().unwrap_or_default();
+
+    if available.is_empty() {
+        if let Some(name) = profiles.first() {
+            return Err(format!("Unknown profile '{name}', the config file has no profiles"));
+        }
+        return Ok(vec![Settings::try_from(options.merge(cli_options))?]);
+    }
+
+    let mut names: Vec<&String> = Vec::new();
+    for name in profiles {
+        if !available.contains_key(name) {
+            let known: Vec<&str> = available.keys().map(|name| name.as_str()).collect();
+            return Err(format!("Unknown profile '{name}', expected one of: {}", known.join(", ")));
+        }
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    if names.is_empty() {
+        names = available.keys().collect();
+    }
+
+    let mut all_settings = Vec::new();
+    for name in names {
+        let profile = available[name].clone();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("Invalid profile name 'profiles.{name}': only letters, digits, '-' and '_' are allowed"));
+        }
+        if profile.profiles.is_some() {
+            return Err(format!("Invalid key 'profiles.{name}.profiles': profiles can not be nested"));
+        }
+
+        // Config file, then the profile, then the command line
+        let mut settings = Settings::try_from(options.clone().merge(profile).merge(cli_options.clone()))
+            .map_err(|err| format!("In profile '{name}': {err}"))?;
+        settings.profile = Some(name.clone());
+        all_settings.push(settings);
+    }
+
+    check_outputs(&all_settings)?;
+    Ok(all_settings)
+}
+
+// Profiles must not overwrite each other, directories are fine since the default theme filename
+// contains the profile name
+fn check_outputs(all_settings: &[Settings]) -> Result<(), String> {
+    let mut outputs: BTreeMap<&str, &str> = BTreeMap::new();
+    for settings in all_settings {
+        let profile = settings.profile.as_deref().unwrap_or_default();
+        for output in settings.output.iter().filter(|output| !path::Path::new(output).is_dir()) {
+            if let Some(other) = outputs.insert(output, profile) {
+                return Err(format!("Profiles '{other}' and '{profile}' both write to '{output}', give them their own 'output'"));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(options: Options) -> Settings {
+        Settings::try_from(options).unwrap()
+    }
+
+    #[test]
+    fn palette_indices_in_range_are_accepted() {
+        let settings = settings(Options {
+            accent: Some(Accent::Role(15)),
+            effects: Some(vec![Effect::Duotone(15)]),
+            ..Options::default()
+        });
+        assert!(settings.validate_palette(16).is_ok());
+    }
+
+    #[test]
+    fn palette_indices_out_of_range_name_the_key() {
+        let cases = [
+            (Options { accent: Some(Accent::Role(16)), ..Options::default() }, "accent"),
+            (Options { out_bubble: Some(BubbleColor::Role(16)), ..Options::default() }, "out-bubble"),
+            (Options { background: Some(Background::Wal), effects: Some(vec![Effect::Duotone(16)]), ..Options::default() }, "effects"),
+            (Options { background: Some(Background::Plain), plain_color: Some(PlainColor::Role(16)), ..Options::default() }, "plain-color"),
+            (Options { background: Some(Background::Gradient), background_colors: Some(vec![0, 16]), ..Options::default() }, "background-colors"),
+        ];
+        for (options, key) in cases {
+            let err = settings(options).validate_palette(16).unwrap_err();
+            assert!(err.contains(&format!("'{key}'")), "{err}");
+        }
+    }
+
+    #[test]
+    fn overflowing_theme_sizes_name_the_key() {
+        let err = Settings::try_from(Options { max_theme_size: Some(u64::MAX / 1024 + 1), ..Options::default() }).unwrap_err();
+        assert!(err.contains("'max-theme-size'"), "{err}");
+
+        let settings = settings(Options { max_theme_size: Some(u64::MAX / 1024), ..Options::default() });
+        assert_eq!(settings.background_processing().max_theme_size, Some(u64::MAX / 1024 * 1024));
+    }
+
+    #[test]
+    fn unused_settings_are_not_checked() {
+        // The plain color is only used by the plain background, the effects by the wallpapers
+        let settings = settings(Options {
+            background: Some(Background::Gradient),
+            plain_color: Some(PlainColor::Role(16)),
+            effects: Some(vec![Effect::Duotone(16)]),
+            ..Options::default()
+        });
+        assert!(settings.validate_palette(16).is_ok());
+    }
+}
@@ -0,0 +1,162 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::rc::Rc;
+
+use crate::color::Color;
+use crate::config::Settings;
+use crate::metadata::serde_name;
+use crate::output::{Overwrite, Writing};
+use crate::telegram::colors::ThemeLine;
+
+// Keys shown to get an idea of the theme
+static SAMPLE_KEYS: [&str; 12] = [
+    "windowBg",
+    "windowFg",
+    "windowSubTextFg",
+    "windowBgActive",
+    "activeButtonBg",
+    "dialogsBgActive",
+    "msgInBg",
+    "msgOutBg",
+    "historyTextInFg",
+    "historyTextOutFg",
+    "historyComposeAreaBg",
+    "msgServiceBg",
+];
+
+// Colored block when printing to a terminal that allows it
+pub fn swatch(color: &Color) -> String {
+    if !std::io::stdout().is_terminal() || std::env::var_os("NO_COLOR").is_some() {
+        return String::new();
+    }
+
+    format!("\x1b[48;2;{};{};{}m    \x1b[0m ", color.u8_red(), color.u8_green(), color.u8_blue())
+}
+
+// Whether a file can be created in the directory, the permission bits don't tell about ACLs,
+// read-only mounts or root, so a probe file is created and removed
+fn can_create_in(dir: &std::path::Path) -> bool {
+    let probe = dir.join(format!(".wal-telegram-probe-{}", rand::random::<u32>()));
+    match std::fs::OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            std::fs::remove_file(&probe).ok();
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+// What would happen to an output
+fn output_status(output_path: &std::path::Path, writing: &Writing) -> String {
+    // The closest existing directory is the one the theme would be created in
+    let writable = output_path.ancestors().skip(1)
+        .find(|dir| dir.is_dir())
+        .is_some_and(can_create_in);
+    if !writable {
+        return String::from("not writable, the theme couldn't be written");
+    }
+
+    if !output_path.exists() {
+        return String::from("would be created");
+    }
+
+    String::from(match writing.overwrite {
+        Overwrite::Yes => "exists, would be overwritten",
+        Overwrite::NoClobber => "exists, would be kept",
+        Overwrite::Ask => "exists, would ask before overwriting it",
+    })
+}
+
+// Summary of the theme that would be written
+// - tmp_dir holds the processed background
+// - colors are the Telegram keys with their colors
+pub fn print_summary(settings: &Settings, palette: &HashMap<String, Rc<RefCell<Color>>>, tmp_dir: &std::path::Path, colors: &[ThemeLine], output_paths: &[std::path::PathBuf], writing: &Writing) {
+    // Same information as the one embedded with --metadata
+    let metadata = crate::metadata::Metadata::new(settings, palette);
+
+    if let Some(profile) = &settings.profile {
+        println!("Profile: {profile}");
+    }
+
+    println!("Palette: {} '{}' ({} mode)", serde_name(&settings.palette), metadata.palette.path, serde_name(&settings.mode));
+    if !settings.adjustments.is_empty() {
+        let adjustments: Vec<String> = settings.adjustments.iter().map(|adjustment| adjustment.to_string()).collect();
+        println!("  Adjusted: {}", adjustments.join(", "));
+    }
+    for index in 0..palette.len() {
+        let color = palette[format!("color{index}").as_str()].borrow();
+        let end = if index % 4 == 3 || index + 1 == palette.len() { "\n" } else { "  " };
+        print!("  {:>7} {}#{}{}", format!("color{index}"), swatch(&color), &color.to_hex()[..6], end);
+    }
+
+    match &metadata.background.path {
+        Some(path) => println!("Background: {} '{}'", serde_name(&settings.background), path),
+        None => println!("Background: {}", serde_name(&settings.background)),
+    }
+    if !metadata.effects.is_empty() {
+        let effects: Vec<String> = metadata.effects.iter().map(|effect| effect.to_string()).collect();
+        println!("  Effects: {}", effects.join(", "));
+    }
+    let background = tmp_dir.join(crate::background::BG_FILENAME);
+    if let (Ok((width, height)), Ok(file)) = (image::image_dimensions(&background), background.metadata()) {
+        println!("  {}x{}, {} KiB", width, height, file.len().div_ceil(1024));
+    }
+    if let Ok(image) = image::open(&background) {
+        let analysis = crate::background::analysis::analyse(&image);
+        println!("  Average luminance {:.2}, busiest region deviation {:.2}", analysis.luminance, analysis.busyness);
+    }
+
+    println!("Sample colors:");
+    for key in SAMPLE_KEYS {
+        if let Some(ThemeLine { color: Some(color), .. }) = colors.iter().find(|line| line.name == key) {
+            println!("  {:<21} {}#{}", key, swatch(color), color.to_hex());
+        }
+    }
+
+    let audit = crate::telegram::colors::audit_text_contrast(colors);
+    if let Some((fg, bg, ratio)) = audit.first() {
+        let under_aaa = audit.iter().filter(|(_, _, ratio)| *ratio < 7.0).count();
+        println!("Text contrast: lowest {ratio:.2}:1 ({fg} on {bg}), {under_aaa} of {} pairs under 7:1", audit.len());
+    }
+
+    println!("Output:");
+    for output_path in output_paths {
+        println!("  '{}' {}", output_path.display(), output_status(output_path, writing));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    const WRITING: Writing = Writing { overwrite: Overwrite::Yes, backups: 0 };
+
+    #[test]
+    fn writable_directories_are_reported_as_such() {
+        let tmp_dir = crate::tmp_dir::create_tmp_dir(false);
+        assert_eq!(output_status(&tmp_dir.join("theme"), &WRITING), "would be created");
+        assert_eq!(output_status(&tmp_dir.join("missing/theme"), &WRITING), "would be created");
+
+        // The probe is not left behind
+        assert_eq!(std::fs::read_dir(&*tmp_dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn read_only_directories_are_not_writable() {
+        let tmp_dir = crate::tmp_dir::create_tmp_dir(false);
+        let read_only = tmp_dir.join("read-only");
+        std::fs::create_dir(&read_only).unwrap();
+        std::fs::set_permissions(&read_only, std::fs::Permissions::from_mode(0o555)).unwrap();
+
+        // Root writes past the permissions, the status must match what writing does
+        let status = output_status(&read_only.join("theme"), &WRITING);
+        let written = std::fs::write(read_only.join("theme"), b"theme").is_ok();
+        assert_eq!(status == "would be created", written, "{status}");
+        std::fs::set_permissions(&read_only, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        // Nobody can create files in /proc
+        assert_eq!(output_status(std::path::Path::new("/proc/theme"), &WRITING), "not writable, the theme couldn't be written");
+    }
+}
@@ -0,0 +1,135 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::color::Color;
+use crate::dry_run::swatch;
+use crate::telegram::colors::ThemeLine;
+use crate::telegram::expr::Expr;
+
+// Recomputes the colors of a key step by step
+struct Explainer<'a> {
+    palette: &'a HashMap<String, Rc<RefCell<Color>>>,
+    lines: &'a [ThemeLine],
+    // Keys whose final color is being explained, a background moved away from its text refers to
+    // the text which refers back to it
+    explaining: RefCell<Vec<String>>,
+}
+
+impl Explainer<'_> {
+    // Name of a palette entry, the contrasts of a color are the palette entries themselves
+    fn palette_name(&self, color: &Rc<RefCell<Color>>) -> String {
+        (0..self.palette.len())
+            .map(|index| format!("color{index}"))
+            .find(|name| Rc::ptr_eq(&self.palette[name.as_str()], color))
+            .unwrap_or_else(|| String::from("?"))
+    }
+
+    // Last definition of a key before the line at index
+    fn definition(&self, name: &str, index: usize) -> Option<(usize, &ThemeLine)> {
+        self.lines[..index].iter().enumerate().rev()
+            .find(|(_, line)| line.name == name && line.expr.is_some())
+    }
+
+    // Compute the color of the expression as it was when the line at index was evaluated, the
+    // steps are added to out from the result down to the palette
+    fn explain(&self, expr: &Expr, index: usize, depth: usize, out: &mut Vec<(usize, Color, String)>) -> Color {
+        // Filled once the color is known
+        let position = out.len();
+        out.push((depth, Color::new(), String::new()));
+
+        let (color, step) = match expr {
+            Expr::Palette(palette_index) => {
+                let color = expr.eval(self.palette, &HashMap::new());
+                (color, format!("palette.color{palette_index}"))
+            }
+            Expr::Hex(hex) => (expr.eval(self.palette, &HashMap::new()), format!("#{hex}")),
+            Expr::Override(_) => (expr.eval(self.palette, &HashMap::new()), String::from("overridden in the config")),
+            Expr::Key(name) if index == self.lines.len() && self.explaining.borrow().contains(name) => {
+                let (_, line) = self.definition(name, index).unwrap();
+                (line.color.clone().unwrap(), format!("{name}, as explained above"))
+            }
+            Expr::Key(name) => {
+                let (definition_index, line) = self.definition(name, index)
+                    .unwrap_or_else(|| panic!("Could not find '{name}' in the previously defined elements"));
+                let mut definition = line.expr.as_ref().unwrap();
+                // The contrast targets are only applied once every key is computed, the keys
+                // referenced by the other keys don't have them yet
+                while let (Expr::MinContrast(inner, _, _) | Expr::Flip(inner, _, _, _) | Expr::Opaque(inner, _), true) = (definition, index < self.lines.len()) {
+                    definition = inner;
+                }
+
+                if index == self.lines.len() {
+                    self.explaining.borrow_mut().push(name.clone());
+                }
+                let color = self.explain(definition, definition_index, depth + 1, out);
+                if index == self.lines.len() {
+                    self.explaining.borrow_mut().pop();
+                }
+                let untested = if line.untested { " [UNTESTED]" } else { "" };
+                (color, format!("{name}{untested}"))
+            }
+            Expr::Darker(inner, percentage) => {
+                let color = self.explain(inner, index, depth + 1, out).darker(*percentage).borrow().clone();
+                (color, format!("darker({percentage})"))
+            }
+            Expr::Lighter(inner, percentage) => {
+                let color = self.explain(inner, index, depth + 1, out).lighter(*percentage).borrow().clone();
+                (color, format!("lighter({percentage})"))
+            }
+            Expr::Alpha(inner, percentage) => {
+                let color = self.explain(inner, index, depth + 1, out).alpha(*percentage).borrow().clone();
+                (color, format!("alpha({percentage})"))
+            }
+            Expr::Contrast(inner, rank) => {
+                let base = self.explain(inner, index, depth + 1, out);
+                let chosen = base.contrast(*rank);
+                let ratio = base.get_contrast(&chosen.borrow());
+                let color = chosen.borrow().clone();
+                (color, format!("contrast({rank}) picks {}, {ratio:.2}:1 with the color it was called on", self.palette_name(&chosen)))
+            }
+            Expr::MinContrast(inner, background, target) => {
+                let color = self.explain(inner, index, depth + 1, out);
+                let background_color = self.explain(&Expr::Key(background.clone()), self.lines.len(), depth + 1, out);
+                let adjusted = color.with_min_contrast(&background_color, *target).borrow().clone();
+                let ratio = adjusted.get_contrast(&background_color);
+                (adjusted, format!("min_contrast({target}) against {background}, {ratio:.2}:1"))
+            }
+            Expr::Flip(inner, text, extreme, target) => {
+                let color = self.explain(inner, index, depth + 1, out);
+                let extreme_color = Expr::Hex(extreme.clone()).eval(self.palette, &HashMap::new());
+                let adjusted = color.with_min_contrast(&extreme_color, *target).borrow().clone();
+                let ratio = adjusted.get_contrast(&extreme_color);
+                (adjusted, format!("flip({target}) for {text} to switch to #{extreme}, {ratio:.2}:1"))
+            }
+            Expr::Opaque(inner, background) => {
+                let color = self.explain(inner, index, depth + 1, out);
+                let background_color = self.explain(&Expr::Key(background.clone()), self.lines.len(), depth + 1, out);
+                (color.over(&background_color), format!("made opaque over {background}"))
+            }
+        };
+
+        out[position] = (depth, color.clone(), step);
+        color
+    }
+}
+
+// Print how the color of a key is derived from the palette
+pub fn print_explanation(key: &str, palette: &HashMap<String, Rc<RefCell<Color>>>, lines: &[ThemeLine]) -> Result<(), String> {
+    let explainer = Explainer { palette, lines, explaining: RefCell::new(Vec::new()) };
+    let (_, line) = explainer.definition(key, lines.len())
+        .ok_or_else(|| format!("'{key}' is not a key of the theme"))?;
+
+    println!("{} = {}", key, line.expr.as_ref().unwrap());
+    if line.untested {
+        println!("  The effect of this key on Telegram hasn't been checked");
+    }
+
+    let mut steps = Vec::new();
+    explainer.explain(&Expr::Key(String::from(key)), lines.len(), 0, &mut steps);
+    for (depth, color, step) in steps {
+        println!("  {}{}#{}  {}", "  ".repeat(depth), swatch(&color), color.to_hex(), step);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use crate::color::Color;
+use crate::telegram::colors::ThemeLine;
+
+pub static ATTHEME_EXTENSION: &str = "attheme";
+
+// Telegram Android keys with the desktop key filling the same role, so that the overrides and the
+// contrast targets of the desktop theme apply to Android as well
+static KEYS: [(&str, &str); 109] = [
+    // Windows and lists
+    ("windowBackgroundWhite", "windowBg"),
+    ("windowBackgroundGray", "windowBgRipple"),
+    ("windowBackgroundWhiteBlackText", "windowFg"),
+    ("windowBackgroundWhiteGrayText", "windowSubTextFg"),
+    ("windowBackgroundWhiteGrayText2", "windowSubTextFg"),
+    ("windowBackgroundWhiteHintText", "placeholderFg"),
+    ("windowBackgroundWhiteBlueText", "windowActiveTextFg"),
+    ("windowBackgroundWhiteLinkText", "windowActiveTextFg"),
+    ("windowBackgroundWhiteValueText", "windowActiveTextFg"),
+    ("windowBackgroundWhiteRedText", "boxTextFgError"),
+    ("windowBackgroundWhiteGreenText", "boxTextFgGood"),
+    ("windowBackgroundWhiteInputField", "inputBorderFg"),
+    ("windowBackgroundWhiteInputFieldActivated", "activeLineFg"),
+    ("divider", "shadowFg"),
+    ("listSelectorSDK21", "windowBgOver"),
+
+    // Action bar
+    ("actionBarDefault", "topBarBg"),
+    ("actionBarDefaultIcon", "menuIconFg"),
+    ("actionBarDefaultTitle", "dialogsNameFg"),
+    ("actionBarDefaultSubtitle", "windowSubTextFg"),
+    ("actionBarDefaultSelector", "windowBgOver"),
+    ("actionBarDefaultSearch", "windowFg"),
+    ("actionBarDefaultSearchPlaceholder", "placeholderFg"),
+    ("actionBarActionModeDefault", "topBarBg"),
+    ("actionBarActionModeDefaultIcon", "menuIconFg"),
+
+    // Avatars
+    ("avatar_text", "historyPeerUserpicFg"),
+    ("avatar_backgroundRed", "historyPeer1UserpicBg"),
+    ("avatar_backgroundGreen", "historyPeer2UserpicBg"),
+    ("avatar_backgroundOrange", "historyPeer8UserpicBg"),
+    ("avatar_backgroundBlue", "historyPeer4UserpicBg"),
+    ("avatar_backgroundViolet", "historyPeer5UserpicBg"),
+    ("avatar_backgroundCyan", "historyPeer7UserpicBg"),
+    ("avatar_backgroundPink", "historyPeer6UserpicBg"),
+    ("avatar_backgroundSaved", "historyPeerSavedMessagesBg"),
+
+    // Chat list
+    ("chats_name", "dialogsNameFg"),
+    ("chats_secretName", "dialogsNameFg"),
+    ("chats_message", "dialogsTextFg"),
+    ("chats_nameMessage", "dialogsTextFgService"),
+    ("chats_attachMessage", "dialogsTextFgService"),
+    ("chats_actionMessage", "dialogsTextFgService"),
+    ("chats_draft", "dialogsDraftFg"),
+    ("chats_date", "dialogsDateFg"),
+    ("chats_pinnedIcon", "dialogsDateFg"),
+    ("chats_sentCheck", "dialogsSentIconFg"),
+    ("chats_sentClock", "dialogsSendingIconFg"),
+    ("chats_unreadCounter", "dialogsUnreadBg"),
+    ("chats_unreadCounterMuted", "dialogsUnreadBgMuted"),
+    ("chats_unreadCounterText", "dialogsUnreadFg"),
+    ("chats_onlineCircle", "dialogsOnlineBadgeFg"),
+    ("chats_actionBackground", "activeButtonBg"),
+    ("chats_actionIcon", "activeButtonFg"),
+    ("chats_menuBackground", "windowBg"),
+    ("chats_menuItemText", "windowFg"),
+    ("chats_menuItemIcon", "menuIconFg"),
+    ("chats_menuTopShadow", "shadowFg"),
+
+    // Messages
+    ("chat_inBubble", "msgInBg"),
+    ("chat_inBubbleSelected", "msgInBgSelected"),
+    ("chat_inBubbleShadow", "msgInShadow"),
+    ("chat_outBubble", "msgOutBg"),
+    ("chat_outBubbleSelected", "msgOutBgSelected"),
+    ("chat_outBubbleShadow", "msgOutShadow"),
+    ("chat_messageTextIn", "historyTextInFg"),
+    ("chat_messageTextOut", "historyTextOutFg"),
+    ("chat_messageLinkIn", "historyLinkInFg"),
+    ("chat_messageLinkOut", "historyLinkOutFg"),
+    ("chat_inTimeText", "msgInDateFg"),
+    ("chat_outTimeText", "msgOutDateFg"),
+    ("chat_inViews", "msgInDateFg"),
+    ("chat_outViews", "msgOutDateFg"),
+    ("chat_outSentCheck", "historyOutIconFg"),
+    ("chat_outSentCheckRead", "historyOutIconFg"),
+    ("chat_outSentClock", "msgOutDateFg"),
+    ("chat_inReplyLine", "msgInReplyBarColor"),
+    ("chat_outReplyLine", "msgOutReplyBarColor"),
+    ("chat_inReplyNameText", "msgInServiceFg"),
+    ("chat_outReplyNameText", "msgOutServiceFg"),
+    ("chat_inReplyMessageText", "historyTextInFg"),
+    ("chat_outReplyMessageText", "historyTextOutFg"),
+    ("chat_inForwardedNameText", "msgInServiceFg"),
+    ("chat_outForwardedNameText", "msgOutServiceFg"),
+    ("chat_selectedBackground", "msgSelectOverlay"),
+    ("chat_serviceBackground", "msgServiceBg"),
+    ("chat_serviceText", "msgServiceFg"),
+    ("chat_serviceLink", "msgServiceFg"),
+    ("chat_unreadMessagesStartBackground", "historyUnreadBarBg"),
+    ("chat_unreadMessagesStartText", "historyUnreadBarFg"),
+    ("chat_wallpaper", "windowBg"),
+
+    // Compose area
+    ("chat_messagePanelBackground", "historyComposeAreaBg"),
+    ("chat_messagePanelText", "historyComposeAreaFg"),
+    ("chat_messagePanelHint", "placeholderFg"),
+    ("chat_messagePanelIcons", "historyComposeIconFg"),
+    ("chat_messagePanelSend", "historySendIconFg"),
+    ("chat_goDownButton", "historyToDownBg"),
+    ("chat_goDownButtonIcon", "historyToDownFg"),
+    ("chat_emojiPanelBackground", "emojiPanBg"),
+    ("chat_emojiPanelIcon", "emojiIconFg"),
+    ("chat_emojiPanelIconSelected", "emojiIconFgActive"),
+
+    // Dialogs and controls
+    ("dialogBackground", "boxBg"),
+    ("dialogTextBlack", "boxTextFg"),
+    ("dialogTextGray", "windowSubTextFg"),
+    ("dialogTextLink", "windowActiveTextFg"),
+    ("dialogButton", "lightButtonFg"),
+    ("checkbox", "activeButtonBg"),
+    ("checkboxCheck", "activeButtonFg"),
+    ("radioBackground", "checkboxFg"),
+    ("radioBackgroundChecked", "activeButtonBg"),
+    ("switchTrack", "sliderBgInactive"),
+    ("switchTrackChecked", "sliderBgActive"),
+    ("profile_actionBackground", "activeButtonBg"),
+    ("profile_actionIcon", "activeButtonFg"),
+];
+
+// Android colors are ARGB packed in a signed 32 bits integer
+fn android_color(color: &Color) -> i32 {
+    u32::from_be_bytes([color.u8_alpha(), color.u8_red(), color.u8_green(), color.u8_blue()]) as i32
+}
+
+// The key=value lines, followed by the background between the WPS and WPE markers
+// - lines are the computed desktop keys
+// - background is the processed background as JPEG, if any
+pub fn render(lines: &[ThemeLine], background: Option<&[u8]>) -> Vec<u8> {
+    // The last definition of a key is the one Telegram uses
+    let colors: HashMap<&str, &Color> = lines.iter()
+        .filter_map(|line| line.color.as_ref().map(|color| (line.name.as_str(), color)))
+        .collect();
+    let mut contents: Vec<u8> = Vec::new();
+
+    for (name, desktop_key) in KEYS {
+        let color = colors.get(desktop_key)
+            .unwrap_or_else(|| panic!("Unexpected error, the key '{desktop_key}' is not in the theme"));
+        contents.extend_from_slice(format!("{}={}\n", name, android_color(color)).as_bytes());
+    }
+
+    if let Some(background) = background {
+        contents.extend_from_slice(b"WPS\n");
+        contents.extend_from_slice(background);
+        contents.extend_from_slice(b"\nWPE\n");
+    }
+
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::{fixtures, Mode};
+    use crate::telegram::colors::{compute_telegram_colors, Mapping};
+
+    // The colors and the wallpaper of an .attheme
+    fn parse(contents: &[u8]) -> (HashMap<String, Color>, Option<Vec<u8>>) {
+        let (colors, wallpaper) = match contents.windows(4).position(|window| window == b"WPS\n") {
+            Some(start) => {
+                let wallpaper = contents[start + 4..].strip_suffix(b"\nWPE\n").expect("The wallpaper is not closed");
+                (&contents[..start], Some(wallpaper.to_vec()))
+            }
+            None => (contents, None),
+        };
+
+        let colors = std::str::from_utf8(colors).unwrap().lines()
+            .map(|line| {
+                let (name, value) = line.split_once('=').unwrap_or_else(|| panic!("'{line}' is not 'key=value'"));
+                let [alpha, red, green, blue] = (value.parse::<i32>().unwrap() as u32).to_be_bytes();
+                (String::from(name), Color::from_hex(&format!("{red:02x}{green:02x}{blue:02x}{alpha:02x}")).unwrap())
+            })
+            .collect();
+        (colors, wallpaper)
+    }
+
+    fn desktop_color(lines: &[ThemeLine], name: &str) -> String {
+        lines.iter().rev().find_map(|line| line.color.as_ref().filter(|_| line.name == name)).unwrap().to_hex()
+    }
+
+    #[test]
+    fn colors_and_wallpaper_round_trip() {
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+        let lines = compute_telegram_colors(&palette, &Mapping::default());
+        let wallpaper = b"\xff\xd8 not really a JPEG \nWPE\n but still kept \xff\xd9";
+
+        let (colors, parsed_wallpaper) = parse(&render(&lines, Some(wallpaper)));
+        assert_eq!(colors.len(), KEYS.len());
+        for (name, desktop_key) in KEYS {
+            assert_eq!(colors[name].to_hex(), desktop_color(&lines, desktop_key), "{name}");
+        }
+        assert_eq!(parsed_wallpaper.as_deref(), Some(&wallpaper[..]));
+    }
+
+    #[test]
+    fn overrides_and_contrast_targets_reach_android() {
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+        let mut mapping = Mapping { text_contrast: Some(7.0), ..Mapping::default() };
+        mapping.overrides.insert(String::from("windowBg"), Color::from_hex("101820").unwrap());
+        let lines = compute_telegram_colors(&palette, &mapping);
+
+        let (colors, _) = parse(&render(&lines, None));
+        assert_eq!(colors["windowBackgroundWhite"].to_hex(), "101820ff");
+        let text = &colors["chat_messageTextIn"];
+        assert!(text.over(&colors["chat_inBubble"]).get_contrast(&colors["chat_inBubble"]) >= 7.0 - 0.05);
+    }
+}
@@ -0,0 +1,46 @@
+use crate::color::Color;
+
+pub static CSS_EXTENSION: &str = "css";
+
+// Custom properties on :root, like --wal-telegram-window-bg
+pub fn render(roles: &[(&str, Color)]) -> String {
+    let mut css = String::from(":root {\n");
+    for (role, color) in roles {
+        css.push_str(format!("  --wal-telegram-{}: #{};\n", role, color.to_hex()).as_str());
+    }
+    css.push_str("}\n");
+
+    css
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::{fixtures, Mode};
+
+    #[test]
+    fn the_roles_parse_back() {
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+        let lines = crate::telegram::colors::compute_telegram_colors(&palette, &crate::telegram::colors::Mapping::default());
+        let roles = super::super::role_colors(&lines);
+        let rendered = render(&roles);
+
+        let body = rendered.strip_prefix(":root {\n").and_then(|body| body.strip_suffix("}\n"))
+            .unwrap_or_else(|| panic!("'{rendered}' is not a :root rule"));
+        let properties: Vec<(&str, &str)> = body.lines()
+            .map(|line| {
+                let property = line.trim().strip_prefix("--wal-telegram-").and_then(|line| line.strip_suffix(';'));
+                property.and_then(|property| property.split_once(": #")).unwrap_or_else(|| panic!("'{line}' is not '--wal-telegram-role: #rrggbbaa;'"))
+            })
+            .collect();
+
+        assert_eq!(properties.len(), super::super::ROLES.len());
+        for ((role, _), (expected_role, expected)) in super::super::ROLES.iter().zip(&roles) {
+            let found: Vec<&&str> = properties.iter().filter(|(name, _)| name == role).map(|(_, value)| value).collect();
+            assert_eq!(found.len(), 1, "{role}");
+            assert_eq!(role, expected_role);
+            assert_eq!(found[0].len(), 8, "{role}");
+            assert_eq!(Color::from_hex(found[0]).unwrap().to_hex(), expected.to_hex(), "{role}");
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::color::Color;
+use crate::telegram::colors::{ThemeLine, SECONDARY_TEXT_PAIRS, TEXT_PAIRS};
+
+pub static HTML_EXTENSION: &str = "html";
+
+// Inline so that the page works offline, the checkerboard shows the transparency of the swatches
+static STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; background: #f4f4f4; color: #222; }
+h2 { margin-top: 2em; border-bottom: 1px solid #ccc; }
+table { border-collapse: collapse; width: 100%; }
+td { padding: 4px 8px; border-bottom: 1px solid #e4e4e4; vertical-align: middle; }
+tr:target { background: #fff3b0; }
+code { font-family: monospace; }
+.swatch { display: inline-block; width: 48px; height: 24px; border: 1px solid #888; vertical-align: middle;
+    background-color: #fff;
+    background-image: linear-gradient(45deg, #ccc 25%, transparent 25%, transparent 75%, #ccc 75%),
+        linear-gradient(45deg, #ccc 25%, transparent 25%, transparent 75%, #ccc 75%);
+    background-size: 12px 12px; background-position: 0 0, 6px 6px; }
+.swatch span { display: block; width: 100%; height: 100%; }
+.untested { font-size: 0.8em; padding: 1px 4px; border-radius: 3px; background: #e0a800; color: #000; }
+.fail { color: #b00020; }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn swatch(color: &Color) -> String {
+    format!(
+        "<span class=\"swatch\"><span style=\"background: rgba({}, {}, {}, {:.3})\"></span></span>",
+        color.u8_red(), color.u8_green(), color.u8_blue(), color.alpha
+    )
+}
+
+// fg/bg pairs with their contrast target
+type Targets<'a> = [(&'a [(&'a str, &'a str)], Option<f32>); 2];
+
+// Contrast of a text key with its background and the target it should reach
+fn contrast_cell(key: &str, colors: &HashMap<&str, &Color>, targets: Targets) -> String {
+    for (pairs, target) in targets {
+        let Some((fg, bg)) = pairs.iter().find(|(fg, _)| *fg == key) else { continue };
+        let (Some(fg_color), Some(bg_color)) = (colors.get(fg), colors.get(bg)) else { continue };
+
+        // Translucent text is seen over its background
+        let ratio = fg_color.over(bg_color).get_contrast(bg_color);
+        let class = if target.is_some_and(|target| ratio < target) { " class=\"fail\"" } else { "" };
+        return format!("<span{class}>{ratio:.2}:1</span> on <a href=\"#{bg}\"><code>{bg}</code></a>");
+    }
+
+    String::new()
+}
+
+// A single page listing the keys of the theme by section
+// - text_contrast and secondary_contrast are the targets the text colors were pushed to, if any
+pub fn render(palette: &HashMap<String, Rc<RefCell<Color>>>, lines: &[ThemeLine], title: &str, text_contrast: Option<f32>, secondary_contrast: Option<f32>) -> String {
+    // The last definition of a key is the one Telegram uses
+    let colors: HashMap<&str, &Color> = lines.iter()
+        .filter_map(|line| line.color.as_ref().map(|color| (line.name.as_str(), color)))
+        .collect();
+    let targets: Targets = [(&TEXT_PAIRS[..], text_contrast), (&SECONDARY_TEXT_PAIRS[..], secondary_contrast)];
+
+    let keys = lines.iter().filter(|line| line.color.is_some()).count();
+    let untested = lines.iter().filter(|line| line.untested).count();
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(format!("<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n", escape(title), STYLE).as_str());
+    html.push_str(format!("<h1>{}</h1>\n<p>{} keys, {} of them untested</p>\n", escape(title), keys, untested).as_str());
+
+    html.push_str("<p>");
+    for index in 0..palette.len() {
+        let color = palette[format!("color{index}").as_str()].borrow();
+        html.push_str(format!("{} <code>color{}</code> ", swatch(&color), index).as_str());
+    }
+    html.push_str("</p>\n");
+
+    // Ids are only given to the first definition of a key
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut in_table = false;
+    for line in lines {
+        let Some(color) = &line.color else {
+            if in_table {
+                html.push_str("</table>\n");
+            }
+            html.push_str(format!("<h2>{}</h2>\n<table>\n", escape(&line.name)).as_str());
+            in_table = true;
+            continue;
+        };
+
+        // Keys before the first comment
+        if !in_table {
+            html.push_str("<table>\n");
+            in_table = true;
+        }
+
+        let id = if seen.insert(line.name.as_str()) { format!(" id=\"{}\"", escape(&line.name)) } else { String::new() };
+        let expr = line.expr.as_ref().map(|expr| expr.to_string()).unwrap_or_default();
+        let flag = if line.untested { "<span class=\"untested\">UNTESTED</span>" } else { "" };
+
+        html.push_str(format!(
+            "<tr{}><td>{}</td><td><code>{}</code> {}</td><td><code>#{}</code></td><td><code>{}</code></td><td>{}</td></tr>\n",
+            id, swatch(color), escape(&line.name), flag, color.to_hex(), escape(&expr), contrast_cell(&line.name, &colors, targets)
+        ).as_str());
+    }
+    if in_table {
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translucent_text_is_composited_over_its_background() {
+        let background = Color::from_hex("000000").unwrap();
+        let opaque = Color::from_hex("ffffff").unwrap();
+        let translucent = Color::from_hex("ffffff80").unwrap();
+        let cell = |text: &Color| {
+            let colors = HashMap::from([("windowFg", text), ("windowBg", &background)]);
+            contrast_cell("windowFg", &colors, [(&TEXT_PAIRS[..], Some(7.0)), (&SECONDARY_TEXT_PAIRS[..], None)])
+        };
+
+        assert!(cell(&opaque).starts_with("<span>21.00:1</span>"), "{}", cell(&opaque));
+        let expected = format!("<span class=\"fail\">{:.2}:1</span>", translucent.over(&background).get_contrast(&background));
+        assert!(cell(&translucent).starts_with(&expected), "{}", cell(&translucent));
+    }
+}
@@ -0,0 +1,78 @@
+use crate::color::Color;
+
+pub static MACOS_EXTENSION: &str = "palette";
+
+// Keys of the Telegram macOS palette with the role they are taken from, the keys left out come
+// from the parent palette
+static KEYS: [(&str, &str); 17] = [
+    ("background", "window-bg"),
+    ("text", "window-fg"),
+    ("grayText", "window-sub-fg"),
+    ("link", "link"),
+    ("accent", "accent"),
+    ("basicAccent", "accent"),
+    ("accentIcon", "accent"),
+    ("badge", "accent"),
+    ("accentSelect", "active"),
+    ("underSelectedColor", "active-fg"),
+    ("redUI", "error"),
+    ("greenUI", "success"),
+    ("chatBackground", "window-bg"),
+    ("bubbleBackground_incoming", "bubble-in"),
+    ("bubbleBackground_outgoing", "bubble-out"),
+    ("textBubble_incoming", "bubble-in-fg"),
+    ("textBubble_outgoing", "bubble-out-fg"),
+];
+
+// A 'key = value' palette like the ones the macOS app exports, translucent roles are made opaque
+// over the window background
+pub fn render(roles: &[(&str, Color)], name: &str) -> String {
+    let role = |role: &str| -> &Color {
+        roles.iter().find(|(other, _)| *other == role).map(|(_, color)| color)
+            .unwrap_or_else(|| panic!("Unexpected error, no '{role}' role"))
+    };
+    let background = role("window-bg");
+    let dark = background.get_relative_luminance() < 0.5;
+
+    let mut palette = format!("name = {}\ncopyright = wal-telegram\n", name.replace('\n', " "));
+    palette.push_str(format!("isDark = {}\ntinted = 0\n", dark as u8).as_str());
+    palette.push_str(format!("parent = {}\n", if dark { "night" } else { "day" }).as_str());
+
+    for (key, key_role) in KEYS {
+        let color = role(key_role).over(background);
+        palette.push_str(format!("{} = #{}\n", key, &color.to_hex()[..6]).as_str());
+    }
+
+    palette
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::palette::{fixtures, Mode};
+
+    #[test]
+    fn the_palette_parses_back() {
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+        let lines = crate::telegram::colors::compute_telegram_colors(&palette, &crate::telegram::colors::Mapping::default());
+        let roles = super::super::role_colors(&lines);
+        let rendered = render(&roles, "wal-telegram");
+
+        let entries: HashMap<&str, &str> = rendered.lines()
+            .map(|line| line.split_once(" = ").unwrap_or_else(|| panic!("'{line}' is not 'key = value'")))
+            .collect();
+        assert_eq!(entries.len(), rendered.lines().count());
+        assert_eq!(entries["name"], "wal-telegram");
+        assert_eq!(entries["isDark"], "1");
+        assert_eq!(entries["parent"], "night");
+
+        let background = &roles.iter().find(|(role, _)| *role == "window-bg").unwrap().1;
+        for (key, role) in KEYS {
+            let color = Color::from_hex(entries[key].strip_prefix('#').unwrap()).unwrap();
+            let expected = &roles.iter().find(|(other, _)| *other == role).unwrap().1;
+            assert_eq!(color.to_hex(), expected.over(background).to_hex(), "{key}");
+        }
+    }
+}
@@ -0,0 +1,90 @@
+pub mod attheme;
+pub mod css;
+pub mod html;
+pub mod macos;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::Color;
+use crate::telegram::colors::{Mapping, ThemeLine};
+
+// Files the derived colors can be exported to
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    // Page listing every Telegram key with its color and derivation
+    Html,
+    // CSS variables of the roles
+    Css,
+    // Telegram macOS palette, the keys it doesn't set come from its day or night theme
+    Macos,
+    // Telegram Android theme, with the background
+    Attheme,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Html => html::HTML_EXTENSION,
+            ExportFormat::Css => css::CSS_EXTENSION,
+            ExportFormat::Macos => macos::MACOS_EXTENSION,
+            ExportFormat::Attheme => attheme::ATTHEME_EXTENSION,
+        }
+    }
+}
+
+// Colors shared with the other apps, each one is taken from the Telegram key filling the same role
+pub static ROLES: [(&str, &str); 16] = [
+    ("window-bg", "windowBg"),
+    ("window-fg", "windowFg"),
+    ("window-sub-fg", "windowSubTextFg"),
+    ("accent", "windowBgActive"),
+    ("accent-fg", "windowFgActive"),
+    ("active", "dialogsBgActive"),
+    ("active-fg", "dialogsNameFgActive"),
+    ("link", "windowActiveTextFg"),
+    ("error", "boxTextFgError"),
+    ("success", "boxTextFgGood"),
+    ("warning", "mediaviewFileYellowCornerFg"),
+    ("info", "mediaviewFileBlueCornerFg"),
+    ("bubble-in", "msgInBg"),
+    ("bubble-in-fg", "historyTextInFg"),
+    ("bubble-out", "msgOutBg"),
+    ("bubble-out-fg", "historyTextOutFg"),
+];
+
+// The role names with their colors, in the ROLES order
+pub fn role_colors(lines: &[ThemeLine]) -> Vec<(&'static str, Color)> {
+    ROLES.iter()
+        .map(|(role, key)| {
+            // The last definition of a key is the one Telegram uses
+            let color = lines.iter().rev()
+                .find_map(|line| line.color.as_ref().filter(|_| line.name == *key))
+                .unwrap_or_else(|| panic!("Unexpected error, the key '{key}' is not in the theme"));
+            (*role, color.clone())
+        })
+        .collect()
+}
+
+// Contents of the export of one profile
+// - tmp_dir holds the processed background
+pub fn export(format: ExportFormat, palette: &HashMap<String, Rc<RefCell<Color>>>, lines: &[ThemeLine], mapping: &Mapping, profile: Option<&str>, tmp_dir: &std::path::Path) -> Vec<u8> {
+    let name = match profile {
+        Some(profile) => format!("wal-telegram ({profile})"),
+        None => String::from("wal-telegram"),
+    };
+
+    match format {
+        ExportFormat::Html => html::render(palette, lines, &format!("{name} theme"), mapping.text_contrast, mapping.secondary_contrast).into_bytes(),
+        ExportFormat::Css => css::render(&role_colors(lines)).into_bytes(),
+        ExportFormat::Macos => macos::render(&role_colors(lines), &name).into_bytes(),
+        ExportFormat::Attheme => {
+            let background = std::fs::read(tmp_dir.join(crate::background::BG_FILENAME)).ok();
+            attheme::render(lines, background.as_deref())
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+use std::process;
+use std::str::FromStr;
+
+// Something run after a theme is written
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Hook {
+    // Open the theme in Telegram Desktop, which asks to apply it
+    Telegram,
+    // Same with the Flatpak version of Telegram Desktop
+    TelegramFlatpak,
+    // A shell command, the theme path is given as its last argument
+    Command(String),
+}
+
+impl FromStr for Hook {
+    type Err = String;
+
+    // Parse the 'telegram' and 'telegram-flatpak' built-in hooks, anything else is a command
+    fn from_str(hook: &str) -> Result<Self, Self::Err> {
+        match hook.trim() {
+            "" => Err(String::from("A hook can not be empty")),
+            "telegram" => Ok(Hook::Telegram),
+            "telegram-flatpak" => Ok(Hook::TelegramFlatpak),
+            command => Ok(Hook::Command(String::from(command))),
+        }
+    }
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Hook::Telegram => write!(f, "telegram"),
+            Hook::TelegramFlatpak => write!(f, "telegram-flatpak"),
+            Hook::Command(command) => write!(f, "{command}"),
+        }
+    }
+}
+
+impl TryFrom<String> for Hook {
+    type Error = String;
+
+    fn try_from(hook: String) -> Result<Self, Self::Error> {
+        hook.parse()
+    }
+}
+
+impl From<Hook> for String {
+    fn from(hook: Hook) -> Self {
+        hook.to_string()
+    }
+}
+
+// Run the hooks for a written theme, a failing hook is reported and the next ones still run
+// - profile is given to the commands as WAL_TELEGRAM_PROFILE
+pub fn run_hooks(hooks: &[Hook], theme_path: &std::path::Path, profile: Option<&str>) {
+    for hook in hooks {
+        if let Err(err) = run_hook(hook, theme_path, profile) {
+            eprintln!("WARNING! The '{}' hook failed: {}", hook, err);
+        }
+    }
+}
+
+fn run_hook(hook: &Hook, theme_path: &std::path::Path, profile: Option<&str>) -> Result<(), String> {
+    let mut command = match hook {
+        Hook::Telegram => {
+            let mut command = process::Command::new("telegram-desktop");
+            command.arg("--").arg(theme_path);
+            command
+        }
+        Hook::TelegramFlatpak => {
+            let mut command = process::Command::new("flatpak");
+            command.args(["run", "org.telegram.desktop", "--"]).arg(theme_path);
+            command
+        }
+        Hook::Command(line) => {
+            // "$@" puts the theme path after the user's arguments
+            let mut command = process::Command::new("sh");
+            command.arg("-c").arg(format!("{line} \"$@\"")).arg("wal-telegram").arg(theme_path);
+            command
+        }
+    };
+
+    let program = command.get_program().to_string_lossy().to_string();
+    command.env("WAL_TELEGRAM_THEME", theme_path)
+        .env("WAL_TELEGRAM_PROFILE", profile.unwrap_or_default());
+
+    match hook {
+        // Telegram keeps running when it wasn't already, so it is not waited for
+        Hook::Telegram | Hook::TelegramFlatpak => {
+            command.stdin(process::Stdio::null()).stdout(process::Stdio::null()).stderr(process::Stdio::null());
+            command.spawn().map_err(|err| format!("couldn't run '{program}': {err}"))?;
+        }
+        Hook::Command(_) => {
+            let status = command.status().map_err(|err| format!("couldn't run '{program}': {err}"))?;
+            if !status.success() {
+                return Err(format!("it exited with {}", status));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_hooks_are_named() {
+        assert_eq!("telegram".parse(), Ok(Hook::Telegram));
+        assert_eq!("telegram-flatpak".parse(), Ok(Hook::TelegramFlatpak));
+        assert_eq!("notify-send Theme".parse(), Ok(Hook::Command(String::from("notify-send Theme"))));
+        assert!(" ".parse::<Hook>().is_err());
+    }
+
+    #[test]
+    fn commands_get_the_theme_path_and_profile() {
+        let tmp_dir = crate::tmp_dir::create_tmp_dir(false);
+        let log = tmp_dir.join("stub.log");
+        let theme_path = tmp_dir.join("my theme.tdesktop-theme");
+
+        // The stub records the profile and then each of its arguments on its own line
+        let stub = Hook::Command(format!("printf '%s\\n' \"$WAL_TELEGRAM_PROFILE\" first >> '{}'", log.display()));
+        // A failing hook doesn't stop the next ones
+        run_hooks(&[Hook::Command(String::from("false")), stub.clone(), stub], &theme_path, Some("dark"));
+
+        let recorded = std::fs::read_to_string(&log).unwrap();
+        let expected = format!("dark\nfirst\n{}\n", theme_path.display());
+        assert_eq!(recorded, expected.repeat(2));
+    }
+}
@@ -1,21 +1,268 @@
 mod background;
+mod cli;
 mod color;
+mod config;
+mod dry_run;
+mod explain;
+mod export;
+mod hooks;
+mod metadata;
+mod output;
 mod palette;
+mod preview;
 mod telegram;
 mod tmp_dir;
 
-fn main() {
-    // env variable for script always yes
-    std::env::set_var("WAL_TELEGRAM_YES_ALL", "TRUE");
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::process::ExitCode;
+use std::rc::Rc;
+
+use clap::Parser;
+
+use color::Color;
+
+fn main() -> ExitCode {
+    let args = cli::Args::parse();
+    let overwrite = args.overwrite();
+
+    if let Some(cli::Command::Info { theme }) = &args.command {
+        match metadata::read_metadata(theme) {
+            Ok(metadata) => metadata::print_info(&metadata),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    if let Some(cli::Command::Check { canonical }) = &args.command {
+        let canonical_keys = match telegram::canonical::read_keys(canonical.as_deref()) {
+            Ok(canonical_keys) => canonical_keys,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        // The palette keys are helpers, they don't matter here
+        let entries = telegram::colors::telegram_entries(0, &telegram::colors::Mapping::default());
+        if !telegram::canonical::print_check(&canonical_keys, &telegram::colors::entries_keys(&entries)) {
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    // Cleaning doesn't depend on the config, it must work even if the config is broken
+    if let Some(cli::Command::Clean { older_than }) = args.command {
+        let removed = tmp_dir::clean_tmp_dirs(std::time::Duration::from_secs(older_than * 60));
+        println!("{} temporary directories removed", removed);
+        return ExitCode::SUCCESS;
+    }
+
+    let all_settings = match config::load(args.config.as_deref(), args.options, &args.profiles) {
+        Ok(all_settings) => all_settings,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match args.command {
+        Some(cli::Command::Config { action: cli::ConfigCommand::Show }) => {
+            show_settings(&all_settings);
+        }
+        Some(cli::Command::Clean { .. } | cli::Command::Info { .. } | cli::Command::Check { .. }) => {}
+        Some(cli::Command::Preview { .. } | cli::Command::Export { .. } | cli::Command::Explain { .. }) | None => {
+            let action = match &args.command {
+                Some(cli::Command::Preview { output }) => Action::Preview(output.as_deref()),
+                Some(cli::Command::Export { format, output }) => Action::Export(*format, output.as_deref()),
+                Some(cli::Command::Explain { key }) => Action::Explain(key),
+                _ if args.dry_run => Action::DryRun,
+                _ => Action::Theme,
+            };
+
+            if let Action::Preview(Some(output)) | Action::Export(_, Some(output)) = action {
+                if all_settings.len() > 1 && !std::path::Path::new(output).is_dir() {
+                    eprintln!("Error: Several profiles are built, '{}' must be a directory", output);
+                    return ExitCode::FAILURE;
+                }
+            }
+
+            // Palettes and wallpapers are only read once for all the profiles
+            let mut palettes = HashMap::new();
+            let mut images = background::ImageCache::default();
+
+            let mut kept = false;
+            for settings in &all_settings {
+                let parsed = palettes.entry((settings.palette, settings.palette_path.clone()))
+                    .or_insert_with(|| palette::read_palette(settings.palette, settings.palette_path.as_deref()));
+                if let Err(err) = settings.validate_palette(parsed.len()) {
+                    match &settings.profile {
+                        Some(profile) => eprintln!("Error: In profile '{}': {}", profile, err),
+                        None => eprintln!("Error: {}", err),
+                    }
+                    return ExitCode::FAILURE;
+                }
+
+                let writing = output::Writing { overwrite, backups: settings.backups };
+                match generate(settings, parsed, &mut images, &writing, args.keep_temp, &action) {
+                    Ok(written) => kept |= !written,
+                    Err(err) => {
+                        eprintln!("Error: {}", err);
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+
+            // The other profiles are still built, but scripts must know an output wasn't updated
+            if kept {
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+// What is done with the generated theme
+enum Action<'a> {
+    Theme,
+    DryRun,
+    // PNG mockup written to the given output
+    Preview(Option<&'a str>),
+    // Colors exported to the given output
+    Export(export::ExportFormat, Option<&'a str>),
+    // Derivation of a key printed
+    Explain(&'a str),
+}
+
+fn show_settings(all_settings: &[config::Settings]) {
+    let toml = match all_settings {
+        [settings] if settings.profile.is_none() => toml::to_string_pretty(settings),
+        _ => {
+            let profiles: BTreeMap<&str, &config::Settings> = all_settings.iter()
+                .map(|settings| (settings.profile.as_deref().unwrap_or_default(), settings))
+                .collect();
+            toml::to_string_pretty(&BTreeMap::from([("profiles", profiles)]))
+        }
+    };
+
+    print!("{}", toml.expect("Unexpected error, could not serialize the settings"));
+}
+
+// Build the theme of one profile, nothing is shared with the other profiles but the parsed palette
+// and the decoded images, which are copied before being modified
+// Returns false if an existing output was kept instead of being overwritten, errors are returned
+// rather than exiting so that the temporary directory is removed
+fn generate(settings: &config::Settings, parsed_palette: &HashMap<String, Rc<RefCell<Color>>>, images: &mut background::ImageCache, writing: &output::Writing, keep_temp: bool, action: &Action) -> Result<bool, String> {
     // Create tmp dir
-    let tmp_dir = tmp_dir::create_tmp_dir();
+    let tmp_dir = tmp_dir::create_tmp_dir(keep_temp);
+
+    let palette = palette::prepare_palette(parsed_palette, settings.mode, &settings.adjustments, settings.keep_roles);
+
+    let mut mapping = settings.mapping();
+    mapping.status = telegram::colors::status_colors(&palette, &settings.status_colors);
+    mapping.accent = settings.accent.as_ref().map(|accent| telegram::colors::pick_accent(&palette, accent));
+    let processing = settings.background_processing();
+    match settings.background {
+        cli::Background::Wal => {
+            background::wal::use_wal_background(&tmp_dir, &processing, &palette, images, settings.background_path.as_deref());
+        }
+        cli::Background::Custom => {
+            let background_path = settings.background_path.as_deref().unwrap();
+            background::custom::use_custom_background(&tmp_dir, &processing, &palette, images, background_path);
+        }
+        cli::Background::Plain => {
+            let wallpaper = match settings.plain_color {
+                background::plain::PlainColor::Dominant => Some(images.open(&settings.wallpaper())),
+                _ => None,
+            };
+            background::plain::use_plain_image(&tmp_dir, &palette, &settings.plain_color, wallpaper, &mapping.bubbles);
+        }
+        cli::Background::Gradient => {
+            let resolution = (settings.resolution.0, settings.resolution.1);
+            background::gradient::use_gradient_image(&tmp_dir, &palette, settings.gradient, &settings.background_colors, settings.gradient_angle, resolution, processing.max_theme_size);
+        }
+        cli::Background::Pattern => {
+            let resolution = (settings.resolution.0, settings.resolution.1);
+            background::pattern::use_pattern_image(&tmp_dir, &palette, settings.pattern, &settings.background_colors, resolution, settings.pattern_size, processing.max_theme_size);
+        }
+    }
+
+    // The automatic bubble and overlay colors depend on the processed background
+    let auto_bubble = settings.out_bubble == telegram::colors::BubbleColor::Auto;
+    if auto_bubble || settings.adaptive_overlays {
+        let background = image::open(tmp_dir.join(background::BG_FILENAME))
+            .expect("Unexpected error, could not read back the processed background");
+        let analysis = background::analysis::analyse(&background);
+        if auto_bubble {
+            mapping.bubbles.out_role = telegram::colors::auto_out_role(&palette, &analysis.out_average, mapping.bubbles.opacity);
+        }
+        if settings.adaptive_overlays {
+            mapping.overlays = telegram::colors::auto_overlays(&palette, &analysis);
+        }
+    }
+    let metadata = settings.metadata.then(|| metadata::Metadata::new(settings, &palette));
+    let profile = settings.profile.as_deref();
+    let outputs: Vec<Option<&str>> = if settings.output.is_empty() {
+        vec![None]
+    }
+    else {
+        settings.output.iter().map(|output| Some(output.as_str())).collect()
+    };
+
+    match action {
+        Action::Theme => {}
+        Action::DryRun => {
+            let output_paths: Vec<std::path::PathBuf> = outputs.iter()
+                .map(|output| output::process_output(output, profile, telegram::theme::THEME_EXTENSION))
+                .collect();
+            let colors = telegram::colors::compute_telegram_colors(&palette, &mapping);
+            dry_run::print_summary(settings, &palette, &tmp_dir, &colors, &output_paths, writing);
+            return Ok(true);
+        }
+        Action::Preview(output) => {
+            let colors = telegram::colors::compute_telegram_colors(&palette, &mapping);
+            let background = image::open(tmp_dir.join(background::BG_FILENAME))
+                .expect("Unexpected error, could not read back the processed background");
+            let preview_path = output::process_output(output, profile, preview::PREVIEW_EXTENSION);
 
-    // If wal then wal else custom blabla
-    let palette = palette::generate_palette(palette::Palette::Wal, None);
+            let contents = preview::encode_preview(preview::render(&colors, &background));
+            if !output::write_output(&preview_path, &contents, writing) {
+                return Ok(false);
+            }
+            println!("Preview written to '{}'", preview_path.display());
+            return Ok(true);
+        }
+        Action::Explain(key) => {
+            let colors = telegram::colors::compute_telegram_colors(&palette, &mapping);
+            if let Some(profile) = profile {
+                println!("Profile: {profile}");
+            }
+            explain::print_explanation(key, &palette, &colors)?;
+            return Ok(true);
+        }
+        Action::Export(format, output) => {
+            let colors = telegram::colors::compute_telegram_colors(&palette, &mapping);
+            let export_path = output::process_output(output, profile, format.extension());
 
-    // Lets say it's wal background
-    // background::wal::use_wal_background(&tmp_dir, 0.1, None);
-    background::plain::use_plain_image(&tmp_dir, Some(&palette), None);
+            let contents = export::export(*format, &palette, &colors, &mapping, profile, &tmp_dir);
+            if !output::write_output(&export_path, &contents, writing) {
+                return Ok(false);
+            }
+            println!("Export written to '{}'", export_path.display());
+            return Ok(true);
+        }
+    }
 
-    telegram::theme::package_theme(&tmp_dir, None, &palette);
+    let mut written = true;
+    for output in outputs {
+        match telegram::theme::package_theme(&tmp_dir, output, &palette, &mapping, profile, writing, metadata.as_ref()) {
+            Some(theme_path) => hooks::run_hooks(&settings.hooks, &theme_path, profile),
+            None => written = false,
+        }
+    }
+    Ok(written)
 }
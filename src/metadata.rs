@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Read;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::background::effects::Effect;
+use crate::cli::Background;
+use crate::color::Color;
+use crate::config::Settings;
+use crate::palette::{Mode, Palette};
+use crate::palette::adjust::Adjustment;
+
+pub static METADATA_FILENAME: &str = "wal-telegram.json";
+
+// What a theme was made from, stored in the theme archive
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Metadata {
+    pub generator: String,
+    pub version: String,
+    // Seconds since the epoch
+    pub timestamp: i64,
+    pub profile: Option<String>,
+    pub palette: PaletteSource,
+    pub background: BackgroundSource,
+    pub effects: Vec<Effect>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PaletteSource {
+    pub source: Palette,
+    pub path: String,
+    pub mode: Mode,
+    // Themes made before the adjustments existed have none
+    #[serde(default)]
+    pub adjustments: Vec<Adjustment>,
+    pub colors: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BackgroundSource {
+    pub source: Background,
+    // Image the background was made from, generated backgrounds have none
+    pub path: Option<String>,
+    pub sha256: Option<String>,
+}
+
+impl Metadata {
+    pub fn new(settings: &Settings, palette: &HashMap<String, Rc<RefCell<Color>>>) -> Metadata {
+        let colors = (0..palette.len())
+            .map(|index| format!("#{}", palette[format!("color{index}").as_str()].borrow().to_hex()))
+            .collect();
+
+        let (image, effects) = match settings.background {
+            Background::Wal => {
+                (Some(crate::background::wal::wallpaper_path(settings.background_path.as_deref())), settings.background_processing().effects)
+            }
+            Background::Custom => {
+                (settings.background_path.clone(), settings.background_processing().effects)
+            }
+            Background::Plain if settings.plain_color == crate::background::plain::PlainColor::Dominant => {
+                (Some(settings.wallpaper()), Vec::new())
+            }
+            _ => (None, Vec::new()),
+        };
+        let sha256 = image.as_deref().map(file_sha256);
+
+        let timestamp = source_date_epoch().unwrap_or_else(|| {
+            std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                .map(|time| time.as_secs() as i64).unwrap_or_default()
+        });
+
+        Metadata {
+            generator: String::from(env!("CARGO_PKG_NAME")),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            timestamp,
+            profile: settings.profile.clone(),
+            palette: PaletteSource {
+                source: settings.palette,
+                path: crate::palette::palette_path(settings.palette, settings.palette_path.as_deref()),
+                mode: settings.mode,
+                adjustments: settings.adjustments.clone(),
+                colors,
+            },
+            background: BackgroundSource {
+                source: settings.background,
+                path: image,
+                sha256,
+            },
+            effects,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Unexpected error, could not serialize the theme metadata")
+    }
+}
+
+fn file_sha256(path: &str) -> String {
+    let contents = std::fs::read(path).unwrap_or_else(|_| panic!("Unexpected error, Couldn't read the file {}", path));
+
+    Sha256::digest(contents).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Build time given by SOURCE_DATE_EPOCH, used instead of the current time so that builds can be
+// reproduced (https://reproducible-builds.org/specs/source-date-epoch/)
+pub fn source_date_epoch() -> Option<i64> {
+    std::env::var("SOURCE_DATE_EPOCH").ok().and_then(|epoch| epoch.trim().parse().ok())
+}
+
+// Seconds since the epoch to UTC (year, month, day, hour, minute, second)
+pub fn utc_date(epoch: i64) -> (i64, u32, u32, u32, u32, u32) {
+    // Days to civil date, from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let (days, seconds) = (epoch.div_euclid(86400), epoch.rem_euclid(86400));
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month as u32, day as u32, (seconds / 3600) as u32, (seconds % 3600 / 60) as u32, (seconds % 60) as u32)
+}
+
+// Read the metadata back from a theme file
+pub fn read_metadata(theme_path: &str) -> Result<Metadata, String> {
+    let file = std::fs::File::open(theme_path)
+        .map_err(|err| format!("Couldn't open the theme '{}': {}", theme_path, err))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|err| format!("'{}' is not a theme archive: {}", theme_path, err))?;
+
+    let mut entry = archive.by_name(METADATA_FILENAME)
+        .map_err(|_| format!("'{}' has no metadata, it was built without --metadata or by another tool", theme_path))?;
+    let mut contents = String::new();
+    entry.read_to_string(&mut contents)
+        .map_err(|err| format!("Couldn't read the metadata of '{}': {}", theme_path, err))?;
+
+    serde_json::from_str(&contents)
+        .map_err(|err| format!("Invalid metadata in '{}': {}", theme_path, err))
+}
+
+pub fn print_info(metadata: &Metadata) {
+    let (year, month, day, hour, minute, second) = utc_date(metadata.timestamp);
+    let effects: Vec<String> = metadata.effects.iter().map(|effect| effect.to_string()).collect();
+
+    println!("Generator:  {} {}", metadata.generator, metadata.version);
+    println!("Built:      {year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02} UTC");
+    println!("Profile:    {}", metadata.profile.as_deref().unwrap_or("-"));
+    println!("Palette:    {} '{}' ({} mode)", serde_name(&metadata.palette.source), metadata.palette.path, serde_name(&metadata.palette.mode));
+    if !metadata.palette.adjustments.is_empty() {
+        let adjustments: Vec<String> = metadata.palette.adjustments.iter().map(|adjustment| adjustment.to_string()).collect();
+        println!("Adjusted:   {}", adjustments.join(", "));
+    }
+    println!("Colors:     {}", metadata.palette.colors.join(" "));
+    match &metadata.background.path {
+        Some(path) => println!("Background: {} '{}'", serde_name(&metadata.background.source), path),
+        None => println!("Background: {}", serde_name(&metadata.background.source)),
+    }
+    if let Some(sha256) = &metadata.background.sha256 {
+        println!("SHA-256:    {sha256}");
+    }
+    println!("Effects:    {}", if effects.is_empty() { String::from("-") } else { effects.join(", ") });
+}
+
+// Name of an enum value as written in the config file
+pub fn serde_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value).ok()
+        .and_then(|value| value.as_str().map(String::from))
+        .unwrap_or_default()
+}
@@ -0,0 +1,230 @@
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path;
+
+// What to do when an output file already exists
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overwrite {
+    // Ask on the terminal, refuse when there is no terminal to ask on
+    Ask,
+    Yes,
+    NoClobber,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Writing {
+    pub overwrite: Overwrite,
+    // Number of previous versions kept next to the output
+    pub backups: usize,
+}
+
+// Where to write a file, either the output given or a file named after the profile in it or in
+// $XDG_CACHE_HOME/wal-telegram
+pub fn process_output(output_str: &Option<&str>, profile: Option<&str>, extension: &str) -> std::path::PathBuf {
+    // Profiles get their own file so they don't overwrite each other
+    let default_filename = match profile {
+        Some(profile) => format!("wal_telegram-{profile}.{extension}"),
+        None => format!("wal_telegram.{extension}"),
+    };
+    let mut default_path = std::path::PathBuf::new();
+
+    if let Ok(xdg_dirs) = xdg::BaseDirectories::new() {
+        default_path.push(xdg_dirs.get_cache_home());
+        default_path.push("wal-telegram");
+    }
+    else {
+        // Problem initializing xdg_dirs, try manually
+        default_path.push("~/.cache/wal-telegram");
+    };
+
+    default_path.push(&default_filename);
+
+    let mut output_path;
+
+    if let Some(output_str) = output_str {
+        output_path = std::path::PathBuf::new();
+        output_path.push(output_str);
+
+        // If it's a dir create the file in there with default filename
+        if output_path.as_path().is_dir() {
+            output_path.push(&default_filename);
+        }
+    }
+    else {
+        // If it's None use default
+        output_path = default_path;
+    }
+
+    output_path
+}
+
+// Ask if an existing file can be replaced
+fn confirm_overwrite(output_path: &path::Path, overwrite: Overwrite) -> bool {
+    match overwrite {
+        Overwrite::Yes => { return true; }
+        Overwrite::NoClobber => {
+            eprintln!("'{}' already exists, not overwriting it", output_path.display());
+            return false;
+        }
+        Overwrite::Ask => {}
+    }
+
+    if !std::io::stdin().is_terminal() {
+        eprintln!("'{}' already exists, use --yes to overwrite it", output_path.display());
+        return false;
+    }
+
+    eprint!("'{}' already exists, overwrite it? [y/N] ", output_path.display());
+    std::io::stderr().flush().ok();
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+
+    let confirmed = matches!(answer.trim().to_lowercase().as_str(), "y" | "yes");
+    if !confirmed {
+        eprintln!("Not overwriting '{}'", output_path.display());
+    }
+    confirmed
+}
+
+// Write the file next to the output then rename it over the output, so that a crash never leaves a
+// truncated file behind
+// Returns false if the output already existed and was kept
+pub fn write_output(output_path: &path::Path, contents: &[u8], writing: &Writing) -> bool {
+    if output_path.is_dir() {
+        panic!("The output path {} already exists as a directory", output_path.display());
+    }
+
+    let exists = output_path.try_exists()
+        .unwrap_or_else(|_| panic!("The output path {} cannot be probed, permissions are probably the issue.", output_path.display()));
+    if exists && !confirm_overwrite(output_path, writing.overwrite) {
+        return false;
+    }
+
+    let parent = output_path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(path::Path::new("."));
+    if let Err(err) = fs::create_dir_all(parent) {
+        eprintln!("Error trying to create directories to '{}'", parent.display());
+        panic!("Error: {}", err);
+    }
+
+    let filename = output_path.file_name()
+        .unwrap_or_else(|| panic!("The output path {} has no file name", output_path.display()))
+        .to_string_lossy();
+    let tmp_path = parent.join(format!(".{}.{}.tmp", filename, std::process::id()));
+
+    let written = fs::File::create(&tmp_path).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    if let Err(err) = written {
+        fs::remove_file(&tmp_path).ok();
+        eprintln!("Error trying to write the temporary output file '{}'", tmp_path.display());
+        panic!("Error: {}", err);
+    }
+
+    if exists && writing.backups > 0 {
+        backup(output_path, writing.backups);
+    }
+
+    if let Err(err) = fs::rename(&tmp_path, output_path) {
+        fs::remove_file(&tmp_path).ok();
+        eprintln!("Error trying to move '{}' to '{}'", tmp_path.display(), output_path.display());
+        panic!("Error: {}", err);
+    }
+
+    true
+}
+
+// Copy the current output to '<output>.<timestamp>.bak' and only keep the most recent backups
+fn backup(output_path: &path::Path, backups: usize) {
+    let filename = output_path.file_name().unwrap().to_string_lossy().to_string();
+    let parent = output_path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(path::Path::new("."));
+
+    let mut timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_millis()).unwrap_or_default();
+    // Two backups in the same millisecond must not overwrite each other
+    while parent.join(format!("{filename}.{timestamp}.bak")).exists() {
+        timestamp += 1;
+    }
+
+    let backup_path = parent.join(format!("{filename}.{timestamp}.bak"));
+    if let Err(err) = fs::copy(output_path, &backup_path) {
+        eprintln!("WARNING! Couldn't back up '{}' to '{}': {}", output_path.display(), backup_path.display(), err);
+        return;
+    }
+
+    let mut existing: Vec<(u128, path::PathBuf)> = match fs::read_dir(parent) {
+        Ok(entries) => entries.filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_str()?;
+            let timestamp = name.strip_prefix(filename.as_str())?.strip_prefix('.')?.strip_suffix(".bak")?.parse().ok()?;
+            Some((timestamp, path))
+        }).collect(),
+        Err(_) => Vec::new(),
+    };
+
+    // Newest first
+    existing.sort_by_key(|(timestamp, _)| std::cmp::Reverse(*timestamp));
+    for (_, old_backup) in existing.iter().skip(backups) {
+        if let Err(err) = fs::remove_file(old_backup) {
+            eprintln!("WARNING! Couldn't remove the old backup '{}': {}", old_backup.display(), err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(dir: &path::Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn no_clobber_refuses_to_overwrite() {
+        let tmp_dir = crate::tmp_dir::create_tmp_dir(false);
+        let output = tmp_dir.join("theme");
+        fs::write(&output, "old").unwrap();
+
+        let writing = Writing { overwrite: Overwrite::NoClobber, backups: 2 };
+        assert!(!write_output(&output, b"new", &writing));
+        assert_eq!(fs::read_to_string(&output).unwrap(), "old");
+        assert_eq!(names(&tmp_dir), ["theme"]);
+    }
+
+    #[test]
+    fn yes_overwrites_without_leftovers() {
+        let tmp_dir = crate::tmp_dir::create_tmp_dir(false);
+        let output = tmp_dir.join("theme");
+        fs::write(&output, "old").unwrap();
+
+        let writing = Writing { overwrite: Overwrite::Yes, backups: 0 };
+        assert!(write_output(&output, b"new", &writing));
+        assert_eq!(fs::read_to_string(&output).unwrap(), "new");
+        assert_eq!(names(&tmp_dir), ["theme"]);
+    }
+
+    #[test]
+    fn backups_keep_the_most_recent_versions() {
+        let tmp_dir = crate::tmp_dir::create_tmp_dir(false);
+        let output = tmp_dir.join("theme");
+
+        let writing = Writing { overwrite: Overwrite::Yes, backups: 2 };
+        for version in 1..=4 {
+            assert!(write_output(&output, format!("version {version}").as_bytes(), &writing));
+        }
+
+        assert_eq!(fs::read_to_string(&output).unwrap(), "version 4");
+        // Backups sort by timestamp, oldest first
+        let backups: Vec<String> = names(&tmp_dir).into_iter().filter(|name| name.ends_with(".bak")).collect();
+        assert_eq!(backups.len(), 2);
+        let contents: Vec<String> = backups.iter().map(|name| fs::read_to_string(tmp_dir.join(name)).unwrap()).collect();
+        assert_eq!(contents, ["version 2", "version 3"]);
+    }
+}
@@ -0,0 +1,239 @@
+use std::{collections::HashMap, rc::Rc, cell::RefCell, fmt, str::FromStr};
+
+use crate::color::Color;
+
+// Background and foreground entries, which the adjustments can leave untouched
+static ROLE_ENTRIES: [usize; 4] = [0, 7, 8, 15];
+
+// An adjustment of the whole palette in OKLab, adjustments are chained in order
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Adjustment {
+    // Chroma change in percent, -100 is grayscale
+    Saturation(f32),
+    // Percent of the way to white, or black when negative
+    Brightness(f32),
+    // Lightness spread around the middle gray in percent, -100 brings every color to the middle
+    // lightness
+    Contrast(f32),
+    // From -100 (cold, blue) to 100 (warm, yellow)
+    Temperature(f32),
+    // Hue rotation in degrees
+    Hue(f32),
+}
+
+impl FromStr for Adjustment {
+    type Err = String;
+
+    // Parse an adjustment formatted as NAME:VALUE, eg. 'saturation:-30' or 'hue:15'
+    fn from_str(adjustment: &str) -> Result<Self, Self::Err> {
+        let (name, value) = adjustment.split_once(':')
+            .ok_or_else(|| format!("'{adjustment}' is not formatted as NAME:VALUE"))?;
+        let value: f32 = value.trim().parse().map_err(|_| format!("Invalid value '{value}' for the {name} adjustment"))?;
+
+        let percentage = |min: f32| -> Result<f32, String> {
+            if !(min..=100.0).contains(&value) {
+                return Err(format!("The {name} adjustment must be between {min} and 100"));
+            }
+            Ok(value)
+        };
+
+        match name.trim() {
+            // Saturation can go past 100%, until the colors leave the gamut
+            "saturation" if value >= -100.0 => Ok(Adjustment::Saturation(value)),
+            "saturation" => Err(String::from("The saturation adjustment can not be under -100")),
+            "brightness" => Ok(Adjustment::Brightness(percentage(-100.0)?)),
+            "contrast" => Ok(Adjustment::Contrast(percentage(-100.0)?)),
+            "temperature" => Ok(Adjustment::Temperature(percentage(-100.0)?)),
+            "hue" => Ok(Adjustment::Hue(value)),
+            _ => Err(format!("Unknown adjustment '{name}', expected one of saturation, brightness, contrast, temperature, hue")),
+        }
+    }
+}
+
+impl fmt::Display for Adjustment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Adjustment::Saturation(value) => write!(f, "saturation:{value}"),
+            Adjustment::Brightness(value) => write!(f, "brightness:{value}"),
+            Adjustment::Contrast(value) => write!(f, "contrast:{value}"),
+            Adjustment::Temperature(value) => write!(f, "temperature:{value}"),
+            Adjustment::Hue(value) => write!(f, "hue:{value}"),
+        }
+    }
+}
+
+impl TryFrom<String> for Adjustment {
+    type Error = String;
+
+    fn try_from(adjustment: String) -> Result<Self, Self::Error> {
+        adjustment.parse()
+    }
+}
+
+impl From<Adjustment> for String {
+    fn from(adjustment: Adjustment) -> Self {
+        adjustment.to_string()
+    }
+}
+
+impl Adjustment {
+    // The adjusted OKLab color
+    fn apply(&self, lab: [f32; 3]) -> [f32; 3] {
+        let [lightness, a, b] = lab;
+
+        match self {
+            Adjustment::Saturation(value) => {
+                let factor = 1.0 + value / 100.0;
+                [lightness, a * factor, b * factor]
+            }
+            Adjustment::Brightness(value) => {
+                let target = if *value >= 0.0 { [1.0, 0.0, 0.0] } else { [0.0; 3] };
+                let amount = value.abs() / 100.0;
+                [0, 1, 2].map(|channel| lab[channel] + (target[channel] - lab[channel]) * amount)
+            }
+            Adjustment::Contrast(value) => [0.5 + (lightness - 0.5) * (1.0 + value / 100.0), a, b],
+            // At 100 the colors move by about half the chroma of a pastel, along the yellow-blue
+            // axis with a bit of red for the warm side
+            Adjustment::Temperature(value) => [lightness, a + value / 100.0 * 0.01, b + value / 100.0 * 0.04],
+            Adjustment::Hue(degrees) => {
+                let (sin, cos) = degrees.to_radians().sin_cos();
+                [lightness, a * cos - b * sin, a * sin + b * cos]
+            }
+        }
+    }
+}
+
+// The color once all the adjustments are applied, out of gamut colors are clamped at the end only
+pub fn adjust_color(color: &Color, adjustments: &[Adjustment]) -> Color {
+    let mut lab = color.to_oklab();
+    for adjustment in adjustments {
+        lab = adjustment.apply(lab);
+    }
+    lab[0] = lab[0].clamp(0.0, 1.0);
+
+    let mut adjusted = Color::from_oklab(lab).expect("Unexpected error, could not convert the adjusted color");
+    adjusted.alpha = color.alpha;
+    adjusted
+}
+
+// Adjust the palette in place
+// - keep_roles leaves the background and foreground entries untouched
+pub fn adjust_palette(palette: &HashMap<String, Rc<RefCell<Color>>>, adjustments: &[Adjustment], keep_roles: bool) {
+    if adjustments.is_empty() {
+        return;
+    }
+
+    // Entries sharing a color are only adjusted once
+    let mut adjusted: Vec<*const RefCell<Color>> = Vec::new();
+    for index in 0..palette.len() {
+        let color = &palette[format!("color{index}").as_str()];
+        if (keep_roles && ROLE_ENTRIES.contains(&index)) || adjusted.contains(&Rc::as_ptr(color)) {
+            continue;
+        }
+
+        let new_color = adjust_color(&color.borrow(), adjustments);
+        *color.borrow_mut() = new_color;
+        adjusted.push(Rc::as_ptr(color));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // OKLab values are compared to the 4th decimal, the f32 conversions lose the rest
+    fn assert_lab(actual: [f32; 3], expected: [f32; 3]) {
+        for (actual_value, expected_value) in actual.iter().zip(expected) {
+            assert!((actual_value - expected_value).abs() < 1e-4, "{actual:?} != {expected:?}");
+        }
+    }
+
+    fn adjusted(hex: &str, adjustments: &str) -> Color {
+        let adjustments: Vec<Adjustment> = adjustments.split(',').map(|adjustment| adjustment.parse().unwrap()).collect();
+        adjust_color(&Color::from_hex(hex).unwrap(), &adjustments)
+    }
+
+    #[test]
+    fn saturation_scales_the_chroma() {
+        assert_lab(Adjustment::Saturation(-50.0).apply([0.6, 0.1, -0.04]), [0.6, 0.05, -0.02]);
+        assert_lab(Adjustment::Saturation(20.0).apply([0.6, 0.1, -0.04]), [0.6, 0.12, -0.048]);
+
+        let grey = adjusted("f7768e", "saturation:-100");
+        assert!(grey.to_oklch()[1] < 1e-3, "{}", grey.to_hex());
+    }
+
+    #[test]
+    fn brightness_moves_toward_white_or_black() {
+        assert_lab(Adjustment::Brightness(50.0).apply([0.4, 0.1, 0.1]), [0.7, 0.05, 0.05]);
+        assert_lab(Adjustment::Brightness(-25.0).apply([0.4, 0.1, 0.1]), [0.3, 0.075, 0.075]);
+
+        assert_eq!(adjusted("7aa2f7", "brightness:100").to_hex(), "ffffffff");
+        assert_eq!(adjusted("7aa2f7", "brightness:-100").to_hex(), "000000ff");
+    }
+
+    #[test]
+    fn contrast_spreads_the_lightness_around_the_middle() {
+        assert_lab(Adjustment::Contrast(100.0).apply([0.6, 0.1, 0.0]), [0.7, 0.1, 0.0]);
+        assert_lab(Adjustment::Contrast(-50.0).apply([0.2, 0.1, 0.0]), [0.35, 0.1, 0.0]);
+        assert_lab(Adjustment::Contrast(-100.0).apply([0.9, 0.1, 0.0]), [0.5, 0.1, 0.0]);
+    }
+
+    #[test]
+    fn temperature_shifts_along_the_yellow_blue_axis() {
+        assert_lab(Adjustment::Temperature(100.0).apply([0.5, 0.0, 0.0]), [0.5, 0.01, 0.04]);
+        assert_lab(Adjustment::Temperature(-50.0).apply([0.5, 0.0, 0.0]), [0.5, -0.005, -0.02]);
+
+        // A warmed up grey turns yellowish, a cooled down one bluish
+        let [_, _, warm] = adjusted("808080", "temperature:100").to_oklab();
+        let [_, _, cold] = adjusted("808080", "temperature:-100").to_oklab();
+        assert!(warm > 0.03 && cold < -0.03, "{warm} {cold}");
+    }
+
+    #[test]
+    fn hue_rotates_without_changing_the_chroma() {
+        assert_lab(Adjustment::Hue(90.0).apply([0.5, 0.1, 0.0]), [0.5, 0.0, 0.1]);
+        assert_lab(Adjustment::Hue(180.0).apply([0.5, 0.1, -0.05]), [0.5, -0.1, 0.05]);
+
+        let original = Color::from_hex("bb9af7").unwrap().to_oklch();
+        let rotated = adjusted("bb9af7", "hue:30").to_oklch();
+        assert!((rotated[1] - original[1]).abs() < 2e-3, "{original:?} {rotated:?}");
+        assert!((rotated[2] - (original[2] + 30.0).rem_euclid(360.0)).abs() < 1.0, "{original:?} {rotated:?}");
+
+        assert_eq!(adjusted("bb9af7", "hue:360").to_hex(), "bb9af7ff");
+    }
+
+    #[test]
+    fn adjustments_are_chained_in_order() {
+        // Desaturating last leaves a grey, desaturating first lets the temperature tint it
+        assert!(adjusted("808080", "temperature:100,saturation:-100").to_oklch()[1] < 1e-3);
+        assert!(adjusted("808080", "saturation:-100,temperature:100").to_oklch()[1] > 0.03);
+
+        let alpha = adjust_color(&Color::from_hex("7aa2f780").unwrap(), &[Adjustment::Brightness(50.0)]);
+        assert!(alpha.to_hex().ends_with("80"), "{}", alpha.to_hex());
+    }
+
+    #[test]
+    fn adjustments_are_parsed_and_printed_back() {
+        for adjustment in ["saturation:-30", "saturation:150", "brightness:10", "contrast:-100", "temperature:25", "hue:-15"] {
+            assert_eq!(adjustment.parse::<Adjustment>().unwrap().to_string(), adjustment);
+        }
+        for adjustment in ["saturation:-101", "brightness:101", "contrast:x", "temperature", "sepia:10"] {
+            assert!(adjustment.parse::<Adjustment>().is_err(), "{adjustment}");
+        }
+    }
+
+    #[test]
+    fn roles_can_be_kept_and_shared_entries_are_adjusted_once() {
+        // The readers share the entries of the repeated colors
+        let mut palette = crate::palette::fixtures::parsed(&crate::palette::fixtures::DARK);
+        palette.insert(String::from("color9"), palette["color1"].clone());
+        let (background, accent) = (palette["color0"].borrow().clone(), palette["color1"].borrow().clone());
+
+        adjust_palette(&palette, &[Adjustment::Brightness(50.0)], true);
+
+        assert_eq!(palette["color0"].borrow().to_hex(), background.to_hex());
+        assert_eq!(palette["color1"].borrow().to_hex(), adjust_color(&accent, &[Adjustment::Brightness(50.0)]).to_hex());
+        assert!(Rc::ptr_eq(&palette["color1"], &palette["color9"]));
+    }
+}
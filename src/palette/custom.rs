@@ -19,15 +19,15 @@ pub fn import_custom_palette(filepath: &str) -> HashMap<String, Rc<RefCell<Color
         panic!("Palette file '{}' not found", path.to_str().unwrap());
     }
 
-    let palette_file = fs::read_to_string(filepath.clone())
-        .expect(format!("Couldn't read the palette file: {}", filepath).as_str());
+    let palette_file = fs::read_to_string(filepath)
+        .unwrap_or_else(|_| panic!("Couldn't read the palette file: {}", filepath));
 
     let mut palette = HashMap::new();
 
     let re = Regex::new(r##"\s*color(?<ind>\d+)\s*=\s*['"]#(?<hex>(?:\d{6}|\d{8}))['"]\s*"##).unwrap(); // Needs ##" to allow # in the string
 
     let mut max_index = 0;
-    for (_, line) in palette_file.lines().into_iter().enumerate() {
+    for line in palette_file.lines() {
 
         if let Some(color) = re.captures(line) {
             max_index = max_index.max(color["ind"].parse().expect("Unexpected error, the color index is not an number for some reason."));
@@ -42,5 +42,5 @@ pub fn import_custom_palette(filepath: &str) -> HashMap<String, Rc<RefCell<Color
         panic!("The palette has {} colors but needs at least 16.", palette.len());
     }
 
-    return palette;
+    palette
 }
@@ -0,0 +1,36 @@
+// Palettes for the tests
+use std::{collections::HashMap, rc::Rc, cell::RefCell};
+
+use crate::color::Color;
+
+use super::Mode;
+
+// tokyonight as wal writes it, the bright colors repeat the normal ones
+pub static DARK: [&str; 16] = [
+    "1a1b26", "f7768e", "9ece6a", "e0af68", "7aa2f7", "bb9af7", "7dcfff", "a9b1d6",
+    "414868", "f7768e", "9ece6a", "e0af68", "7aa2f7", "bb9af7", "7dcfff", "c0caf5",
+];
+
+// A palette with grey entries where the mapping picks its accents, color1, color2, color9 and color10
+pub static GREY: [&str; 16] = [
+    "1c1c1c", "5f5f5f", "6a6a6a", "d75f00", "5f87af", "af5f87", "5f8787", "d0d0d0",
+    "4e4e4e", "707070", "7a7a7a", "ff8700", "87afd7", "d787af", "87afaf", "eeeeee",
+];
+
+// Accents of opposite hues in pairs, the average of the palette is a mid grey
+pub static BALANCED: [&str; 16] = [
+    "303030", "b05050", "50b050", "b0b050", "5050b0", "b050b0", "50b0b0", "c0c0c0",
+    "505050", "c06060", "60c060", "c0c060", "6060c0", "c060c0", "60c0c0", "e0e0e0",
+];
+
+// The palette as the readers give it, every entry in its own color
+pub fn parsed(hexes: &[&str]) -> HashMap<String, Rc<RefCell<Color>>> {
+    hexes.iter().enumerate()
+        .map(|(index, hex)| (format!("color{index}"), Rc::new(RefCell::new(Color::from_hex(hex).unwrap()))))
+        .collect()
+}
+
+// The palette ready for a theme
+pub fn prepared(hexes: &[&str], mode: Mode) -> HashMap<String, Rc<RefCell<Color>>> {
+    super::prepare_palette(&parsed(hexes), mode, &[], false)
+}
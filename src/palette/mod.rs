@@ -1,5 +1,8 @@
+pub mod adjust;
 mod wal;
 mod custom;
+#[cfg(test)]
+pub mod fixtures;
 
 use std::{collections::HashMap, rc::Rc, cell::RefCell};
 
@@ -7,27 +10,105 @@ use crate::color::Color;
 
 pub static PALETTE_FILENAME: &str = "colors.tdesktop-theme";
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, clap::ValueEnum, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum Palette {
     Wal,
     Custom,
 }
 
-pub fn generate_palette(palette_type: Palette, filepath: Option<&str>) -> HashMap<String, Rc<RefCell<Color>>> {
-    let palette;
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    Dark,
+    Light,
+}
+
+// Parse the palette file, the result is shared between profiles and never modified
+pub fn read_palette(palette_type: Palette, filepath: Option<&str>) -> HashMap<String, Rc<RefCell<Color>>> {
     match palette_type {
         Palette::Wal => {
-            palette = wal::import_wal_palette(filepath);
+            wal::import_wal_palette(filepath)
         }
         Palette::Custom => {
             if filepath.is_none() {
                 panic!("Filepath with custom palette needs a path")
             }
-            palette = custom::import_custom_palette(filepath.unwrap());
+            custom::import_custom_palette(filepath.unwrap())
         }
     }
+}
+
+// The file the palette is read from
+pub fn palette_path(palette_type: Palette, filepath: Option<&str>) -> String {
+    match palette_type {
+        Palette::Wal => wal::colors_path(filepath),
+        Palette::Custom => String::from(filepath.unwrap_or_default()),
+    }
+}
+
+// Copy a parsed palette and get it ready for a theme, the copy doesn't share any color with the
+// parsed one so that a theme can't change the colors of another
+// - adjustments are applied once the roles match the mode, keep_roles leaves the background and
+//   foreground entries out of them
+pub fn prepare_palette(parsed: &HashMap<String, Rc<RefCell<Color>>>, mode: Mode, adjustments: &[adjust::Adjustment], keep_roles: bool) -> HashMap<String, Rc<RefCell<Color>>> {
+    // Entries sharing a color keep sharing it in the copy
+    let mut copies: HashMap<*const RefCell<Color>, Rc<RefCell<Color>>> = HashMap::new();
+    let mut palette = HashMap::new();
+    for (name, color) in parsed {
+        let copy = copies.entry(Rc::as_ptr(color))
+            .or_insert_with(|| Rc::new(RefCell::new(color.borrow().clone())));
+        palette.insert(name.clone(), copy.clone());
+    }
+
+    apply_mode(&mut palette, mode);
+    adjust::adjust_palette(&palette, adjustments, keep_roles);
 
     // Generate contrasts
     super::color::compute_contrasts(&palette);
 
-    return palette;
+    palette
+}
+
+// Swap the background and foreground roles (color0 <-> color15 and color7 <-> color8) when the
+// palette doesn't already match the mode, like 'wal -l' does
+fn apply_mode(palette: &mut HashMap<String, Rc<RefCell<Color>>>, mode: Mode) {
+    let bg_luma = palette["color0"].borrow().get_relative_luminance();
+    let fg_luma = palette["color15"].borrow().get_relative_luminance();
+
+    let is_light = bg_luma > fg_luma;
+    if is_light == (mode == Mode::Light) {
+        return;
+    }
+
+    for (first, second) in [("color0", "color15"), ("color7", "color8")] {
+        let first_color = palette[first].clone();
+        let second_color = palette.insert(String::from(second), first_color).unwrap();
+        palette.insert(String::from(first), second_color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adjustments_do_not_leak_into_the_next_profile() {
+        let parsed = fixtures::parsed(&fixtures::DARK);
+        let hexes = |palette: &HashMap<String, Rc<RefCell<Color>>>| {
+            let mut hexes: Vec<(String, String)> = palette.iter().map(|(name, color)| (name.clone(), color.borrow().to_hex())).collect();
+            hexes.sort();
+            hexes
+        };
+
+        let alone = prepare_palette(&fixtures::parsed(&fixtures::DARK), Mode::Dark, &[], false);
+
+        // Both profiles use the same parsed palette
+        let adjusted = prepare_palette(&parsed, Mode::Light, &[adjust::Adjustment::Saturation(-100.0)], false);
+        let unchanged = prepare_palette(&parsed, Mode::Dark, &[], false);
+
+        assert_ne!(hexes(&adjusted), hexes(&alone));
+        assert_eq!(hexes(&unchanged), hexes(&alone));
+        assert_eq!(hexes(&parsed), hexes(&fixtures::parsed(&fixtures::DARK)));
+    }
 }
@@ -8,44 +8,10 @@ use crate::color::Color;
 
 pub fn import_wal_palette(wal_colors_path: Option<&str>) -> HashMap<String, Rc<RefCell<Color>>> {
 
-    let filepath;
-    if wal_colors_path.is_none() {
-        // Try to get wal from the default location
-        if let Ok(xdg_dirs) = xdg::BaseDirectories::new() {
-            filepath = String::from(xdg_dirs.find_cache_file("wal/colors")
-                                            .expect(format!("Wal 'colors' file not found in default location: {}", xdg_dirs.get_cache_home().display()).as_str()).to_str()
-                                            .expect("Unexpected error, colors filename is not UTF-8"));
-        }
-        else {
-            // Problem initializing xdg_dirs, try manually
-            let path = path::Path::new("~/.cache/colors");
-
-            if !path.exists() {
-                panic!("Wal 'colors' file not found in default location: {}", path.parent().unwrap().display());
-            }
-
-            filepath = String::from(path.to_str().expect("Unexpected error, colors filename is not UTF-8"));
-        };
-    }
-    else
-    {
-        if wal_colors_path.unwrap().is_empty() {
-            panic!("wal color path can not be an empty string");
-        }
-
-        let mut path = path::PathBuf::new();
-        path.push(wal_colors_path.unwrap());
-        path.push("/colors");
-
-        if !path.exists() {
-            panic!("Wal 'colors' file not found in path: {}", path.parent().unwrap().display());
-        }
-
-        filepath = String::from(path.to_str().expect("Unexpected error, colors filename is not UTF-8"));
-    }
+    let filepath = colors_path(wal_colors_path);
 
     let palette_file = fs::read_to_string(filepath.clone())
-        .expect(format!("Unexpected error, Couldn't read the file {}", filepath).as_str());
+        .unwrap_or_else(|_| panic!("Unexpected error, Couldn't read the file {}", filepath));
 
     let mut palette = HashMap::new();
 
@@ -53,7 +19,7 @@ pub fn import_wal_palette(wal_colors_path: Option<&str>) -> HashMap<String, Rc<R
     let mut colors_vec: Vec<(f32, Rc<RefCell<Color>>)> = Vec::new();
     
     let mut color_vec_count = 0;
-    for (index, col) in palette_file.lines().into_iter().enumerate() {
+    for (index, col) in palette_file.lines().enumerate() {
         // If one of the main bg or fg colors
         if index == 0 || index == 7 || index == 8 || index == 15 {
             palette.insert(format!("color{index}"), Rc::new(RefCell::new(Color::from_hex(col).unwrap())));
@@ -77,11 +43,52 @@ pub fn import_wal_palette(wal_colors_path: Option<&str>) -> HashMap<String, Rc<R
     // Double colors if not wal16
     if palette.len() < 16 {
         let len = palette.len();
-        for (index, (_, col)) in palette.clone().into_iter().enumerate() {
-            let ind = len + index;
-            palette.insert(format!("color{ind}"), col.clone());
+        for index in 0..len {
+            let col = palette[format!("color{index}").as_str()].clone();
+            palette.insert(format!("color{}", len + index), col);
+        }
+    }
+
+    palette
+}
+
+// Get the path of the 'colors' file in the wal cache
+pub fn colors_path(wal_colors_path: Option<&str>) -> String {
+
+    let filepath;
+    if let Some(wal_colors_path) = wal_colors_path {
+        if wal_colors_path.is_empty() {
+            panic!("wal color path can not be an empty string");
+        }
+
+        let mut path = path::PathBuf::new();
+        path.push(wal_colors_path);
+        path.push("colors");
+
+        if !path.exists() {
+            panic!("Wal 'colors' file not found in path: {}", path.parent().unwrap().display());
+        }
+
+        filepath = String::from(path.to_str().expect("Unexpected error, colors filename is not UTF-8"));
+    }
+    else {
+        // Try to get wal from the default location
+        if let Ok(xdg_dirs) = xdg::BaseDirectories::new() {
+            filepath = String::from(xdg_dirs.find_cache_file("wal/colors")
+                                            .unwrap_or_else(|| panic!("Wal 'colors' file not found in default location: {}", xdg_dirs.get_cache_home().display())).to_str()
+                                            .expect("Unexpected error, colors filename is not UTF-8"));
         }
+        else {
+            // Problem initializing xdg_dirs, try manually
+            let path = path::Path::new("~/.cache/colors");
+
+            if !path.exists() {
+                panic!("Wal 'colors' file not found in default location: {}", path.parent().unwrap().display());
+            }
+
+            filepath = String::from(path.to_str().expect("Unexpected error, colors filename is not UTF-8"));
+        };
     }
 
-    return palette;
+    filepath
 }
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+use image::{imageops, DynamicImage, RgbaImage};
+
+use crate::color::Color;
+use crate::telegram::colors::ThemeLine;
+
+pub static PREVIEW_EXTENSION: &str = "png";
+
+// Layout of the mockup, roughly the one of Telegram Desktop
+static WIDTH: u32 = 1000;
+static HEIGHT: u32 = 640;
+static DIALOGS_WIDTH: u32 = 300;
+static TOP_BAR_HEIGHT: u32 = 54;
+static COMPOSE_HEIGHT: u32 = 54;
+static ROW_HEIGHT: u32 = 62;
+// Text is drawn as bars of this height
+static TEXT_HEIGHT: u32 = 8;
+
+// Messages of the chat, outgoing or not and the width of each line
+static MESSAGES: [(bool, &[u32]); 5] = [
+    (false, &[220, 160]),
+    (true, &[180]),
+    (false, &[260, 200, 120]),
+    (true, &[240, 150]),
+    (true, &[90]),
+];
+
+struct Canvas {
+    image: RgbaImage,
+    colors: HashMap<String, Color>,
+}
+
+impl Canvas {
+    fn color(&self, key: &str) -> Color {
+        self.colors.get(key).cloned()
+            .unwrap_or_else(|| panic!("Unexpected error, the key '{key}' is not in the theme"))
+    }
+
+    // Blend the color over a pixel, coverage is the part of the pixel covered by the shape
+    fn blend(&mut self, x: u32, y: u32, color: &Color, coverage: f32) {
+        if x >= self.image.width() || y >= self.image.height() {
+            return;
+        }
+
+        let alpha = color.alpha * coverage.clamp(0.0, 1.0);
+        let pixel = self.image.get_pixel_mut(x, y);
+        for (channel, value) in pixel.0.iter_mut().zip([color.red, color.green, color.blue]) {
+            *channel = (*channel as f32 * (1.0 - alpha) + value * 255.0 * alpha).round() as u8;
+        }
+    }
+
+    fn rect(&mut self, x: u32, y: u32, width: u32, height: u32, key: &str) {
+        let color = self.color(key);
+        for py in y..(y + height) {
+            for px in x..(x + width) {
+                self.blend(px, py, &color, 1.0);
+            }
+        }
+    }
+
+    // Antialiased rectangle with rounded corners
+    fn round_rect(&mut self, x: u32, y: u32, width: u32, height: u32, radius: f32, key: &str) {
+        let color = self.color(key);
+        let radius = radius.min(width as f32 / 2.0).min(height as f32 / 2.0);
+        let (left, top) = (x as f32 + radius, y as f32 + radius);
+        let (right, bottom) = ((x + width) as f32 - radius, (y + height) as f32 - radius);
+
+        for py in y..(y + height) {
+            for px in x..(x + width) {
+                // Distance from the pixel center to the rectangle shrunk by the radius
+                let (cx, cy) = (px as f32 + 0.5, py as f32 + 0.5);
+                let dx = (left - cx).max(cx - right).max(0.0);
+                let dy = (top - cy).max(cy - bottom).max(0.0);
+                let distance = (dx * dx + dy * dy).sqrt();
+
+                self.blend(px, py, &color, radius - distance + 0.5);
+            }
+        }
+    }
+
+    fn circle(&mut self, x: u32, y: u32, diameter: u32, key: &str) {
+        self.round_rect(x, y, diameter, diameter, diameter as f32 / 2.0, key);
+    }
+
+    // A line of text
+    fn text(&mut self, x: u32, y: u32, width: u32, key: &str) {
+        self.round_rect(x, y, width, TEXT_HEIGHT, TEXT_HEIGHT as f32 / 2.0, key);
+    }
+}
+
+// Draw the mockup
+// - colors are the Telegram keys with their colors
+// - background is the chat background
+pub fn render(colors: &[ThemeLine], background: &DynamicImage) -> RgbaImage {
+    let mut canvas = Canvas {
+        image: RgbaImage::from_pixel(WIDTH, HEIGHT, image::Rgba([0, 0, 0, 255])),
+        colors: colors.iter()
+            .filter_map(|line| line.color.as_ref().map(|color| (line.name.clone(), color.clone())))
+            .collect(),
+    };
+
+    draw_dialogs(&mut canvas);
+    draw_chat(&mut canvas, background);
+    draw_menu(&mut canvas);
+
+    canvas.image
+}
+
+fn draw_dialogs(canvas: &mut Canvas) {
+    canvas.rect(0, 0, DIALOGS_WIDTH, HEIGHT, "dialogsBg");
+
+    // Menu button and search field
+    for line in 0..3 {
+        canvas.round_rect(18, 20 + line * 6, 18, 2, 1.0, "dialogsMenuIconFg");
+    }
+    canvas.round_rect(56, 11, 228, 32, 16.0, "filterInputInactiveBg");
+    canvas.text(72, 23, 60, "placeholderFg");
+
+    // Normal rows, the second one is hovered and the third one is active
+    let userpics = ["historyPeer1UserpicBg", "historyPeer2UserpicBg", "historyPeer3UserpicBg", "historyPeer4UserpicBg"];
+    for row in 0..8 {
+        let y = TOP_BAR_HEIGHT + row * ROW_HEIGHT;
+        let state = match row {
+            1 => "Over",
+            2 => "Active",
+            _ => "",
+        };
+
+        canvas.rect(0, y, DIALOGS_WIDTH, ROW_HEIGHT, format!("dialogsBg{state}").as_str());
+
+        canvas.circle(10, y + 9, 44, userpics[row as usize % userpics.len()]);
+        canvas.text(26, y + 27, 12, "historyPeerUserpicFg");
+
+        canvas.text(64, y + 16, 90 + (row * 37) % 60, format!("dialogsNameFg{state}").as_str());
+        canvas.text(64, y + 38, 150 + (row * 23) % 40, format!("dialogsTextFg{state}").as_str());
+        canvas.text(254, y + 16, 30, format!("dialogsDateFg{state}").as_str());
+
+        // Unread counters, muted or not
+        if row == 0 || row == 4 {
+            let key = if row == 0 { "dialogsUnreadBg" } else { "dialogsUnreadBgMuted" };
+            canvas.round_rect(262, y + 32, 22, 20, 10.0, key);
+            canvas.text(269, y + 38, 8, "dialogsUnreadFg");
+        }
+    }
+
+    canvas.rect(DIALOGS_WIDTH, 0, 1, HEIGHT, "shadowFg");
+}
+
+fn draw_chat(canvas: &mut Canvas, background: &DynamicImage) {
+    let x = DIALOGS_WIDTH + 1;
+    let width = WIDTH - x;
+    let history_height = HEIGHT - TOP_BAR_HEIGHT - COMPOSE_HEIGHT;
+
+    // Background covering the history
+    let background = background.resize_to_fill(width, history_height, imageops::FilterType::Triangle).to_rgba8();
+    imageops::overlay(&mut canvas.image, &background, x as i64, TOP_BAR_HEIGHT as i64);
+
+    // Top bar with the chat name and status
+    canvas.rect(x, 0, width, TOP_BAR_HEIGHT, "topBarBg");
+    canvas.text(x + 20, 15, 140, "windowBoldFg");
+    canvas.text(x + 20, 33, 90, "windowSubTextFg");
+    for icon in 0..3 {
+        canvas.circle(WIDTH - 90 + icon * 26, 19, 16, "menuIconFg");
+    }
+    canvas.rect(x, TOP_BAR_HEIGHT, width, 1, "shadowFg");
+
+    // Service message
+    let center = x + width / 2;
+    canvas.round_rect(center - 60, TOP_BAR_HEIGHT + 14, 120, 24, 12.0, "msgServiceBg");
+    canvas.text(center - 40, TOP_BAR_HEIGHT + 22, 80, "msgServiceFg");
+
+    let mut y = TOP_BAR_HEIGHT + 52;
+    for (index, (outgoing, lines)) in MESSAGES.iter().enumerate() {
+        let side = if *outgoing { "Out" } else { "In" };
+        let bubble_width = lines.iter().max().unwrap() + 24 + 40;
+        let bubble_height = lines.len() as u32 * 18 + 24;
+        let bubble_x = if *outgoing { WIDTH - 20 - bubble_width } else { x + 20 };
+
+        canvas.round_rect(bubble_x, y + 1, bubble_width, bubble_height, 12.0, format!("msg{side}Shadow").as_str());
+        canvas.round_rect(bubble_x, y, bubble_width, bubble_height, 12.0, format!("msg{side}Bg").as_str());
+
+        for (line, line_width) in lines.iter().enumerate() {
+            // The last line of the third message is a link
+            let key = if index == 2 && line + 1 == lines.len() { format!("historyLink{side}Fg") } else { format!("historyText{side}Fg") };
+            canvas.text(bubble_x + 12, y + 12 + line as u32 * 18, *line_width, key.as_str());
+        }
+
+        let date_y = y + bubble_height - 14;
+        canvas.text(bubble_x + bubble_width - 40, date_y, 24, format!("msg{side}DateFg").as_str());
+        if *outgoing {
+            canvas.text(bubble_x + bubble_width - 14, date_y, 8, "historyOutIconFg");
+        }
+
+        y += bubble_height + 10;
+    }
+
+    // Compose area
+    let compose_y = HEIGHT - COMPOSE_HEIGHT;
+    canvas.rect(x, compose_y, width, COMPOSE_HEIGHT, "historyComposeAreaBg");
+    canvas.rect(x, compose_y, width, 1, "shadowFg");
+    canvas.circle(x + 18, compose_y + 16, 22, "historyComposeIconFg");
+    canvas.text(x + 56, compose_y + 23, 150, "historyComposeAreaFgService");
+    canvas.circle(WIDTH - 80, compose_y + 16, 22, "historyComposeIconFg");
+    canvas.circle(WIDTH - 42, compose_y + 16, 22, "historySendIconFg");
+}
+
+// Menu opened from the top bar, with its second item hovered
+fn draw_menu(canvas: &mut Canvas) {
+    let (x, y, width) = (WIDTH - 250, TOP_BAR_HEIGHT - 4, 230);
+    let items = 4;
+    let height = items * 36 + 9 + 16;
+
+    canvas.round_rect(x - 1, y, width + 2, height + 2, 8.0, "shadowFg");
+    canvas.round_rect(x, y, width, height, 8.0, "menuBg");
+
+    let mut item_y = y + 8;
+    for item in 0..items {
+        // Separator before the last item
+        if item == items - 1 {
+            canvas.rect(x, item_y + 4, width, 1, "menuSeparatorFg");
+            item_y += 9;
+        }
+
+        let hovered = item == 1;
+        if hovered {
+            canvas.rect(x, item_y, width, 36, "menuBgOver");
+        }
+        canvas.circle(x + 16, item_y + 9, 18, if hovered { "menuIconFgOver" } else { "menuIconFg" });
+        canvas.text(x + 48, item_y + 14, 80 + (item * 29) % 50, "windowFg");
+
+        item_y += 36;
+    }
+}
+
+// Encode the mockup as a PNG
+pub fn encode_preview(preview: RgbaImage) -> Vec<u8> {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    DynamicImage::ImageRgba8(preview).write_to(&mut bytes, image::ImageOutputFormat::Png)
+        .expect("Unexpected error, could not encode the preview as PNG");
+
+    bytes.into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::{fixtures, Mode};
+
+    static GOLDEN_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/preview.png");
+
+    // Run with UPDATE_GOLDEN=1 to write the golden image again after changing the layout on purpose
+    #[test]
+    fn the_layout_matches_the_golden_image() {
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+        let colors = crate::telegram::colors::compute_telegram_colors(&palette, &crate::telegram::colors::Mapping::default());
+        let background = DynamicImage::ImageRgb8(image::RgbImage::from_fn(320, 200, |x, y| image::Rgb([(x / 2) as u8, y as u8, 96])));
+        let preview = render(&colors, &background);
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(std::path::Path::new(GOLDEN_PATH).parent().unwrap()).unwrap();
+            std::fs::write(GOLDEN_PATH, encode_preview(preview.clone())).unwrap();
+        }
+
+        let golden = image::open(GOLDEN_PATH).expect("The golden image is missing, run the test with UPDATE_GOLDEN=1").to_rgba8();
+        assert_eq!(preview.dimensions(), golden.dimensions());
+        // Off by one channel values are rounding, anything more is a change of the layout or colors
+        let different = preview.pixels().zip(golden.pixels())
+            .filter(|(pixel, golden)| pixel.0.iter().zip(golden.0).any(|(value, golden)| value.abs_diff(golden) > 1))
+            .count();
+        assert_eq!(different, 0, "{different} pixels differ from the golden image");
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::{HashMap, HashSet};
+
+// Keys of Telegram Desktop, from its colors.palette
+static BUNDLED_PALETTE: &str = include_str!("colors.palette");
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DefaultValue {
+    // Hex color without the '#'
+    Color(String),
+    // Same color as another key
+    Key(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CanonicalKey {
+    pub name: String,
+    pub default: DefaultValue,
+    // Key copied instead of the default when the theme sets it
+    pub fallback: Option<String>,
+}
+
+// What Telegram uses for a key the theme doesn't set
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resolution {
+    // Copy of a key set by the theme
+    Key(String),
+    // Telegram's own default, made for its day theme
+    Default(String),
+}
+
+fn parse_value(value: &str, line_number: usize) -> Result<DefaultValue, String> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Line {line_number}: '{value}' is not a valid color"));
+        }
+        return Ok(DefaultValue::Color(String::from(hex)));
+    }
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("Line {line_number}: '{value}' is neither a color nor a key"));
+    }
+
+    Ok(DefaultValue::Key(String::from(value)))
+}
+
+// Parse a colors.palette file, 'key: value;' or 'key: #color | fallbackKey;' with // comments
+pub fn parse_palette(contents: &str) -> Result<Vec<CanonicalKey>, String> {
+    let mut keys: Vec<CanonicalKey> = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.split("//").next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (name, value) = line.split_once(':')
+            .ok_or_else(|| format!("Line {line_number}: expected 'key: value;'"))?;
+        let value = value.trim().strip_suffix(';')
+            .ok_or_else(|| format!("Line {line_number}: missing ';'"))?;
+        let name = name.trim();
+
+        let (default, fallback) = match value.split_once('|') {
+            Some((default, fallback)) => {
+                let DefaultValue::Key(fallback) = parse_value(fallback, line_number)? else {
+                    return Err(format!("Line {line_number}: the fallback of '{name}' must be a key"));
+                };
+                (parse_value(default, line_number)?, Some(fallback))
+            }
+            None => (parse_value(value, line_number)?, None),
+        };
+
+        if keys.iter().any(|key| key.name == name) {
+            return Err(format!("Line {line_number}: '{name}' is defined twice"));
+        }
+        keys.push(CanonicalKey { name: String::from(name), default, fallback });
+    }
+
+    // References must point to keys defined before, like in Telegram
+    for (index, key) in keys.iter().enumerate() {
+        let references = [key.fallback.as_ref(), match &key.default { DefaultValue::Key(name) => Some(name), _ => None }];
+        for reference in references.into_iter().flatten() {
+            if !keys[..index].iter().any(|other| &other.name == reference) {
+                return Err(format!("'{}' refers to '{}', which is not defined before it", key.name, reference));
+            }
+        }
+    }
+
+    Ok(keys)
+}
+
+pub fn bundled_keys() -> Vec<CanonicalKey> {
+    parse_palette(BUNDLED_PALETTE).unwrap_or_else(|err| panic!("Unexpected error, invalid bundled colors.palette: {err}"))
+}
+
+// The bundled keys, or the ones of a colors.palette from a Telegram Desktop checkout
+pub fn read_keys(path: Option<&str>) -> Result<Vec<CanonicalKey>, String> {
+    let Some(path) = path else {
+        return Ok(bundled_keys());
+    };
+
+    let contents = std::fs::read_to_string(path).map_err(|err| format!("Couldn't read '{}': {}", path, err))?;
+    parse_palette(&contents).map_err(|err| format!("Invalid palette '{}': {}", path, err))
+}
+
+// Follow Telegram's fallbacks for a key the theme doesn't set
+// - set are the keys the theme sets
+pub fn resolve(keys: &[CanonicalKey], name: &str, set: &HashSet<&str>) -> Resolution {
+    let by_name: HashMap<&str, &CanonicalKey> = keys.iter().map(|key| (key.name.as_str(), key)).collect();
+
+    let mut current = by_name[name];
+    loop {
+        if let Some(fallback) = current.fallback.as_deref().filter(|fallback| set.contains(fallback)) {
+            return Resolution::Key(String::from(fallback));
+        }
+
+        match &current.default {
+            DefaultValue::Color(hex) => return Resolution::Default(hex.clone()),
+            DefaultValue::Key(other) if set.contains(other.as_str()) => return Resolution::Key(other.clone()),
+            // References only go to earlier keys so this ends
+            DefaultValue::Key(other) => current = by_name[other.as_str()],
+        }
+    }
+}
+
+// Compare the keys set by the mapping with the ones of Telegram, returns whether they match
+pub fn print_check(keys: &[CanonicalKey], set: &[&str]) -> bool {
+    let canonical: HashSet<&str> = keys.iter().map(|key| key.name.as_str()).collect();
+    let set_keys: HashSet<&str> = set.iter().copied().collect();
+
+    let unknown: Vec<&str> = set.iter().copied()
+        .filter(|name| !canonical.contains(name) && !super::colors::is_helper_key(name))
+        .collect();
+    let missing: Vec<(&str, Resolution)> = keys.iter()
+        .filter(|key| !set_keys.contains(key.name.as_str()))
+        .map(|key| (key.name.as_str(), resolve(keys, &key.name, &set_keys)))
+        .collect();
+    let defaulted: Vec<&(&str, Resolution)> = missing.iter().filter(|(_, resolution)| matches!(resolution, Resolution::Default(_))).collect();
+
+    let telegram_set = set.iter().filter(|name| !super::colors::is_helper_key(name)).count();
+    println!("{} keys set, {} keys defined by Telegram", telegram_set, keys.len());
+
+    if !unknown.is_empty() {
+        println!("Keys set that Telegram doesn't define:");
+        for name in &unknown {
+            println!("  {name}");
+        }
+    }
+
+    if missing.len() > defaulted.len() {
+        println!("Keys not set, following Telegram's fallbacks:");
+        for (name, resolution) in &missing {
+            if let Resolution::Key(other) = resolution {
+                println!("  {name} -> {other}");
+            }
+        }
+    }
+
+    if !defaulted.is_empty() {
+        println!("Keys not set, silently using Telegram's default:");
+        for (name, resolution) in &defaulted {
+            if let Resolution::Default(hex) = resolution {
+                println!("  {name} #{hex}");
+            }
+        }
+    }
+
+    unknown.is_empty() && defaulted.is_empty()
+}
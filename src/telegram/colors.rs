@@ -1,1090 +1,2139 @@
-use std::{collections::HashMap, rc::Rc, cell::RefCell};
+use std::{collections::{BTreeMap, HashMap, HashSet}, rc::Rc, cell::RefCell, fmt, str::FromStr};
+use crate::background::analysis::Analysis;
 use crate::color::Color;
+use super::canonical::{self, Resolution};
+use super::expr::{hex, key, palette, Expr};
+
+// Main text keys and the background they are drawn on
+pub static TEXT_PAIRS: [(&str, &str); 30] = [
+    ("windowFg", "windowBg"),
+    ("windowFgOver", "windowBgOver"),
+    ("windowBoldFg", "windowBg"),
+    ("windowBoldFgOver", "windowBgOver"),
+    ("windowFgActive", "windowBgActive"),
+    ("activeButtonFg", "activeButtonBg"),
+    ("lightButtonFg", "lightButtonBg"),
+    ("tooltipFg", "tooltipBg"),
+    ("boxTextFg", "boxBg"),
+    ("boxTitleFg", "boxBg"),
+    ("contactsNameFg", "contactsBg"),
+    ("introTitleFg", "introBg"),
+    ("dialogsNameFg", "dialogsBg"),
+    ("dialogsTextFg", "dialogsBg"),
+    ("dialogsNameFgOver", "dialogsBgOver"),
+    ("dialogsTextFgOver", "dialogsBgOver"),
+    ("dialogsNameFgActive", "dialogsBgActive"),
+    ("dialogsTextFgActive", "dialogsBgActive"),
+    ("dialogsUnreadFg", "dialogsUnreadBg"),
+    ("historyTextInFg", "msgInBg"),
+    ("historyTextOutFg", "msgOutBg"),
+    ("historyTextInFgSelected", "msgInBgSelected"),
+    ("historyTextOutFgSelected", "msgOutBgSelected"),
+    ("msgServiceFg", "msgServiceBg"),
+    ("historyComposeAreaFg", "historyComposeAreaBg"),
+    ("mainMenuCoverFg", "mainMenuCoverBg"),
+    ("toastFg", "toastBg"),
+    ("mediaviewCaptionFg", "mediaviewCaptionBg"),
+    ("sideBarTextFg", "sideBarBg"),
+    ("menuIconFgOver", "menuBgOver"),
+];
+
+// Secondary text keys (dates, statuses, links...) and the background they are drawn on
+pub static SECONDARY_TEXT_PAIRS: [(&str, &str); 16] = [
+    ("windowSubTextFg", "windowBg"),
+    ("windowActiveTextFg", "windowBg"),
+    ("dialogsDateFg", "dialogsBg"),
+    ("dialogsDateFgOver", "dialogsBgOver"),
+    ("dialogsDateFgActive", "dialogsBgActive"),
+    ("contactsStatusFg", "contactsBg"),
+    ("msgInDateFg", "msgInBg"),
+    ("msgOutDateFg", "msgOutBg"),
+    ("msgInServiceFg", "msgInBg"),
+    ("msgOutServiceFg", "msgOutBg"),
+    ("historyLinkInFg", "msgInBg"),
+    ("historyLinkOutFg", "msgOutBg"),
+    ("introDescriptionFg", "introBg"),
+    ("boxTitleAdditionalFg", "boxBg"),
+    ("historyComposeAreaFgService", "historyComposeAreaBg"),
+    ("placeholderFg", "windowBg"),
+];
+
+// Times the high contrast preset goes over the pairs at most
+static HIGH_CONTRAST_PASSES: usize = 5;
+
+// Borders and separators with the background they are drawn on, the high contrast preset makes
+// them stand out (3:1, WCAG non-text contrast)
+pub static BORDER_PAIRS: [(&str, &str); 7] = [
+    ("shadowFg", "windowBg"),
+    ("menuSeparatorFg", "menuBg"),
+    ("inputBorderFg", "windowBg"),
+    ("filterInputBorderFg", "filterInputInactiveBg"),
+    ("tooltipBorderFg", "tooltipBg"),
+    ("checkboxFg", "windowBg"),
+    ("sliderBgInactive", "windowBg"),
+];
+
+// Keys showing a state with the key showing the other state, the high contrast preset sets them
+// apart by their lightness (3:1) and not only by their hue
+pub static STATE_PAIRS: [(&str, &str); 6] = [
+    ("dialogsBgActive", "dialogsBg"),
+    ("dialogsUnreadBg", "dialogsUnreadBgMuted"),
+    ("msgInBgSelected", "msgInBg"),
+    ("msgOutBgSelected", "msgOutBg"),
+    ("activeLineFg", "inputBorderFg"),
+    ("sliderBgActive", "sliderBgInactive"),
+];
+
+// Keys named like text but drawn as lines, shadows or decorations over pictures, along with the
+// borders, they aren't text
+static NON_TEXT_KEYS: [&str; 18] = [
+    "activeLineFg",
+    "activeLineFgError",
+    "outlineButtonOutlineFg",
+    "windowShadowFg",
+    "windowShadowFgFallback",
+    "slideFadeOutShadowFg",
+    "mediaviewFileBlueCornerFg",
+    "mediaviewFileGreenCornerFg",
+    "mediaviewFileRedCornerFg",
+    "mediaviewFileYellowCornerFg",
+    "mediaviewTransparentFg",
+    "songCoverOverlayFg",
+    "historyVideoMessageProgressFg",
+    "photoCropPointFg",
+    "historyPeerUserpicFg",
+    "historyForwardChooseFg",
+    "overviewCheckFg",
+    "overviewCheckFgActive",
+];
+
+// Backgrounds of the text keys whose name doesn't lead to them, by start of the key
+static TEXT_SURFACES: [(&str, &str); 8] = [
+    ("historyPeer", "msgIn"),
+    ("historySendingInverted", "msgDateImg"),
+    ("historySend", "historyComposeArea"),
+    ("historyCompose", "historyComposeArea"),
+    ("historyFileThumb", "msgDateImg"),
+    ("historyIcon", "msgDateImg"),
+    ("emoji", "emojiPan"),
+    ("msgBotKb", "msgService"),
+];
+
+// Options of the palette to Telegram keys mapping
+#[derive(Clone, Debug, Default)]
+pub struct Mapping {
+    // Keys set to a fixed color, the keys derived from them follow
+    pub overrides: BTreeMap<String, Color>,
+    // Minimum contrast of the text keys against their background
+    pub text_contrast: Option<f32>,
+    pub secondary_contrast: Option<f32>,
+    pub bubbles: Bubbles,
+    pub overlays: Overlays,
+    // Color of the active, link, selection and badge keys, the mapping picks them otherwise
+    pub accent: Option<Expr>,
+    pub status: StatusColors,
+    // Borders and states stand out and the adjusted keys are opaque
+    pub high_contrast: bool,
+}
 
-pub fn get_telegram_colors(colors: &HashMap<String, Rc<RefCell<Color>>>) -> String {
-
-    let elements: Rc<RefCell<HashMap<String, Option<Rc<RefCell<Color>>>>>> = Rc::new(RefCell::new(HashMap::new()));
-    let ret: Rc<RefCell<String>> = Rc::new(RefCell::new(String::new()));
+// How the accent is picked in the palette
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Accent {
+    // The most colorful entry
+    Saturated,
+    // The entry contrasting the most with color0, the background
+    Contrast,
+    // The entry whose hue is the closest to the one given in degrees
+    Hue(f32),
+    // A palette entry
+    Role(usize),
+    // A fixed color, in hex without the '#'
+    Hex(String),
+}
 
-    let add_any = |el: &str, col: Option<Rc<RefCell<Color>>>| {
-        let color = col.to_owned();
+impl FromStr for Accent {
+    type Err = String;
 
-        if !col.is_none() {
-            // If it is a Rc<Color>
-            ret.as_ref().borrow_mut().push_str((String::from(el) + ": #" + color.as_ref().unwrap().borrow().to_hex().as_str() + ";\n").as_str());
+    // Parse 'saturated', 'contrast', 'hue:DEGREES', a palette index or a #RRGGBB color
+    fn from_str(accent: &str) -> Result<Self, Self::Err> {
+        let accent = accent.trim();
+        if accent == "saturated" {
+            return Ok(Accent::Saturated);
         }
-        else {
-            // If comment
-            ret.as_ref().borrow_mut().push_str((String::from("\n// ") + el + "\n").as_str());
+        if accent == "contrast" {
+            return Ok(Accent::Contrast);
+        }
+        if let Some(hue) = accent.strip_prefix("hue:") {
+            let hue: f32 = hue.trim().parse().map_err(|_| format!("'{hue}' is not a hue in degrees"))?;
+            return Ok(Accent::Hue(hue.rem_euclid(360.0)));
+        }
+        if let Some(hex) = accent.strip_prefix('#') {
+            if Color::from_hex(hex).is_none() {
+                return Err(format!("'{accent}' is not a #RRGGBB or #RRGGBBAA color"));
+            }
+            return Ok(Accent::Hex(String::from(hex)));
         }
 
-        elements.as_ref().borrow_mut().insert(String::from(el), color);
+        crate::cli::parse_palette_index(accent).map(Accent::Role)
+            .map_err(|_| format!("'{accent}' is neither saturated, contrast, hue:DEGREES, a palette index nor a #RRGGBB color"))
+    }
+}
+
+impl fmt::Display for Accent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Accent::Saturated => write!(f, "saturated"),
+            Accent::Contrast => write!(f, "contrast"),
+            Accent::Hue(hue) => write!(f, "hue:{hue}"),
+            Accent::Role(index) => write!(f, "{index}"),
+            Accent::Hex(hex) => write!(f, "#{hex}"),
+        }
+    }
+}
+
+impl TryFrom<String> for Accent {
+    type Error = String;
+
+    fn try_from(accent: String) -> Result<Self, Self::Error> {
+        accent.parse()
+    }
+}
+
+impl From<Accent> for String {
+    fn from(accent: Accent) -> Self {
+        accent.to_string()
+    }
+}
+
+// The colors of the palette, the background, foreground and grays (0, 7, 8 and 15) are left out
+// unless nothing else is there
+fn chromatic_entries(colors: &HashMap<String, Rc<RefCell<Color>>>) -> Vec<(usize, Color)> {
+    let candidates: Vec<(usize, Color)> = (0..colors.len())
+        .filter(|index| ![0, 7, 8, 15].contains(index))
+        .map(|index| (index, palette(index).eval(colors, &HashMap::new())))
+        .collect();
+    if candidates.is_empty() {
+        return (1..colors.len()).map(|index| (index, palette(index).eval(colors, &HashMap::new()))).collect();
+    }
+    candidates
+}
+
+// Derivation of the accent, the strategies choose among the chromatic entries
+pub fn pick_accent(colors: &HashMap<String, Rc<RefCell<Color>>>, accent: &Accent) -> Expr {
+    let candidates = chromatic_entries(colors);
+    let background = palette(0).eval(colors, &HashMap::new());
+
+    // Index with the highest score, reversed so that ties keep the lowest index, max_by returning
+    // the last maximum
+    let best = |score: &dyn Fn(&Color) -> f32| -> Expr {
+        let (index, _) = candidates.iter().rev()
+            .max_by(|a, b| score(&a.1).total_cmp(&score(&b.1)))
+            .expect("The palette has no color to pick the accent from");
+        palette(*index)
     };
 
-    // Add a color with the pointer directly
-    let add_color = |el: &str, col: Rc<RefCell<Color>>| {
-        add_any(el, Some(col));
+    match accent {
+        Accent::Saturated => best(&|color| color.to_oklch()[1]),
+        Accent::Contrast => best(&|color| color.get_contrast(&background)),
+        Accent::Hue(hue) => best(&|color| {
+            let distance = (color.to_oklch()[2] - hue).rem_euclid(360.0);
+            -distance.min(360.0 - distance)
+        }),
+        Accent::Role(index) => palette(*index),
+        Accent::Hex(color) => hex(color),
+    }
+}
+
+// Palette color the outgoing bubbles are made from
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum BubbleColor {
+    // A palette entry
+    Role(usize),
+    // The entry standing out the most from the background while keeping the text readable
+    Auto,
+}
+
+impl FromStr for BubbleColor {
+    type Err = String;
+
+    // Parse 'auto' or a palette index given as '8' or 'color8'
+    fn from_str(bubble: &str) -> Result<Self, Self::Err> {
+        if bubble.trim() == "auto" {
+            return Ok(BubbleColor::Auto);
+        }
+
+        Ok(BubbleColor::Role(crate::cli::parse_palette_index(bubble)?))
+    }
+}
+
+impl fmt::Display for BubbleColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BubbleColor::Role(index) => write!(f, "{index}"),
+            BubbleColor::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+impl TryFrom<String> for BubbleColor {
+    type Error = String;
+
+    fn try_from(bubble: String) -> Result<Self, Self::Error> {
+        bubble.parse()
+    }
+}
+
+impl From<BubbleColor> for String {
+    fn from(bubble: BubbleColor) -> Self {
+        bubble.to_string()
+    }
+}
+
+// Meaning conveyed by a color
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    Error,
+    Success,
+    Warning,
+    Info,
+}
+
+impl Status {
+    pub const ALL: [Status; 4] = [Status::Error, Status::Success, Status::Warning, Status::Info];
+
+    // Hue of the status in OKLab, in degrees
+    fn hue(&self) -> f32 {
+        match self {
+            Status::Error => 25.0,
+            Status::Success => 142.0,
+            Status::Warning => 90.0,
+            Status::Info => 250.0,
+        }
+    }
+
+    // Color used when nothing better is known
+    fn fallback(&self) -> &'static str {
+        match self {
+            Status::Error => "d55070",
+            Status::Success => "6bf576",
+            Status::Warning => "e5c07b",
+            Status::Info => "61afef",
+        }
+    }
+}
+
+impl FromStr for Status {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status.trim() {
+            "error" => Ok(Status::Error),
+            "success" => Ok(Status::Success),
+            "warning" => Ok(Status::Warning),
+            "info" => Ok(Status::Info),
+            _ => Err(format!("'{status}' is not a status, expected error, success, warning or info")),
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Status::Error => write!(f, "error"),
+            Status::Success => write!(f, "success"),
+            Status::Warning => write!(f, "warning"),
+            Status::Info => write!(f, "info"),
+        }
+    }
+}
+
+// A status pinned to a color, given as 'error=#RRGGBB'
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct StatusColor {
+    pub status: Status,
+    // Hex without the '#'
+    pub color: String,
+}
+
+impl FromStr for StatusColor {
+    type Err = String;
+
+    fn from_str(pin: &str) -> Result<Self, Self::Err> {
+        let (status, color) = pin.split_once('=')
+            .ok_or_else(|| format!("'{pin}' is not a STATUS=#RRGGBB pair"))?;
+        let hex = color.trim().strip_prefix('#').filter(|hex| Color::from_hex(hex).is_some())
+            .ok_or_else(|| format!("'{}' is not a #RRGGBB or #RRGGBBAA color", color.trim()))?;
+
+        Ok(StatusColor { status: status.parse()?, color: String::from(hex) })
+    }
+}
+
+impl fmt::Display for StatusColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}=#{}", self.status, self.color)
+    }
+}
+
+impl TryFrom<String> for StatusColor {
+    type Error = String;
+
+    fn try_from(pin: String) -> Result<Self, Self::Error> {
+        pin.parse()
+    }
+}
+
+impl From<StatusColor> for String {
+    fn from(pin: StatusColor) -> Self {
+        pin.to_string()
+    }
+}
+
+// Derivations of the status colors, in the Status::ALL order
+#[derive(Clone, Debug)]
+pub struct StatusColors([Expr; 4]);
+
+impl Default for StatusColors {
+    fn default() -> Self {
+        StatusColors(Status::ALL.map(|status| hex(status.fallback())))
+    }
+}
+
+impl StatusColors {
+    pub fn get(&self, status: Status) -> Expr {
+        self.0[status as usize].clone()
+    }
+}
+
+// The palette entry with the closest hue in OKLab for each status, when none is within 30° a color
+// of that hue is made at the average lightness and chroma of the palette
+// - pins are the statuses set to a fixed color
+pub fn status_colors(colors: &HashMap<String, Rc<RefCell<Color>>>, pins: &[StatusColor]) -> StatusColors {
+    let candidates: Vec<(usize, [f32; 3])> = chromatic_entries(colors).into_iter()
+        .map(|(index, color)| (index, color.to_oklch()))
+        .collect();
+    let average = |channel: usize| candidates.iter().map(|(_, lch)| lch[channel]).sum::<f32>() / candidates.len() as f32;
+    let (lightness, chroma) = (average(0), average(1));
+
+    StatusColors(Status::ALL.map(|status| {
+        // The last pin wins, like for the other options
+        if let Some(pin) = pins.iter().rev().find(|pin| pin.status == status) {
+            return Expr::Override(pin.color.clone());
+        }
+
+        let distance = |hue: f32| { let distance = (hue - status.hue()).rem_euclid(360.0); distance.min(360.0 - distance) };
+        // Grays have no meaningful hue
+        let closest = candidates.iter()
+            .filter(|(_, lch)| lch[1] >= 0.04 && distance(lch[2]) <= 30.0)
+            .min_by(|a, b| distance(a.1[2]).total_cmp(&distance(b.1[2])));
+        if let Some((index, _)) = closest {
+            return palette(*index);
+        }
+
+        let hue = status.hue().to_radians();
+        let made = Color::from_oklab([lightness, chroma.max(0.08) * hue.cos(), chroma.max(0.08) * hue.sin()])
+            .unwrap_or_else(|| panic!("Unexpected error, could not make the {status} color"));
+        hex(&made.to_hex())
+    }))
+}
+
+// How the message bubbles are derived
+#[derive(Clone, Debug)]
+pub struct Bubbles {
+    // Palette entry of the outgoing bubbles
+    pub out_role: usize,
+    // Opacity of the bubbles over the background, in percent
+    pub opacity: u8,
+}
+
+impl Default for Bubbles {
+    fn default() -> Self {
+        Bubbles { out_role: 8, opacity: 100 }
+    }
+}
+
+impl Bubbles {
+    // The bubble color once seen over the background
+    fn over_background(&self, expr: Expr) -> Expr {
+        if self.opacity >= 100 {
+            return expr;
+        }
+        expr.alpha(self.opacity)
+    }
+}
+
+// Derivations of the incoming and outgoing message bubbles
+pub fn message_bubbles(bubbles: &Bubbles) -> [Expr; 2] {
+    [
+        bubbles.over_background(palette(7).darker(70)),
+        bubbles.over_background(palette(bubbles.out_role).darker(60)),
+    ]
+}
+
+// Palette entry of the outgoing bubbles that stands out the most from the average color of the
+// background under them, among the ones keeping the outgoing text readable (4.5:1, WCAG AA)
+// - opacity is the bubble opacity, translucent bubbles are blended over the background
+pub fn auto_out_role(colors: &HashMap<String, Rc<RefCell<Color>>>, background: &Color, opacity: u8) -> usize {
+    // Same derivation as historyTextOutFg
+    let text = palette(7).eval(colors, &HashMap::new());
+
+    // Each palette entry with the contrast of its bubble against the background and the text
+    let candidates: Vec<(usize, f32, f32)> = (0..colors.len())
+        .map(|index| {
+            let seen = palette(index).darker(60).alpha(opacity).eval(colors, &HashMap::new()).over(background);
+
+            (index, seen.get_contrast(background), text.get_contrast(&seen))
+        })
+        .collect();
+
+    // Reversed so that ties keep the lowest index, max_by returning the last maximum
+    let readable = candidates.iter().rev()
+        .filter(|(_, _, text_contrast)| *text_contrast >= 4.5)
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    // When no bubble keeps the text readable, the most readable one is used
+    let (index, _, _) = readable
+        .or_else(|| candidates.iter().rev().max_by(|a, b| a.2.total_cmp(&b.2)))
+        .expect("Unexpected error, the palette is empty");
+
+    *index
+}
+
+// How the service messages and the scroll down button, drawn straight over the background, are
+// derived
+#[derive(Clone, Debug)]
+pub struct Overlays {
+    // Palette entry they are made from
+    pub role: usize,
+    // Opacity over the background, in percent
+    pub opacity: u8,
+    // Their text and icons are the palette entry contrasting the most with them, instead of the
+    // usual keys
+    pub contrasted_text: bool,
+}
+
+impl Default for Overlays {
+    fn default() -> Self {
+        Overlays { role: 0, opacity: 100, contrasted_text: false }
+    }
+}
+
+impl Overlays {
+    pub fn over_background(&self, expr: Expr) -> Expr {
+        if self.opacity >= 100 {
+            return expr;
+        }
+        expr.alpha(self.opacity)
+    }
+
+    // The text drawn over them, or the given expression when it doesn't follow them
+    pub fn text_or(&self, expr: Expr) -> Expr {
+        if self.contrasted_text {
+            return palette(self.role).contrast(0);
+        }
+        expr
+    }
+}
+
+// Palette entry and opacity of the overlays keeping their text readable (4.5:1, WCAG AA) over
+// every part of the background, the most transparent one is picked and the busier the background
+// the more opaque it starts
+pub fn auto_overlays(colors: &HashMap<String, Rc<RefCell<Color>>>, analysis: &Analysis) -> Overlays {
+    let samples = analysis.samples();
+
+    // Contrast of the text over the worst sample, relative to the target
+    let score = |index: usize, opacity: u8| -> f32 {
+        let overlay = palette(index).alpha(opacity).eval(colors, &HashMap::new());
+        let text = palette(index).contrast(0).eval(colors, &HashMap::new());
+        samples.iter()
+            .map(|sample| text.get_contrast(&overlay.over(sample)) / 4.5)
+            .fold(f32::INFINITY, f32::min)
     };
 
+    // A standard deviation of 0.25 is a background alternating between black and white
+    let mut opacity = 50 + (analysis.busyness / 0.25 * 50.0).min(50.0) as u8;
+    loop {
+        // Reversed so that ties keep the lowest index, max_by returning the last maximum
+        let (role, best) = (0..colors.len()).rev()
+            .map(|index| (index, score(index, opacity)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("Unexpected error, the palette is empty");
+        // When nothing reaches the target the most readable opaque overlay is used
+        if best >= 1.0 || opacity >= 100 {
+            return Overlays { role, opacity, contrasted_text: true };
+        }
+        opacity = (opacity + 5).min(100);
+    }
+}
+
+// A line of the theme mapping
+#[derive(Clone, Debug)]
+pub enum Entry {
+    Comment(String),
+    Color {
+        key: String,
+        expr: Expr,
+        // The effect of the key on Telegram hasn't been checked
+        untested: bool,
+    },
+}
+
+#[derive(Default)]
+struct Entries(Vec<Entry>);
+
+impl Entries {
     // Add a comment to the colors file
-    let add_comment = |el: &str| {
-        add_any(el, None);
-    };
+    fn add_comment(&mut self, comment: &str) {
+        self.0.push(Entry::Comment(String::from(comment)));
+    }
 
-    // Get an already defined element's pointer
-    let get_element = |el: &str| -> Rc<RefCell<Color>> {
-        let col = elements.borrow().get(el)
-                        .expect(format!("Could not find '{el}' in the previously defined elements").as_str())
-                        .clone()
-                        .expect("Color should not be none");
+    fn add_color(&mut self, key: &str, expr: Expr) {
+        self.0.push(Entry::Color { key: String::from(key), expr, untested: false });
+    }
 
-        return col;
+    fn add_untested(&mut self, key: &str, expr: Expr) {
+        self.0.push(Entry::Color { key: String::from(key), expr, untested: true });
+    }
+}
+
+// A line of the generated theme, comments have no color
+#[derive(Clone, Debug)]
+pub struct ThemeLine {
+    // Key or comment
+    pub name: String,
+    pub color: Option<Color>,
+    pub expr: Option<Expr>,
+    pub untested: bool,
+}
+
+// The theme file contents
+pub fn get_telegram_colors(colors: &HashMap<String, Rc<RefCell<Color>>>, mapping: &Mapping) -> String {
+    let mut ret = String::new();
+    for line in compute_telegram_colors(colors, mapping) {
+        match line.color {
+            // If it is a color
+            Some(color) => ret.push_str((line.name + ": #" + color.to_hex().as_str() + ";\n").as_str()),
+            // If comment
+            None => ret.push_str((String::from("\n// ") + line.name.as_str() + "\n").as_str()),
+        }
+    }
+
+    ret
+}
+
+// A key moved away from another one to reach a contrast target
+#[derive(Clone)]
+struct Move {
+    from: String,
+    target: f32,
+    // Transparency removed first so that the ratio is the one seen
+    opaque: bool,
+    // For readable text, which comes before the borders and states
+    text: bool,
+    // Away from black or white instead of the key, for a background that would lose its border or
+    // state by moving away from its text, the text then switches to that extreme
+    flip: Option<Color>,
+}
+
+// Color of a key before it was moved away from other keys, with its moves in order
+type Unadjusted = HashMap<String, (Color, Vec<Move>)>;
+
+// Move a key away from another one until they reach the target contrast, the lines defining
+// the key follow. The previous moves of the key are done again from its color before them, so
+// that it stays what its expression gives once the keys it was moved away from moved too.
+// Returns whether the key changed
+fn push_away(lines: &mut [ThemeLine], keys: &mut HashMap<String, Color>, unadjusted: &mut Unadjusted, key: &str, next: Move) -> bool {
+    let (original, mut moves) = unadjusted.get(key).cloned().unwrap_or_else(|| (keys[key].clone(), Vec::new()));
+    let apply = |color: Color, step: &Move| -> Color {
+        let color = if step.opaque { color.over(&keys[&step.from]) } else { color };
+        let from = step.flip.clone().unwrap_or_else(|| keys[&step.from].clone());
+        color.with_min_contrast(&from, step.target).borrow().clone()
     };
 
-    add_comment("Color palette");
-    let mut color_keys: Vec<_> = colors.keys().collect();
-    color_keys.sort_by(|a, b| {
-        let a_int: u32 = a.split_at(5).1.parse().unwrap();
-        let b_int: u32 = b.split_at(5).1.parse().unwrap();
-        a_int.cmp(&b_int)
-    });
+    let mut adjusted = moves.iter().fold(original.clone(), apply);
+    // A pair already reaching the target doesn't add a move, and a key moved for the text over it
+    // isn't moved for a border or a state anymore
+    let mut wrapped = None;
+    let known = moves.iter().any(|step| step.from == next.from);
+    let text_first = !next.text && moves.iter().any(|step| step.text);
+    if !known && !text_first {
+        let moved = apply(adjusted.clone(), &next);
+        if moved.to_hex() != adjusted.to_hex() {
+            adjusted = moved;
+            moves.push(next.clone());
+            wrapped = Some(next);
+        }
+    }
+    if adjusted.to_hex() == keys[key].to_hex() {
+        return false;
+    }
+
+    keys.insert(String::from(key), adjusted.clone());
+    for line in lines.iter_mut().filter(|line| line.name == key && line.color.is_some()) {
+        line.color = Some(adjusted.clone());
+        if let Some(step) = &wrapped {
+            line.expr = line.expr.take()
+                .map(|expr| if step.opaque { Expr::Opaque(Box::new(expr), step.from.clone()) } else { expr })
+                .map(|expr| match &step.flip {
+                    Some(extreme) => Expr::Flip(Box::new(expr), step.from.clone(), extreme.to_hex(), step.target),
+                    None => Expr::MinContrast(Box::new(expr), step.from.clone(), step.target),
+                });
+        }
+    }
+    unadjusted.insert(String::from(key), (original, moves));
+    true
+}
+
+// The Telegram keys with their colors in the theme order
+pub fn compute_telegram_colors(colors: &HashMap<String, Rc<RefCell<Color>>>, mapping: &Mapping) -> Vec<ThemeLine> {
+    let mut keys: HashMap<String, Color> = HashMap::new();
+    let mut lines: Vec<ThemeLine> = Vec::new();
+
+    for entry in theme_entries(colors.len(), mapping) {
+        match entry {
+            Entry::Comment(comment) => {
+                lines.push(ThemeLine { name: comment, color: None, expr: None, untested: false });
+            }
+            Entry::Color { key, expr, untested } => {
+                // Overridden colors replace the computed ones, the keys derived from them follow
+                let expr = match mapping.overrides.get(&key) {
+                    Some(color) => Expr::Override(color.to_hex()),
+                    None => expr,
+                };
+
+                let color = expr.eval(colors, &keys);
+                keys.insert(key.clone(), color.clone());
+                lines.push(ThemeLine { name: key, color: Some(color), expr: Some(expr), untested });
+            }
+        }
+    }
+
+    for over in mapping.overrides.keys() {
+        if !keys.contains_key(over) {
+            eprintln!("WARNING! The overridden key '{over}' is not set by the mapping, ignoring it");
+        }
+    }
+
+    // Push the colors away from their background until they reach the contrast targets, the
+    // borders and states first so that the text is then readable over them
+    let high_contrast = mapping.high_contrast.then_some(3.0);
+    // The other text keys only follow the high contrast preset, at the secondary target
+    let other_pairs = other_text_pairs(&lines);
+    let other_pairs: Vec<(&str, &str)> = other_pairs.iter().map(|(fg, bg)| (fg.as_str(), bg.as_str())).collect();
+    let targets = [
+        (&BORDER_PAIRS[..], high_contrast, false),
+        (&STATE_PAIRS[..], high_contrast, false),
+        (&TEXT_PAIRS[..], mapping.text_contrast, true),
+        (&SECONDARY_TEXT_PAIRS[..], mapping.secondary_contrast, true),
+        (&other_pairs[..], mapping.secondary_contrast.filter(|_| mapping.high_contrast), true),
+    ];
+    // A background can be shared by pairs adjusted before it moves, the high contrast preset goes
+    // over the pairs again until nothing changes
+    let passes = if mapping.high_contrast { HIGH_CONTRAST_PASSES } else { 1 };
+    let mut unadjusted = Unadjusted::new();
+    for _ in 0..passes {
+        let mut changed = false;
+        for (pairs, target, text) in targets {
+            let Some(target) = target else { continue };
+
+            for (fg, bg) in pairs {
+                // Overridden colors are kept as they are
+                if mapping.overrides.contains_key(*fg) {
+                    continue;
+                }
+
+                // Transparent colors depend on what is under them, the high contrast preset removes
+                // their transparency so that the ratio is the one seen
+                let opaque = mapping.high_contrast && keys[*fg].alpha < 1.0;
+                let step = Move { from: String::from(*bg), target, opaque, text, flip: None };
+                changed |= push_away(&mut lines, &mut keys, &mut unadjusted, fg, step);
+
+                // When no text color reaches the target over a mid-tone background, the background
+                // moves away from the text as well
+                let moved = unadjusted.get(*bg).is_some_and(|(_, moves)| moves.iter().any(|step| step.from == *fg));
+                if text && mapping.high_contrast && !mapping.overrides.contains_key(*bg)
+                    && (moved || keys[*fg].written().over(&keys[*bg].written()).get_contrast(&keys[*bg].written()) < target) {
+                    // A background keeps its borders and states, it takes the text to its other
+                    // extreme rather than moving back toward them
+                    let away = keys[*bg].with_min_contrast(&keys[*fg], target).borrow().clone();
+                    let breaks = BORDER_PAIRS.iter().chain(STATE_PAIRS.iter())
+                        .filter_map(|(first, second)| match *bg {
+                            key if key == *first => Some(second),
+                            key if key == *second => Some(first),
+                            _ => None,
+                        })
+                        .any(|partner| high_contrast.is_some_and(|separation| {
+                            let partner = keys[*partner].written();
+                            keys[*bg].written().get_contrast(&partner) >= separation && away.written().get_contrast(&partner) < separation
+                        }));
+                    let flip = breaks.then(|| keys[*fg].other_extreme());
+                    let step = Move { from: String::from(*fg), target, opaque: false, text, flip };
+                    changed |= push_away(&mut lines, &mut keys, &mut unadjusted, bg, step);
+                }
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    lines
+}
+
+// Words of a camel case key, eg. 'historyPeer1NameFg' gives 'history', 'Peer1', 'Name', 'Fg'
+fn key_words(name: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = name.char_indices()
+        .filter(|(index, char)| *index == 0 || char.is_ascii_uppercase())
+        .map(|(index, _)| index)
+        .collect();
+    starts.push(name.len());
+    starts.windows(2).map(|bounds| &name[bounds[0]..bounds[1]]).collect()
+}
+
+// Background a text key is drawn on among the written keys, None if it isn't text
+// - The key's state (Over, Active, Selected) is looked for first
+// - The message keys are on the bubble of their side, the others on the background named like the
+//   longest start of their name, then on their surface or the window
+fn text_background(name: &str, written: &HashSet<&str>) -> Option<String> {
+    if NON_TEXT_KEYS.contains(&name) || BORDER_PAIRS.iter().any(|(fg, _)| *fg == name) {
+        return None;
+    }
+    let (start, end) = name.split_once("Fg")?;
+    let state = ["ActiveOver", "Active", "Over", "Selected"].into_iter().find(|state| end.ends_with(state)).unwrap_or("");
+    let background = |base: &str| [format!("{base}Bg{state}"), format!("{base}Bg")].into_iter()
+        .find(|bg| written.contains(bg.as_str()));
+
+    let words = key_words(start);
+    if ["history", "msg", "media"].contains(&words[0]) {
+        if let Some(bg) = ["In", "Out"].into_iter().find(|side| words.contains(side)).and_then(|side| background(&format!("msg{side}"))) {
+            return Some(bg);
+        }
+    }
+    (1..=words.len()).rev().find_map(|length| background(&words[..length].concat()))
+        .or_else(|| TEXT_SURFACES.iter().find(|(surface, _)| start.starts_with(surface)).and_then(|(_, base)| background(base)))
+        .or_else(|| Some(String::from("windowBg")))
+}
+
+// The written text keys outside of the main and secondary pairs, with their background
+fn other_text_pairs(lines: &[ThemeLine]) -> Vec<(String, String)> {
+    let written: HashSet<&str> = lines.iter().filter(|line| line.color.is_some()).map(|line| line.name.as_str()).collect();
+    let mut paired: HashSet<&str> = TEXT_PAIRS.iter().chain(SECONDARY_TEXT_PAIRS.iter()).map(|(fg, _)| *fg).collect();
+
+    let mut pairs = Vec::new();
+    for line in lines.iter().filter(|line| line.color.is_some()) {
+        if !paired.insert(line.name.as_str()) {
+            continue;
+        }
+        if let Some(bg) = text_background(&line.name, &written) {
+            pairs.push((line.name.clone(), bg));
+        }
+    }
+    pairs
+}
+
+// Every written text key with its background, the main and secondary pairs first
+pub fn text_pairs(lines: &[ThemeLine]) -> Vec<(String, String)> {
+    TEXT_PAIRS.iter().chain(SECONDARY_TEXT_PAIRS.iter())
+        .map(|(fg, bg)| (String::from(*fg), String::from(*bg)))
+        .chain(other_text_pairs(lines))
+        .collect()
+}
+
+// Contrast ratio of every text pair as seen on screen, with the colors as written, the lowest first
+pub fn audit_text_contrast(lines: &[ThemeLine]) -> Vec<(String, String, f32)> {
+    // The last definition of a key is the one Telegram uses
+    let color = |name: &str| lines.iter().rev()
+        .find_map(|line| line.color.as_ref().filter(|_| line.name == name))
+        .unwrap_or_else(|| panic!("Unexpected error, the key '{name}' is not in the theme"));
+
+    let mut ratios: Vec<(String, String, f32)> = text_pairs(lines).into_iter()
+        .map(|(fg, bg)| {
+            let [fg_color, bg_color] = [&fg, &bg].map(|name| color(name).written());
+            let ratio = fg_color.over(&bg_color).get_contrast(&bg_color);
+            (fg, bg, ratio)
+        })
+        .collect();
+    ratios.sort_by(|a, b| a.2.total_cmp(&b.2));
+    ratios
+}
+
+// Keys the mapping only sets to derive the others from, Telegram ignores them. Only the palette
+// entries 'color<index>' are, Telegram has keys starting with 'color' too
+pub fn is_helper_key(name: &str) -> bool {
+    name.strip_prefix("color").is_some_and(|index| !index.is_empty() && index.bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+// Keys set by the entries, in order and without duplicates
+pub fn entries_keys(entries: &[Entry]) -> Vec<&str> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    entries.iter()
+        .filter_map(|entry| match entry {
+            Entry::Color { key, .. } => Some(key.as_str()),
+            Entry::Comment(_) => None,
+        })
+        .filter(|key| seen.insert(key))
+        .collect()
+}
+
+// The mapping followed by the Telegram keys it doesn't set, which are given the colors Telegram
+// would pick through its fallbacks so that older versions of Telegram show the same theme
+pub fn theme_entries(palette_size: usize, mapping: &Mapping) -> Vec<Entry> {
+    let mut entries = telegram_entries(palette_size, mapping);
+    let canonical_keys = canonical::bundled_keys();
+
+    let set: HashSet<&str> = entries_keys(&entries).into_iter().collect();
+    let missing: Vec<Entry> = canonical_keys.iter()
+        .filter(|canonical_key| !set.contains(canonical_key.name.as_str()))
+        .map(|canonical_key| {
+            let expr = match canonical::resolve(&canonical_keys, &canonical_key.name, &set) {
+                Resolution::Key(name) => key(&name),
+                Resolution::Default(color) => hex(&color),
+            };
+            Entry::Color { key: canonical_key.name.clone(), expr, untested: true }
+        })
+        .collect();
+
+    if !missing.is_empty() {
+        entries.push(Entry::Comment(String::from("Telegram fallbacks")));
+        entries.extend(missing);
+    }
+
+    entries
+}
 
-    for col_name in color_keys {
-        add_color(format!("{col_name}").as_str(), colors[col_name].clone());
+// The palette to Telegram keys mapping, in the theme order
+pub fn telegram_entries(palette_size: usize, mapping: &Mapping) -> Vec<Entry> {
+    let (bubbles, overlays) = (&mapping.bubbles, &mapping.overlays);
+    // The configured accent replaces the ones the mapping picks for the active, link, selection
+    // and badge keys
+    let accent = |default: Expr| mapping.accent.clone().unwrap_or(default);
+    let mut entries = Entries::default();
+
+    entries.add_comment("Color palette");
+    for index in 0..palette_size {
+        entries.add_color(format!("color{index}").as_str(), palette(index));
     }
 
-    add_comment("Colors for testing purposes");
-    add_color("colorPink", Rc::new(RefCell::new(Color::from_hex("ff7fc6").unwrap())));
-    add_color("colorGreen", Rc::new(RefCell::new(Color::from_hex("0bd500").unwrap())));
+    entries.add_comment("Colors for testing purposes");
+    entries.add_color("colorPink", mapping.status.get(Status::Error));
+    entries.add_color("colorGreen", mapping.status.get(Status::Success));
 
-    add_comment("Special common colors");
-    add_color("colorError", Rc::new(RefCell::new(Color::from_hex("#d55070").unwrap())));
-    add_color("colorSuccess", Rc::new(RefCell::new(Color::from_hex("#6bf576").unwrap())));
+    entries.add_comment("Special common colors");
+    entries.add_color("colorError", mapping.status.get(Status::Error));
+    entries.add_color("colorSuccess", mapping.status.get(Status::Success));
 
-    add_comment("Basic window options");
+    entries.add_comment("Basic window options");
     // \/ bg left menu list items + right click menu
-    add_color("windowBg", get_element("color0").clone());
+    entries.add_color("windowBg", key("color0"));
     // \/ fg plain text + right click menu options
-    add_color("windowFg", get_element("windowBg").borrow().contrast(0));
+    entries.add_color("windowFg", key("windowBg").contrast(0));
     // \/ bg left menu list items and right click menu with cursor over
-    add_color("windowBgOver", get_element("windowBg").borrow().contrast(13));
+    entries.add_color("windowBgOver", key("windowBg").contrast(13));
     // \/ bg left menu list items and right click menu with click pressed
-    add_color("windowBgRipple", get_element("windowBgOver").borrow().darker(10));
+    entries.add_color("windowBgRipple", key("windowBgOver").darker(10));
     // \/ fg menu options right button with cursor over
-    add_color("windowFgOver", get_element("windowBgOver").borrow().contrast(0));
+    entries.add_color("windowFgOver", key("windowBgOver").contrast(0));
     // \/ bottom left text in the left menu + faded text in the options menu
-    add_color("windowSubTextFg", get_element("windowBg").borrow().contrast(4).borrow().alpha(50));
+    entries.add_color("windowSubTextFg", key("windowBg").contrast(4).alpha(50));
     // \/ [UNTESTED]: in theory like the option above but with cursor above ( Doesn't seem to work )
-    add_color("windowSubTextFgOver", get_element("windowSubTextFg").borrow().darker(10));
+    entries.add_untested("windowSubTextFgOver", key("windowSubTextFg").darker(10));
     // \/ bold text color, found in the left menu options, option menu titles, and group description titles
-    add_color("windowBoldFg", get_element("windowFg").borrow().darker(20));
+    entries.add_color("windowBoldFg", key("windowFg").darker(20));
     // \/ same as above but with the cursor above, for example in the left menu options
-    add_color("windowBoldFgOver", get_element("windowFgOver").borrow().darker(10));
+    entries.add_color("windowBoldFgOver", key("windowFgOver").darker(10));
     // \/ Checkboxes background, Rounded + buttons, Emotes icon (foreground), Microphone icon, and other things that 'fill with colour', see options menu
-    add_color("windowBgActive", get_element("windowBg").borrow().contrast(7));
+    entries.add_color("windowBgActive", accent(key("windowBg").contrast(7)));
     // \/ fg of elements using the bg above
-    add_color("windowFgActive", get_element("windowBgActive").borrow().contrast(0));
+    entries.add_color("windowFgActive", key("windowBgActive").contrast(0));
     // \/ user active status text, input text hint if traveling up when active, options category
     // \/ titles, options state text (eg. for the language option -> English)
-    add_color("windowActiveTextFg", get_element("color15").borrow().lighter(20));
+    entries.add_color("windowActiveTextFg", accent(key("color15").lighter(20)));
     // \/ contour shadow left menu, options menu, right click menu and all the window elements with a shadow
-    add_color("windowShadowFg", get_element("windowFg"));
+    entries.add_color("windowShadowFg", key("windowFg"));
     // \/ [UNTESTED]: fallback for shadows without opacity
-    add_color("windowShadowFgFallback", get_element("windowFg"));
+    entries.add_untested("windowShadowFgFallback", key("windowFg"));
 
-    add_comment("Shadow");
+    entries.add_comment("Shadow");
     // \/ most shadows (with opacity) (the color of the divider between parts of the home screen)
-    add_color("shadowFg", get_element("windowBg").borrow().contrast(3).borrow().alpha(30));
+    entries.add_color("shadowFg", key("windowBg").contrast(3).alpha(30));
 
-    add_comment("Slide");
+    entries.add_comment("Slide");
     // \/ Slide animation that appears when you press the arrow of a forwarded message (from chat to profile), or in the right drawer when looking at posted elements from the User Info menu.
-    add_color("slideFadeOutBg", colors["color0"].borrow().alpha(10));
+    entries.add_color("slideFadeOutBg", palette(0).alpha(10));
     // \/ [UNTESTED]: still talking about the animation explained above, it is the line on the right side that moves to the left
-    add_color("slideFadeOutShadowFg", get_element("windowShadowFg"));
+    entries.add_untested("slideFadeOutShadowFg", key("windowShadowFg"));
 
-    add_comment("Image");
+    entries.add_comment("Image");
     // \/ [UNTESTED]: When the photo is smaller than the max. size
-    add_color("imageBg", colors["color2"].clone());
+    entries.add_untested("imageBg", accent(palette(2)));
     // \/ image background when it is an image with opacity, even if this is not required
-    add_color("imageBgTransparent", colors["color7"].clone());
+    entries.add_color("imageBgTransparent", palette(7));
 
-    add_comment("Active");
+    entries.add_comment("Active");
     // \/ bg color active button, eg. first button at the top left in the options menu, the "take a
     // photo" button in the profile, "Add" button in sticker packs selection
-    add_color("activeButtonBg", colors["color2"].clone());
+    entries.add_color("activeButtonBg", accent(palette(2)));
     // \/ as above but with the cursor above
-    add_color("activeButtonBgOver", get_element("activeButtonBg").borrow().lighter(20));
+    entries.add_color("activeButtonBgOver", key("activeButtonBg").lighter(20));
     // \/ as above but ripple effect, click held
-    add_color("activeButtonBgRipple", get_element("activeButtonBg").borrow().lighter(50));
+    entries.add_color("activeButtonBgRipple", key("activeButtonBg").lighter(50));
     // \/ button text explained above
-    add_color("activeButtonFg", get_element("activeButtonBg").borrow().contrast(0));
+    entries.add_color("activeButtonFg", key("activeButtonBg").contrast(0));
     // \/ Button text explained above but with cursor above
-    add_color("activeButtonFgOver", get_element("activeButtonFg").borrow().lighter(20));
+    entries.add_color("activeButtonFgOver", key("activeButtonFg").lighter(20));
     // \/ when you select a message, the numbers next to forward and delete
-    add_color("activeButtonSecondaryFg", get_element("activeButtonBg").borrow().contrast(2));
+    entries.add_color("activeButtonSecondaryFg", key("activeButtonBg").contrast(2));
     // \/ same as above but with the cursor above
-    add_color("activeButtonSecondaryFgOver", get_element("activeButtonSecondaryFg").borrow().lighter(20));
+    entries.add_color("activeButtonSecondaryFgOver", key("activeButtonSecondaryFg").lighter(20));
     // \/ Input text bottom line when focused, eg. line under the name choice when creating a group
-    add_color("activeLineFg", colors["color2"].clone());
+    entries.add_color("activeLineFg", accent(palette(2)));
     // \/ same as above but when errors occur
-    add_color("activeLineFgError", get_element("colorError"));
+    entries.add_color("activeLineFgError", key("colorError"));
 
     // TODO: Reworked until here
-    add_comment("Light");
+    entries.add_comment("Light");
     // \/ button on the right in the options menu and in short the light buttons
-    add_color("lightButtonBg", colors["color0"].clone());
+    entries.add_color("lightButtonBg", palette(0));
     // \/ same as above but with the cursor above
-    add_color("lightButtonBgOver", colors["color0"].borrow().lighter(40));
+    entries.add_color("lightButtonBgOver", palette(0).lighter(40));
     // \/ same as above but ripple effect, click held
-    add_color("lightButtonBgRipple", colors["color0"].borrow().lighter(60));
+    entries.add_color("lightButtonBgRipple", palette(0).lighter(60));
     // \/ button text explained above
-    add_color("lightButtonFg", colors["color2"].clone());
+    entries.add_color("lightButtonFg", accent(palette(2)));
     // \/ Button text unfolded above with cursor above
-    add_color("lightButtonFgOver", get_element("lightButtonFg"));
+    entries.add_color("lightButtonFgOver", key("lightButtonFg"));
 
-    add_comment("Attention");
+    entries.add_comment("Attention");
     // \/ [UNTESTED]: default attention button text (like confirm button on log out)
-    add_color("attentionButtonFg", colors["color1"].clone());
+    entries.add_untested("attentionButtonFg", palette(1));
     // \/ [UNTESTED]: default attention button text with mouse over
-    add_color("attentionButtonFgOver", colors["color1"].borrow().lighter(30));
+    entries.add_untested("attentionButtonFgOver", palette(1).lighter(30));
     // \/ [UNTESTED]: default attention button background with mouse over
-    add_color("attentionButtonBgOver", colors["color0"].borrow().lighter(40));
+    entries.add_untested("attentionButtonBgOver", palette(0).lighter(40));
     // \/ [UNTESTED]: default attention button ripple effect
-    add_color("attentionButtonBgRipple", colors["color0"].borrow().lighter(60));
+    entries.add_untested("attentionButtonBgRipple", palette(0).lighter(60));
 
-    add_comment("Outline");
+    entries.add_comment("Outline");
     // \/ [UNTESTED]: default left outlined button background (like shared media links in profiles)
-    add_color("outlineButtonBg", get_element("windowBg"));
+    entries.add_untested("outlineButtonBg", key("windowBg"));
     // \/ [UNTESTED]: default left outlined button background with mouse over
-    add_color("outlineButtonBgOver", colors["color0"].borrow().lighter(40));
+    entries.add_untested("outlineButtonBgOver", palette(0).lighter(40));
     // \/ [UNTESTED]: default left outlined button left outline border
-    add_color("outlineButtonOutlineFg", colors["color2"].clone());
+    entries.add_untested("outlineButtonOutlineFg", accent(palette(2)));
     // \/ [UNTESTED]: default left outlined button ripple effect
-    add_color("outlineButtonBgRipple", colors["color0"].borrow().lighter(60));
+    entries.add_untested("outlineButtonBgRipple", palette(0).lighter(60));
 
-    add_comment("Menu");
+    entries.add_comment("Menu");
     // \/ top and bottom lines of popup menus, such as the three dots at the top right in chats
-    add_color("menuBg", colors["color0"].clone());
+    entries.add_color("menuBg", palette(0));
     // \/ [UNTESTED]: in theory the comments page of the popu menu with the cursor above
-    add_color("menuBgOver", colors["color0"].borrow().lighter(40));
+    entries.add_untested("menuBgOver", palette(0).lighter(40));
     // \/ [UNTESTED]: in theory the same as above but ripple effect
-    add_color("menuBgRipple", colors["color0"].borrow().lighter(60));
+    entries.add_untested("menuBgRipple", palette(0).lighter(60));
     // \/ options menu icons and bar above in the chat area
-    add_color("menuIconFg", colors["color7"].clone());
+    entries.add_color("menuIconFg", palette(7));
     // \/ same as above but with cursor above
-    add_color("menuIconFgOver", colors["color7"].borrow().lighter(40));
+    entries.add_color("menuIconFgOver", palette(7).lighter(40));
     // \/ in the message field, if you press the right button, there is an arrow in the popup menu
-    add_color("menuSubmenuArrowFg", colors["color7"].clone());
+    entries.add_color("menuSubmenuArrowFg", palette(7));
     // \/ text disabled in the popup menu (right click in the search field or in the text field)
-    add_color("menuFgDisabled", colors["color7"].borrow().darker(40));
+    entries.add_color("menuFgDisabled", palette(7).darker(40));
     // \/ separator in the menu right click in input field
-    add_color("menuSeparatorFg", colors["color7"].borrow().darker(40));
+    entries.add_color("menuSeparatorFg", palette(7).darker(40));
 
-    add_comment("Scroll");
+    entries.add_comment("Scroll");
     // \/ default scroll bar current rectangle, the bar itself (like in chats list)
-    add_color("scrollBarBg", colors["color7"].borrow().alpha(30));
+    entries.add_color("scrollBarBg", palette(7).alpha(30));
     // \/ default scroll bar current rectangle with mouse over it
-    add_color("scrollBarBgOver", colors["color7"].borrow().alpha(45));
+    entries.add_color("scrollBarBgOver", palette(7).alpha(45));
     // \/ default scroll bar background
-    add_color("scrollBg", colors["color7"].borrow().alpha(05));
+    entries.add_color("scrollBg", palette(7).alpha(5));
     // \/ default scroll bar background with mouse over the scroll bar
-    add_color("scrollBgOver", colors["color7"].borrow().alpha(15));
+    entries.add_color("scrollBgOver", palette(7).alpha(15));
 
-    add_comment("Small");
+    entries.add_comment("Small");
     // \/ small cross for example next to the header in the emoji panel
-    add_color("smallCloseIconFg", colors["color7"].borrow().darker(40));
+    entries.add_color("smallCloseIconFg", palette(7).darker(40));
     // \/ as above but with the cursor above
-    add_color("smallCloseIconFgOver", colors["color7"].clone());
+    entries.add_color("smallCloseIconFgOver", palette(7));
 
-    add_comment("Radial");
+    entries.add_comment("Radial");
     // \/ [UNTESTED]: default radial loader line (like in Media Viewer when loading a photo)
-    add_color("radialFg", get_element("windowFgActive"));
+    entries.add_untested("radialFg", key("windowFgActive"));
     // \/ [UNTESTED]: default radial loader background (like in Media Viewer when loading a photo)
-    add_color("radialBg", colors["color0"].borrow().alpha(30));
+    entries.add_untested("radialBg", palette(0).alpha(30));
 
-    add_comment("Placeholder");
+    entries.add_comment("Placeholder");
     // \/ type the default placeholder text of the search bar and insert text
-    add_color("placeholderFg", colors["color7"].clone());
+    entries.add_color("placeholderFg", palette(7));
     // \/ same as above, but when the field is in focus
-    add_color("placeholderFgActive", colors["color7"].borrow().darker(40));
+    entries.add_color("placeholderFgActive", palette(7).darker(40));
 
-    add_comment("Input");
+    entries.add_comment("Input");
     // \/ like the alternative line fg when you are creating a channel
-    add_color("inputBorderFg", colors["color7"].clone());
+    entries.add_color("inputBorderFg", palette(7));
 
-    add_comment("Filter");
+    entries.add_comment("Filter");
     // \/ border that appears when you click in the search bar
-    add_color("filterInputBorderFg", colors["color0"].borrow().lighter(40));
+    entries.add_color("filterInputBorderFg", palette(0).lighter(40));
     // \/ bg search field inactive
-    add_color("filterInputInactiveBg", colors["color8"].borrow().darker(30));
+    entries.add_color("filterInputInactiveBg", palette(8).darker(30));
     // \/ bg active search field
-    add_color("filterInputActiveBg", colors["color8"].borrow().darker(20));
+    entries.add_color("filterInputActiveBg", palette(8).darker(20));
 
-    add_comment("Checkbox");
+    entries.add_comment("Checkbox");
     // \/ emoji category icons and also un'ticked' tick boxes
-    add_color("checkboxFg", colors["color7"].borrow().darker(40));
+    entries.add_color("checkboxFg", palette(7).darker(40));
 
-    add_comment("Slider");
+    entries.add_comment("Slider");
     // \/ slider not active
-    add_color("sliderBgInactive", colors["color7"].borrow().darker(40));
+    entries.add_color("sliderBgInactive", palette(7).darker(40));
     // \/ active slider
-    add_color("sliderBgActive", get_element("windowBgActive"));
+    entries.add_color("sliderBgActive", key("windowBgActive"));
 
-    add_comment("Tooltip");
+    entries.add_comment("Tooltip");
     // \/ bg of the tooltip field, like when you wait with the cursor over the timestamp of the mex
-    add_color("tooltipBg", colors["color7"].clone());
+    entries.add_color("tooltipBg", palette(7));
     // \/ fg of the tooltip
-    add_color("tooltipFg", colors["color0"].clone());
+    entries.add_color("tooltipFg", palette(0));
     // \/ tooltip edges
-    add_color("tooltipBorderFg", colors["color7"].clone());
+    entries.add_color("tooltipBorderFg", palette(7));
 
-    add_comment("Title");
+    entries.add_comment("Title");
     // \/ [UNTESTED]: one pixel line shadow at the bottom of custom window title
-    add_color("titleShadow", colors["color0"].borrow().alpha(05));
+    entries.add_untested("titleShadow", palette(0).alpha(5));
     // \/ [UNTESTED]: custom window title background when window is inactive
-    add_color("titleBg", colors["color0"].clone());
+    entries.add_untested("titleBg", palette(0));
     // \/ [UNTESTED]: custom window title background when window is active
-    add_color("titleBgActive", get_element("titleBg"));
+    entries.add_untested("titleBgActive", key("titleBg"));
     // \/ [UNTESTED]: custom window title minimize/maximize/restore button background when window is inactive (Windows only)
-    add_color("titleButtonBg", get_element("titleBg"));
+    entries.add_untested("titleButtonBg", key("titleBg"));
     // \/ [UNTESTED]: custom window title minimize/maximize/restore button icon when window is inactive (Windows only)
-    add_color("titleButtonFg", colors["color7"].clone());
+    entries.add_untested("titleButtonFg", palette(7));
     // \/ [UNTESTED]: custom window title minimize/maximize/restore button background with mouse over when window is inactive (Windows only)
-    add_color("titleButtonBgOver", colors["color0"].borrow().lighter(40));
+    entries.add_untested("titleButtonBgOver", palette(0).lighter(40));
     // \/ [UNTESTED]: custom window title minimize/maximize/restore button icon with mouse over when window is inactive (Windows only)
-    add_color("titleButtonFgOver", colors["color7"].borrow().lighter(40));
+    entries.add_untested("titleButtonFgOver", palette(7).lighter(40));
     // \/ [UNTESTED]: custom window title minimize/maximize/restore button background when window is active (Windows only)
-    add_color("titleButtonBgActive", get_element("titleButtonBg"));
+    entries.add_untested("titleButtonBgActive", key("titleButtonBg"));
     // \/ [UNTESTED]: custom window title minimize/maximize/restore button icon when window is active (Windows only)
-    add_color("titleButtonFgActive", get_element("titleButtonFg"));
+    entries.add_untested("titleButtonFgActive", key("titleButtonFg"));
     // \/ [UNTESTED]: custom window title minimize/maximize/restore button background with mouse over when window is active (Windows only)
-    add_color("titleButtonBgActiveOver", get_element("titleButtonBgOver"));
+    entries.add_untested("titleButtonBgActiveOver", key("titleButtonBgOver"));
     // \/ [UNTESTED]: custom window title minimize/maximize/restore button icon with mouse over when window is active (Windows only)
-    add_color("titleButtonFgActiveOver", get_element("titleButtonFgOver"));
+    entries.add_untested("titleButtonFgActiveOver", key("titleButtonFgOver"));
     // \/ [UNTESTED]: custom window title close button background when window is inactive (Windows only)
-    add_color("titleButtonCloseBg", get_element("titleButtonBg"));
+    entries.add_untested("titleButtonCloseBg", key("titleButtonBg"));
     // \/ [UNTESTED]: custom window title close button icon when window is inactive (Windows only)
-    add_color("titleButtonCloseFg", get_element("titleButtonFg"));
+    entries.add_untested("titleButtonCloseFg", key("titleButtonFg"));
     // \/ [UNTESTED]: custom window title close button background with mouse over when window is inactive (Windows only)
-    add_color("titleButtonCloseBgOver", colors["color0"].borrow().lighter(40));
+    entries.add_untested("titleButtonCloseBgOver", palette(0).lighter(40));
     // \/ [UNTESTED]: custom window title close button icon with mouse over when window is inactive (Windows only)
-    add_color("titleButtonCloseFgOver", get_element("windowFgActive"));
+    entries.add_untested("titleButtonCloseFgOver", key("windowFgActive"));
     // \/ [UNTESTED]: custom window title close button background when window is active (Windows only)
-    add_color("titleButtonCloseBgActive", get_element("titleButtonCloseBg"));
+    entries.add_untested("titleButtonCloseBgActive", key("titleButtonCloseBg"));
     // \/ [UNTESTED]: custom window title close button icon when window is active (Windows only)
-    add_color("titleButtonCloseFgActive", get_element("titleButtonCloseFg"));
+    entries.add_untested("titleButtonCloseFgActive", key("titleButtonCloseFg"));
     // \/ [UNTESTED]: custom window title close button background with mouse over when window is active (Windows only)
-    add_color("titleButtonCloseBgActiveOver", get_element("titleButtonCloseBgOver"));
+    entries.add_untested("titleButtonCloseBgActiveOver", key("titleButtonCloseBgOver"));
     // \/ [UNTESTED]: custom window title close button icon with mouse over when window is active (Windows only)
-    add_color("titleButtonCloseFgActiveOver", get_element("titleButtonCloseFgOver"));
+    entries.add_untested("titleButtonCloseFgActiveOver", key("titleButtonCloseFgOver"));
     // \/ [UNTESTED]: custom window title text when window is inactive (macOS only)
-    add_color("titleFg", colors["color7"].clone());
+    entries.add_untested("titleFg", palette(7));
     // \/ [UNTESTED]: custom window title text when window is active (macOS only)
-    add_color("titleFgActive", colors["color7"].borrow().lighter(40));
+    entries.add_untested("titleFgActive", palette(7).lighter(40));
 
-    add_comment("Tray");
+    entries.add_comment("Tray");
     // \/ [UNTESTED]: tray icon counter background
-    add_color("trayCounterBg", colors["color2"].clone());
+    entries.add_untested("trayCounterBg", accent(palette(2)));
     // \/ [UNTESTED]: tray icon counter background if all unread messages are muted
-    add_color("trayCounterBgMute", colors["color0"].clone());
+    entries.add_untested("trayCounterBgMute", palette(0));
     // \/ [UNTESTED]: tray icon counter text
-    add_color("trayCounterFg", colors["color7"].clone());
+    entries.add_untested("trayCounterFg", palette(7));
     // \/ [UNTESTED]: tray icon counter background when tray icon is pressed or when dark theme of macOS is used (macOS only)
-    add_color("trayCounterBgMacInvert", colors["color7"].clone());
+    entries.add_untested("trayCounterBgMacInvert", palette(7));
     // \/ [UNTESTED]: tray icon counter text when tray icon is pressed or when dark theme of macOS is used (macOS only)
-    add_color("trayCounterFgMacInvert", colors["color2"].clone());
+    entries.add_untested("trayCounterFgMacInvert", accent(palette(2)));
 
-    add_comment("Layer");
+    entries.add_comment("Layer");
     // \/ fade menu options and left menu
-    add_color("layerBg", colors["color0"].borrow().alpha(45));
+    entries.add_color("layerBg", palette(0).alpha(45));
 
-    add_comment("Cancel");
+    entries.add_comment("Cancel");
     // \/ fg cross to close the options menu and other things
-    add_color("cancelIconFg", colors["color7"].borrow().darker(40));
+    entries.add_color("cancelIconFg", palette(7).darker(40));
     // \/ same as above but with cursor above the cross
-    add_color("cancelIconFgOver", colors["color7"].clone());
+    entries.add_color("cancelIconFgOver", palette(7));
 
-    add_comment("Box");
+    entries.add_comment("Box");
     // \/ bg menu options
-    add_color("boxBg", get_element("windowBg"));
+    entries.add_color("boxBg", key("windowBg"));
     // \/ fg menu options
-    add_color("boxTextFg", get_element("windowFg"));
+    entries.add_color("boxTextFg", key("windowFg"));
     // \/ [UNTESTED]: accepted box text (like when choosing username that is not occupied)
-    add_color("boxTextFgGood", colors["color2"].clone());
+    entries.add_untested("boxTextFgGood", key("colorSuccess"));
     // \/ [UNTESTED]: rejecting box text (like when choosing username that is occupied)
-    add_color("boxTextFgError", colors["color1"].clone());
+    entries.add_untested("boxTextFgError", key("colorError"));
     // \/ box text, such as confirmation after changing theme
-    add_color("boxTitleFg", colors["color7"].borrow().lighter(40));
+    entries.add_color("boxTitleFg", palette(7).lighter(40));
     // \/ bg box search field, type search in the contacts option in the left menu
-    add_color("boxSearchBg", colors["color0"].clone());
+    entries.add_color("boxSearchBg", palette(0));
     // \/ subtext of the boxTitleFg, type where you can see the number of people to add when you are creating a group
-    add_color("boxTitleAdditionalFg", colors["color7"].borrow().darker(40));
+    entries.add_color("boxTitleAdditionalFg", palette(7).darker(40));
     // \/ other crosses
-    add_color("boxTitleCloseFg", get_element("cancelIconFg"));
+    entries.add_color("boxTitleCloseFg", key("cancelIconFg"));
     // \/ other crosses with cursor above
-    add_color("boxTitleCloseFgOver", get_element("cancelIconFgOver"));
+    entries.add_color("boxTitleCloseFgOver", key("cancelIconFgOver"));
 
-    add_comment("Members");
+    entries.add_comment("Members");
     // \/ text when the member addition limit is exceeded (since it is very high, it's troublesome to check)
-    add_color("membersAboutLimitFg", colors["color1"].clone());
+    entries.add_color("membersAboutLimitFg", palette(1));
 
-    add_comment("Contacts");
+    entries.add_comment("Contacts");
     // \/ bg of the boxes that contain the contacts in the appropriate section accessible from the menu on the left
-    add_color("contactsBg", colors["color0"].borrow().lighter(40));
+    entries.add_color("contactsBg", palette(0).lighter(40));
     // \/ same as above but with the cursor above
-    add_color("contactsBgOver", colors["color0"].clone());
+    entries.add_color("contactsBgOver", palette(0));
     // \/ fg of the contact names in the section described above
-    add_color("contactsNameFg", get_element("boxTextFg"));
+    entries.add_color("contactsNameFg", key("boxTextFg"));
     // \/ fg of the status of the contact names
-    add_color("contactsStatusFg", colors["color7"].borrow().darker(40));
+    entries.add_color("contactsStatusFg", palette(7).darker(40));
     // \/ same as above but with the cursor above
-    add_color("contactsStatusFgOver", colors["color7"].borrow().darker(40));
+    entries.add_color("contactsStatusFgOver", palette(7).darker(40));
     // \/ fg of the online writing in the online contacts precisely
-    add_color("contactsStatusFgOnline", colors["color10"].clone());
+    entries.add_color("contactsStatusFgOnline", accent(palette(10)));
 
-    add_comment("Photo");
+    entries.add_comment("Photo");
     // \/ the crop background of the chosen image (when you need to set an image for the group or for your profile)
-    add_color("photoCropFadeBg", get_element("layerBg"));
+    entries.add_color("photoCropFadeBg", key("layerBg"));
     // \/ small rectangles that delimit the image you are setting
-    add_color("photoCropPointFg", colors["color7"].borrow().alpha(45));
+    entries.add_color("photoCropPointFg", palette(7).alpha(45));
 
-    add_comment("Call");
+    entries.add_comment("Call");
     // \/ [UNTESTED]: received phone call arrow (in calls list box)
-    add_color("callArrowFg", colors["color2"].clone());
+    entries.add_untested("callArrowFg", palette(2));
     // \/ [UNTESTED]: missed phone call arrow (in calls list box)
-    add_color("callArrowMissedFg", colors["color1"].clone());
+    entries.add_untested("callArrowMissedFg", palette(1));
 
-    add_comment("Intro");
+    entries.add_comment("Intro");
     // \/ [UNTESTED]: login background
-    add_color("introBg", get_element("windowBg"));
+    entries.add_untested("introBg", key("windowBg"));
     // \/ [UNTESTED]: login title text
-    add_color("introTitleFg", colors["color7"].borrow().lighter(40));
+    entries.add_untested("introTitleFg", palette(7).lighter(40));
     // \/ [UNTESTED]: login description text
-    add_color("introDescriptionFg", colors["color7"].clone());
+    entries.add_untested("introDescriptionFg", palette(7));
     // \/ [UNTESTED]: login error text (like when providing a wrong log in code)
-    add_color("introErrorFg", colors["color1"].clone());
+    entries.add_untested("introErrorFg", key("colorError"));
     // \/ [UNTESTED]: intro gradient top (from)
-    add_color("introCoverTopBg", colors["color2"].clone());
+    entries.add_untested("introCoverTopBg", accent(palette(2)));
     // \/ [UNTESTED]: intro gradient bottom (to)
-    add_color("introCoverBottomBg", colors["color2"].clone());
+    entries.add_untested("introCoverBottomBg", accent(palette(2)));
     // \/ [UNTESTED]: intro cloud graphics
-    add_color("introCoverIconsFg", colors["color2"].borrow().lighter(40));
+    entries.add_untested("introCoverIconsFg", accent(palette(2)).lighter(40));
     // \/ [UNTESTED]: intro plane traces
-    add_color("introCoverPlaneTrace", colors["color2"].borrow().lighter(40));
+    entries.add_untested("introCoverPlaneTrace", accent(palette(2)).lighter(40));
     // \/ [UNTESTED]: intro plane part
-    add_color("introCoverPlaneInner", colors["color1"].borrow().lighter(40));
+    entries.add_untested("introCoverPlaneInner", palette(1).lighter(40));
     // \/ [UNTESTED]: intro plane part
-    add_color("introCoverPlaneOuter", colors["color1"].clone());
+    entries.add_untested("introCoverPlaneOuter", palette(1));
     // \/ [UNTESTED]: intro plane part
-    add_color("introCoverPlaneTop", colors["color7"].borrow().lighter(40));
+    entries.add_untested("introCoverPlaneTop", palette(7).lighter(40));
 
-    add_comment("Dialogs default");
+    entries.add_comment("Dialogs default");
     // \/ main menu and lock telegram icon
-    add_color("dialogsMenuIconFg", get_element("menuIconFg"));
+    entries.add_color("dialogsMenuIconFg", key("menuIconFg"));
     // \/ main menu and lock telegram icon with mouse over
-    add_color("dialogsMenuIconFgOver", get_element("menuIconFgOver"));
+    entries.add_color("dialogsMenuIconFgOver", key("menuIconFgOver"));
     // \/ dialogue box bg
-    add_color("dialogsBg", get_element("windowBg"));
+    entries.add_color("dialogsBg", key("windowBg"));
     // \/ dialogue box fg names
-    add_color("dialogsNameFg", colors["color7"].borrow().lighter(40));
+    entries.add_color("dialogsNameFg", palette(7).lighter(40));
     // \/ dialogue box group or contact icons
-    add_color("dialogsChatIconFg", get_element("dialogsNameFg"));
+    entries.add_color("dialogsChatIconFg", key("dialogsNameFg"));
     // \/ date text dialogue box
-    add_color("dialogsDateFg", colors["color7"].borrow().darker(40));
+    entries.add_color("dialogsDateFg", palette(7).darker(40));
     // \/ message text dialogue box (small under the name)
-    add_color("dialogsTextFg", colors["color7"].clone());
+    entries.add_color("dialogsTextFg", palette(7));
     // \/ sender's message text dialogue box
-    add_color("dialogsTextFgService", colors["color7"].clone());
+    entries.add_color("dialogsTextFgService", palette(7));
     // \/ draft text color dialogue box
-    add_color("dialogsDraftFg", colors["color1"].clone());
+    entries.add_color("dialogsDraftFg", palette(1));
     // \/ bg verified profile icon
-    add_color("dialogsVerifiedIconBg", colors["color10"].clone());
+    entries.add_color("dialogsVerifiedIconBg", accent(palette(10)));
     // \/ fg verified profile icon
-    add_color("dialogsVerifiedIconFg", colors["color0"].clone());
+    entries.add_color("dialogsVerifiedIconFg", palette(0));
     // \/ send message icon (clock)
-    add_color("dialogsSendingIconFg", colors["color10"].clone());
+    entries.add_color("dialogsSendingIconFg", accent(palette(10)));
     // \/ single/double ticks to confirm sending message
-    add_color("dialogsSentIconFg", colors["color10"].clone());
+    entries.add_color("dialogsSentIconFg", accent(palette(10)));
     // \/ [UNTESTED]: chat list unread badge background for not muted chat
-    add_color("dialogsUnreadBg", colors["color1"].clone());
+    entries.add_untested("dialogsUnreadBg", accent(palette(1)));
     // \/ fg pinned icon for pinned chats
-    add_color("dialogsUnreadBgMuted", colors["color7"].borrow().darker(40));
+    entries.add_color("dialogsUnreadBgMuted", palette(7).darker(40));
     // \/ [UNTESTED]: chat list unread badge text
-    add_color("dialogsUnreadFg", colors["color7"].borrow().lighter(40));
+    entries.add_untested("dialogsUnreadFg", palette(7).lighter(40));
 
-    add_comment("Dialogs over");
+    entries.add_comment("Dialogs over");
     // \/ cursor over dialog box
-    add_color("dialogsBgOver", colors["color2"].borrow().darker(50));
+    entries.add_color("dialogsBgOver", accent(palette(2)).darker(50));
     // \/ dialogue box fg names with cursor above
-    add_color("dialogsNameFgOver", get_element("windowBoldFgOver"));
+    entries.add_color("dialogsNameFgOver", key("windowBoldFgOver"));
     // \/ dialogue box group or contact icons with cursor above
-    add_color("dialogsChatIconFgOver", get_element("dialogsNameFgOver"));
+    entries.add_color("dialogsChatIconFgOver", key("dialogsNameFgOver"));
     // \/ date text dialogue box with cursor above
-    add_color("dialogsDateFgOver", colors["color7"].borrow().darker(40));
+    entries.add_color("dialogsDateFgOver", palette(7).darker(40));
     // \/ message text dialogue box (small under the name) with cursor above
-    add_color("dialogsTextFgOver", colors["color7"].clone());
+    entries.add_color("dialogsTextFgOver", palette(7));
     // \/ sender's message text dialogue box with cursor above
-    add_color("dialogsTextFgServiceOver", colors["color7"].clone());
+    entries.add_color("dialogsTextFgServiceOver", palette(7));
     // \/ draft text color dialogue box with cursor above
-    add_color("dialogsDraftFgOver", get_element("dialogsDraftFg"));
+    entries.add_color("dialogsDraftFgOver", key("dialogsDraftFg"));
     // \/ bg verified profile icon with cursor over
-    add_color("dialogsVerifiedIconBgOver", colors["color2"].clone());
+    entries.add_color("dialogsVerifiedIconBgOver", accent(palette(2)));
     // \/ fg verified profile icon with cursor over
-    add_color("dialogsVerifiedIconFgOver", colors["color0"].clone());
+    entries.add_color("dialogsVerifiedIconFgOver", palette(0));
     // \/ send message icon (clock) with cursor above
-    add_color("dialogsSendingIconFgOver", get_element("dialogsSendingIconFg"));
+    entries.add_color("dialogsSendingIconFgOver", key("dialogsSendingIconFg"));
     // \/ single/double ticks to confirm sending message with cursor above
-    add_color("dialogsSentIconFgOver", colors["color10"].clone());
+    entries.add_color("dialogsSentIconFgOver", accent(palette(10)));
     // \/ [UNTESTED]: chat list unread badge background for not muted chat with mouse over
-    add_color("dialogsUnreadBgOver", colors["color1"].borrow().darker(40));
+    entries.add_untested("dialogsUnreadBgOver", accent(palette(1)).darker(40));
     // \/ [UNTESTED]: chat list unread badge background for muted chat with mouse over
-    add_color("dialogsUnreadBgMutedOver", colors["color7"].borrow().darker(40));
+    entries.add_untested("dialogsUnreadBgMutedOver", palette(7).darker(40));
     // \/ [UNTESTED]: chat list unread badge text with mouse over
-    add_color("dialogsUnreadFgOver", get_element("dialogsUnreadFg"));
+    entries.add_untested("dialogsUnreadFgOver", key("dialogsUnreadFg"));
 
-    add_comment("Dialogs active");
+    entries.add_comment("Dialogs active");
     // \/ color bg dialog box active
-    add_color("dialogsBgActive", colors["color2"].clone());
+    entries.add_color("dialogsBgActive", accent(palette(2)));
     // \/ dialog box fg names active
-    add_color("dialogsNameFgActive", get_element("windowBoldFgOver"));
+    entries.add_color("dialogsNameFgActive", key("windowBoldFgOver"));
     // \/ dialog box active group or contact icons
-    add_color("dialogsChatIconFgActive", get_element("dialogsNameFgActive"));
+    entries.add_color("dialogsChatIconFgActive", key("dialogsNameFgActive"));
     // \/ active date text dialog box
-    add_color("dialogsDateFgActive", colors["color7"].borrow().lighter(40));
+    entries.add_color("dialogsDateFgActive", palette(7).lighter(40));
     // \/ message text dialog box (small under the name) active
-    add_color("dialogsTextFgActive", colors["color7"].borrow().lighter(40));
+    entries.add_color("dialogsTextFgActive", palette(7).lighter(40));
     // \/ sender message text dialog box active
-    add_color("dialogsTextFgServiceActive", colors["color7"].borrow().lighter(40));
+    entries.add_color("dialogsTextFgServiceActive", palette(7).lighter(40));
     // \/ active draft text color dialog box
-    add_color("dialogsDraftFgActive", colors["color7"].borrow().lighter(40));
+    entries.add_color("dialogsDraftFgActive", palette(7).lighter(40));
     // \/ [UNTESTED]: chat list verified icon background for current (active) chat
-    add_color("dialogsVerifiedIconBgActive", get_element("dialogsTextFgActive"));
+    entries.add_untested("dialogsVerifiedIconBgActive", key("dialogsTextFgActive"));
     // \/ [UNTESTED]: chat list verified icon check for current (active) chat
-    add_color("dialogsVerifiedIconFgActive", get_element("dialogsBgActive"));
+    entries.add_untested("dialogsVerifiedIconFgActive", key("dialogsBgActive"));
     // \/ message sending icon (clock) active
-    add_color("dialogsSendingIconFgActive", colors["color7"].borrow().lighter(40));
+    entries.add_color("dialogsSendingIconFgActive", palette(7).lighter(40));
     // \/ single/double ticks to confirm active message sending
-    add_color("dialogsSentIconFgActive", get_element("dialogsTextFgActive"));
+    entries.add_color("dialogsSentIconFgActive", key("dialogsTextFgActive"));
     // \/ [UNTESTED]: chat list unread badge background for not muted chat for current (active) chat
-    add_color("dialogsUnreadBgActive", get_element("dialogsTextFgActive"));
+    entries.add_untested("dialogsUnreadBgActive", key("dialogsTextFgActive"));
     // \/ [UNTESTED]: chat list unread badge background for muted chat for current (active) chat
-    add_color("dialogsUnreadBgMutedActive", colors["color7"].borrow().lighter(40));
+    entries.add_untested("dialogsUnreadBgMutedActive", palette(7).lighter(40));
     // \/ [UNTESTED]: chat list unread badge text for current (active) chat
-    add_color("dialogsUnreadFgActive", colors["color7"].borrow().lighter(40));
+    entries.add_untested("dialogsUnreadFgActive", palette(7).lighter(40));
 
-    add_comment("Dialogs ripple");
+    entries.add_comment("Dialogs ripple");
     // \/ ripple effect on the dialogue box not active
-    add_color("dialogsRippleBg", colors["color0"].borrow().lighter(60));
+    entries.add_color("dialogsRippleBg", palette(0).lighter(60));
     // \/ ripple effect on the active dialogue box
-    add_color("dialogsRippleBgActive", colors["color2"].borrow().lighter(40));
+    entries.add_color("dialogsRippleBgActive", accent(palette(2)).lighter(40));
 
-    add_comment("Dialogs forward");
+    entries.add_comment("Dialogs forward");
     // \/ forwarding panel background (when forwarding messages in the smallest window size)
-    add_color("dialogsForwardBg", get_element("dialogsBgActive"));
+    entries.add_color("dialogsForwardBg", key("dialogsBgActive"));
     // \/ forwarding panel text (when forwarding messages in the smallest window size)
-    add_color("dialogsForwardFg", get_element("dialogsNameFgActive"));
+    entries.add_color("dialogsForwardFg", key("dialogsNameFgActive"));
 
-    add_comment("Searched");
+    entries.add_comment("Searched");
     // \/ bg of the text part when searching for messages in a single chat
-    add_color("searchedBarBg", colors["color0"].borrow().lighter(40));
+    entries.add_color("searchedBarBg", palette(0).lighter(40));
     // \/ fg of the text of the comment described above
-    add_color("searchedBarFg", colors["color7"].clone());
+    entries.add_color("searchedBarFg", palette(7));
 
-    add_comment("Top");
+    entries.add_comment("Top");
     // \/ bg of the top bar on the right side of the main screen (inside the chats)
-    add_color("topBarBg", colors["color0"].clone());
+    entries.add_color("topBarBg", palette(0));
 
-    add_comment("Emoji");
+    entries.add_comment("Emoji");
     // \/ bg of the emoji panel
-    add_color("emojiPanBg", get_element("windowBg"));
+    entries.add_color("emojiPanBg", key("windowBg"));
     // \/ bg of the lower part of the emoji panel (categories)
-    add_color("emojiPanCategories", colors["color0"].clone());
+    entries.add_color("emojiPanCategories", palette(0));
     // \/ fg descriptive header of the emoji panel
-    add_color("emojiPanHeaderFg", colors["color7"].clone());
+    entries.add_color("emojiPanHeaderFg", palette(7));
     // \/ [UNTESTED]: bg of the comment described above
-    add_color("emojiPanHeaderBg", colors["color0"].clone());
+    entries.add_untested("emojiPanHeaderBg", palette(0));
     // \/ fg of the emoji not active (final part of the emoji panel)
-    add_color("emojiIconFg", colors["color7"].clone());
+    entries.add_color("emojiIconFg", palette(7));
     // \/ fg of the active emoji
-    add_color("emojiIconFgActive", colors["color2"].clone());
+    entries.add_color("emojiIconFgActive", accent(palette(2)));
 
-    add_comment("Sticker");
+    entries.add_comment("Sticker");
     // \/ [UNTESTED]: delete X button background for custom sent stickers in stickers panel (legacy)
-    add_color("stickerPanDeleteBg", colors["color0"].borrow().alpha(75));
+    entries.add_untested("stickerPanDeleteBg", palette(0).alpha(75));
     // \/ [UNTESTED]: delete X button icon for custom sent stickers in stickers panel (legacy)
-    add_color("stickerPanDeleteFg", get_element("windowFgActive"));
+    entries.add_untested("stickerPanDeleteFg", key("windowFgActive"));
     // \/ sticker and GIF preview background (when you press and hold on a sticker)
-    add_color("stickerPreviewBg", colors["color0"].borrow().alpha(65));
+    entries.add_color("stickerPreviewBg", palette(0).alpha(65));
 
-    add_comment("History");
+    entries.add_comment("History");
     // \/ inbox mex text not selected
-    add_color("historyTextInFg", get_element("windowFg"));
+    entries.add_color("historyTextInFg", key("windowFg"));
     // \/ inbox mex text selected
-    add_color("historyTextInFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyTextInFgSelected", palette(7).lighter(40));
     // \/ outbox mex text not selected
-    add_color("historyTextOutFg", colors["color7"].clone());
+    entries.add_color("historyTextOutFg", palette(7));
     // \/ outbox mex text selected
-    add_color("historyTextOutFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyTextOutFgSelected", palette(7).lighter(40));
     // \/ inbox mex link text not selected
-    add_color("historyLinkInFg", colors["color10"].clone());
+    entries.add_color("historyLinkInFg", accent(palette(10)));
     // \/ inbox text mex link selected
-    add_color("historyLinkInFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyLinkInFgSelected", palette(7).lighter(40));
     // \/ outbox mex link text not selected
-    add_color("historyLinkOutFg", colors["color10"].clone());
+    entries.add_color("historyLinkOutFg", accent(palette(10)));
     // \/ outbox mex link text selected
-    add_color("historyLinkOutFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyLinkOutFgSelected", palette(7).lighter(40));
     // \/ inbox text filename media not selected
-    add_color("historyFileNameInFg", get_element("historyTextInFg"));
+    entries.add_color("historyFileNameInFg", key("historyTextInFg"));
     // \/ inbox text filename selected media
-    add_color("historyFileNameInFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyFileNameInFgSelected", palette(7).lighter(40));
     // \/ outbox text filename media not selected
-    add_color("historyFileNameOutFg", get_element("historyTextOutFg"));
+    entries.add_color("historyFileNameOutFg", key("historyTextOutFg"));
     // \/ outbox text filename selected media
-    add_color("historyFileNameOutFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyFileNameOutFgSelected", palette(7).lighter(40));
     // \/ outbox tick/double tick unselected text
-    add_color("historyOutIconFg", colors["color1"].borrow().lighter(70));
+    entries.add_color("historyOutIconFg", palette(1).lighter(70));
     // \/ outbox tick/double tick selected text
-    add_color("historyOutIconFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyOutIconFgSelected", palette(7).lighter(40));
     // \/ outbox tick/double tick average
-    add_color("historyIconFgInverted", colors["color2"].clone());
+    entries.add_color("historyIconFgInverted", accent(palette(2)));
     // \/ outbox send mex icon (clock)
-    add_color("historySendingOutIconFg", colors["color2"].clone());
+    entries.add_color("historySendingOutIconFg", accent(palette(2)));
     // \/ inbox send message icon (clock)
-    add_color("historySendingInIconFg", colors["color2"].clone());
+    entries.add_color("historySendingInIconFg", accent(palette(2)));
     // \/ inbox media send icon (clock)
-    add_color("historySendingInvertedIconFg", colors["color2"].borrow().alpha(75));
+    entries.add_color("historySendingInvertedIconFg", accent(palette(2)).alpha(75));
     // \/ [UNTESTED]: received phone call arrow
-    add_color("historyCallArrowInFg", colors["color1"].clone());
+    entries.add_untested("historyCallArrowInFg", palette(1));
     // \/ [UNTESTED]: received phone call arrow in a selected message
-    add_color("historyCallArrowInFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_untested("historyCallArrowInFgSelected", palette(7).lighter(40));
     // \/ [UNTESTED]: missed phone call arrow
-    add_color("historyCallArrowMissedInFg", get_element("callArrowMissedFg"));
+    entries.add_untested("historyCallArrowMissedInFg", key("callArrowMissedFg"));
     // \/ [UNTESTED]: missed phone call arrow in a selected message
-    add_color("historyCallArrowMissedInFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_untested("historyCallArrowMissedInFgSelected", palette(7).lighter(40));
     // \/ [UNTESTED]: outgoing phone call arrow
-    add_color("historyCallArrowOutFg", colors["color7"].borrow().lighter(40));
+    entries.add_untested("historyCallArrowOutFg", palette(7).lighter(40));
     // \/ [UNTESTED]: outgoing phone call arrow
-    add_color("historyCallArrowOutFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_untested("historyCallArrowOutFgSelected", palette(7).lighter(40));
     // \/ [UNTESTED]: new unread messages bar background
-    add_color("historyUnreadBarBg", colors["color0"].clone());
+    entries.add_untested("historyUnreadBarBg", palette(0));
     // \/ [UNTESTED]: new unread messages bar shadow
-    add_color("historyUnreadBarBorder", get_element("shadowFg"));
+    entries.add_untested("historyUnreadBarBorder", key("shadowFg"));
     // \/ [UNTESTED]: new unread messages bar text
-    add_color("historyUnreadBarFg", colors["color1"].clone());
+    entries.add_untested("historyUnreadBarFg", palette(1));
     // \/ [UNTESTED]: forwarding messages in a large window size 'choose recipient' background
-    add_color("historyForwardChooseBg", colors["color0"].borrow().alpha(25));
+    entries.add_untested("historyForwardChooseBg", palette(0).alpha(25));
     // \/ [UNTESTED]: forwarding messages in a large window size 'choose recipient' text
-    add_color("historyForwardChooseFg", get_element("windowFgActive"));
+    entries.add_untested("historyForwardChooseFg", key("windowFgActive"));
     // \/ username 1 mex not selected
-    add_color("historyPeer1NameFg", colors["color1"].clone());
+    entries.add_color("historyPeer1NameFg", palette(1));
     // \/ username 1 mex selected
-    add_color("historyPeer1NameFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyPeer1NameFgSelected", palette(7).lighter(40));
     // \/ bg userpic 1
-    add_color("historyPeer1UserpicBg", colors["color1"].clone());
+    entries.add_color("historyPeer1UserpicBg", palette(1));
     // \/ 2 mex username not selected
-    add_color("historyPeer2NameFg", colors["color2"].clone());
+    entries.add_color("historyPeer2NameFg", palette(2));
     // \/ 2 mex username selected
-    add_color("historyPeer2NameFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyPeer2NameFgSelected", palette(7).lighter(40));
     // \/ bg userpic 2
-    add_color("historyPeer2UserpicBg", colors["color2"].clone());
+    entries.add_color("historyPeer2UserpicBg", palette(2));
     // \/ 3 mex username not selected
-    add_color("historyPeer3NameFg", colors["color3"].clone());
+    entries.add_color("historyPeer3NameFg", palette(3));
     // \/ 3 mex username selected
-    add_color("historyPeer3NameFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyPeer3NameFgSelected", palette(7).lighter(40));
     // \/ bg userpic 3
-    add_color("historyPeer3UserpicBg", colors["color3"].clone());
+    entries.add_color("historyPeer3UserpicBg", palette(3));
     // \/ 4 mex username not selected
-    add_color("historyPeer4NameFg", colors["color4"].clone());
+    entries.add_color("historyPeer4NameFg", palette(4));
     // \/ 4 mex username selected
-    add_color("historyPeer4NameFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyPeer4NameFgSelected", palette(7).lighter(40));
     // \/ bg userpic 4
-    add_color("historyPeer4UserpicBg", colors["color4"].clone());
+    entries.add_color("historyPeer4UserpicBg", palette(4));
     // \/ 5 mex username not selected
-    add_color("historyPeer5NameFg", colors["color5"].clone());
+    entries.add_color("historyPeer5NameFg", palette(5));
     // \/ 5 mex username selected
-    add_color("historyPeer5NameFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyPeer5NameFgSelected", palette(7).lighter(40));
     // \/ bg userpic 5
-    add_color("historyPeer5UserpicBg", colors["color5"].clone());
+    entries.add_color("historyPeer5UserpicBg", palette(5));
     // \/ 6 mex username not selected
-    add_color("historyPeer6NameFg", colors["color6"].clone());
+    entries.add_color("historyPeer6NameFg", palette(6));
     // \/ 6 mex username selected
-    add_color("historyPeer6NameFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyPeer6NameFgSelected", palette(7).lighter(40));
     // \/ bg userpic 6
-    add_color("historyPeer6UserpicBg", colors["color6"].clone());
+    entries.add_color("historyPeer6UserpicBg", palette(6));
     // \/ 7 mex username not selected
-    add_color("historyPeer7NameFg", colors["color7"].clone());
+    entries.add_color("historyPeer7NameFg", palette(7));
     // \/ 7 mex username selected
-    add_color("historyPeer7NameFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyPeer7NameFgSelected", palette(7).lighter(40));
     // \/ bg userpic 7
-    add_color("historyPeer7UserpicBg", colors["color7"].clone());
+    entries.add_color("historyPeer7UserpicBg", palette(7));
     // \/ 8 mex username not selected
-    add_color("historyPeer8NameFg", colors["color8"].clone());
+    entries.add_color("historyPeer8NameFg", palette(8));
     // \/ 8 mex username selected
-    add_color("historyPeer8NameFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyPeer8NameFgSelected", palette(7).lighter(40));
     // \/ bg userpic 8
-    add_color("historyPeer8UserpicBg", colors["color8"].clone());
+    entries.add_color("historyPeer8UserpicBg", palette(8));
     // \/ userpic initial fg
-    add_color("historyPeerUserpicFg", get_element("windowFgActive"));
+    entries.add_color("historyPeerUserpicFg", key("windowFgActive"));
     // \/ bg normal slash container
-    add_color("historyScrollBarBg", colors["color7"].borrow().alpha(45));
+    entries.add_color("historyScrollBarBg", palette(7).alpha(45));
     // \/ bg slash container with cursor over
-    add_color("historyScrollBarBgOver", colors["color7"].borrow().alpha(65));
+    entries.add_color("historyScrollBarBgOver", palette(7).alpha(65));
     // \/ bg normal slash
-    add_color("historyScrollBg", colors["color7"].borrow().alpha(25));
+    entries.add_color("historyScrollBg", palette(7).alpha(25));
     // \/ bg bar with cursor above
-    add_color("historyScrollBgOver", colors["color7"].borrow().alpha(40));
+    entries.add_color("historyScrollBgOver", palette(7).alpha(40));
 
-    add_comment("Msg");
+    entries.add_comment("Msg");
+    let [msg_in_bg, msg_out_bg] = message_bubbles(bubbles);
     // \/ inbox mex bg not selected
-    add_color("msgInBg", colors["color7"].borrow().darker(70));
+    entries.add_color("msgInBg", msg_in_bg);
     // \/ inbox mex bg selected
-    add_color("msgInBgSelected", colors["color2"].clone());
+    entries.add_color("msgInBgSelected", bubbles.over_background(accent(palette(2))));
     // \/ outbox mex bg not selected
-    add_color("msgOutBg", colors["color8"].borrow().darker(60));
+    entries.add_color("msgOutBg", msg_out_bg);
     // \/ outbox mex bg selected
-    add_color("msgOutBgSelected", colors["color2"].clone());
+    entries.add_color("msgOutBgSelected", bubbles.over_background(accent(palette(2))));
     // \/ overlay over the selected message
-    add_color("msgSelectOverlay", colors["color2"].borrow().alpha(25));
+    entries.add_color("msgSelectOverlay", accent(palette(2)).alpha(25));
     // \/ overlay over the selected sticker
-    add_color("msgStickerOverlay", colors["color2"].borrow().alpha(45));
+    entries.add_color("msgStickerOverlay", accent(palette(2)).alpha(45));
     // \/ inbox text color information type forwarded by... not selected
-    add_color("msgInServiceFg", get_element("windowActiveTextFg"));
+    entries.add_color("msgInServiceFg", key("windowActiveTextFg"));
     // \/ inbox text color information type forwarded by... selected
-    add_color("msgInServiceFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("msgInServiceFgSelected", palette(7).lighter(40));
     // \/ outbox text color information type submitted by... not selected
-    add_color("msgOutServiceFg", colors["color10"].clone());
+    entries.add_color("msgOutServiceFg", accent(palette(10)));
     // \/ outbox text color information type forwarded by... selected
-    add_color("msgOutServiceFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("msgOutServiceFgSelected", palette(7).lighter(40));
     // \/ inbox ombre mex not selected
-    add_color("msgInShadow", colors["color0"].borrow().alpha(00));
+    // The shadows follow their bubble, whichever palette entry and opacity it has
+    entries.add_color("msgInShadow", key("msgInBg").darker(40).alpha(30));
     // \/ inbox shadow mex selected
-    add_color("msgInShadowSelected", colors["color2"].borrow().alpha(00));
+    entries.add_color("msgInShadowSelected", key("msgInBgSelected").darker(40).alpha(30));
     // \/ outbox shadow mex not selected
-    add_color("msgOutShadow", colors["color0"].borrow().alpha(00));
+    entries.add_color("msgOutShadow", key("msgOutBg").darker(40).alpha(30));
     // \/ outbox shadow mex selected
-    add_color("msgOutShadowSelected", colors["color2"].borrow().alpha(00));
+    entries.add_color("msgOutShadowSelected", key("msgOutBgSelected").darker(40).alpha(30));
     // \/ inbox hours sending message not selected
-    add_color("msgInDateFg", colors["color7"].borrow().darker(40));
+    entries.add_color("msgInDateFg", palette(7).darker(40));
     // \/ inbox hours sending message selected
-    add_color("msgInDateFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("msgInDateFgSelected", palette(7).lighter(40));
     // \/ outbox hours send mex not selected
-    add_color("msgOutDateFg", colors["color7"].borrow().darker(40));
+    entries.add_color("msgOutDateFg", palette(7).darker(40));
     // \/ outbox hours sending message selected
-    add_color("msgOutDateFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("msgOutDateFgSelected", palette(7).lighter(40));
     // \/ service fg mex (data mex type, group title changed, etc.)
-    add_color("msgServiceFg", get_element("windowFgActive"));
+    entries.add_color("msgServiceFg", overlays.text_or(key("windowFgActive")));
     // \/ bg mex service not selected
-    add_color("msgServiceBg", colors["color0"].clone());
+    entries.add_color("msgServiceBg", overlays.over_background(palette(overlays.role)));
     // \/ bg mex of selected service
-    add_color("msgServiceBgSelected", colors["color10"].clone());
+    entries.add_color("msgServiceBgSelected", accent(palette(10)));
     // \/ inbox text color type forwarded from etc... not selected
-    add_color("msgInReplyBarColor", colors["color10"].clone());
+    entries.add_color("msgInReplyBarColor", accent(palette(10)));
     // \/ inbox text color type forwarded from etc.... selected
-    add_color("msgInReplyBarSelColor", colors["color7"].borrow().lighter(40));
+    entries.add_color("msgInReplyBarSelColor", palette(7).lighter(40));
     // \/ outbox text color type forwarded by etc.... not selected
-    add_color("msgOutReplyBarColor", colors["color10"].clone());
+    entries.add_color("msgOutReplyBarColor", accent(palette(10)));
     // \/ outbox text color type forwarded from etc.... selected
-    add_color("msgOutReplyBarSelColor", colors["color7"].borrow().lighter(40));
+    entries.add_color("msgOutReplyBarSelColor", palette(7).lighter(40));
     // \/ [UNTESTED]: Forwarded text color when dealing with images
-    add_color("msgImgReplyBarColor", get_element("msgServiceFg"));
+    entries.add_untested("msgImgReplyBarColor", key("msgServiceFg"));
     // \/ inbox mex monospace not selected
-    add_color("msgInMonoFg", colors["color7"].clone());
+    entries.add_color("msgInMonoFg", palette(7));
     // \/ inbox mex monospace selected
-    add_color("msgInMonoFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("msgInMonoFgSelected", palette(7).lighter(40));
     // \/ outbox mex monospace not selected
-    add_color("msgOutMonoFg", colors["color7"].clone());
+    entries.add_color("msgOutMonoFg", palette(7));
     // \/ outbox mex monospace selected
-    add_color("msgOutMonoFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("msgOutMonoFgSelected", palette(7).lighter(40));
     // \/ mex media fg bubble hours sending
-    add_color("msgDateImgFg", get_element("msgServiceFg"));
+    entries.add_color("msgDateImgFg", key("msgServiceFg"));
     // \/ mex media bg bubble hours sending
-    add_color("msgDateImgBg", colors["color0"].borrow().alpha(30));
+    entries.add_color("msgDateImgBg", palette(0).alpha(30));
     // \/ mex media bg bubble hours sending with cursor over
-    add_color("msgDateImgBgOver", colors["color0"].borrow().alpha(45));
+    entries.add_color("msgDateImgBgOver", palette(0).alpha(45));
     // \/ mex media bg bubble hours sending selected
-    add_color("msgDateImgBgSelected", colors["color2"].borrow().alpha(50));
+    entries.add_color("msgDateImgBgSelected", accent(palette(2)).alpha(50));
     // \/ inbox file media file mex download not selected
-    add_color("msgFileThumbLinkInFg", get_element("lightButtonFg"));
+    entries.add_color("msgFileThumbLinkInFg", key("lightButtonFg"));
     // \/ inbox file media file mex download selected
-    add_color("msgFileThumbLinkInFgSelected", get_element("lightButtonFgOver"));
+    entries.add_color("msgFileThumbLinkInFgSelected", key("lightButtonFgOver"));
     // \/ outbox file media file mex download not selected
-    add_color("msgFileThumbLinkOutFg", colors["color10"].clone());
+    entries.add_color("msgFileThumbLinkOutFg", accent(palette(10)));
     // \/ outbox file media file mex download selected
-    add_color("msgFileThumbLinkOutFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("msgFileThumbLinkOutFgSelected", palette(7).lighter(40));
     // \/ inbox bg audio file circle download
-    add_color("msgFileInBg", colors["color2"].clone());
+    entries.add_color("msgFileInBg", accent(palette(2)));
     // \/ inbox bg audio file download circle with cursor above
-    add_color("msgFileInBgOver", colors["color2"].borrow().lighter(30));
+    entries.add_color("msgFileInBgOver", accent(palette(2)).lighter(30));
     // \/ inbox bg audio file circle download selected
-    add_color("msgFileInBgSelected", colors["color2"].borrow().lighter(50));
+    entries.add_color("msgFileInBgSelected", accent(palette(2)).lighter(50));
     // \/ outbox bg audio file circle download
-    add_color("msgFileOutBg", colors["color2"].clone());
+    entries.add_color("msgFileOutBg", accent(palette(2)));
     // \/ outbox bg audio file circle download with cursor above
-    add_color("msgFileOutBgOver", colors["color2"].borrow().lighter(30));
+    entries.add_color("msgFileOutBgOver", accent(palette(2)).lighter(30));
     // \/ outbox bg audio file circle download selected
-    add_color("msgFileOutBgSelected", colors["color2"].borrow().lighter(50));
+    entries.add_color("msgFileOutBgSelected", accent(palette(2)).lighter(50));
     // \/ [UNTESTED]: blue shared links / files without image square thumbnail
-    add_color("msgFile1Bg", colors["color1"].clone());
+    entries.add_untested("msgFile1Bg", palette(1));
     // \/ [UNTESTED]: blue shared files without image download circle background
-    add_color("msgFile1BgDark", colors["color1"].borrow().darker(30));
+    entries.add_untested("msgFile1BgDark", palette(1).darker(30));
     // \/ [UNTESTED]: blue shared files without image download circle background with mouse over
-    add_color("msgFile1BgOver", colors["color1"].borrow().lighter(40));
+    entries.add_untested("msgFile1BgOver", palette(1).lighter(40));
     // \/ [UNTESTED]: blue shared files without image download circle background if file is selected
-    add_color("msgFile1BgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_untested("msgFile1BgSelected", palette(7).lighter(40));
     // \/ [UNTESTED]: green shared links / shared files without image square thumbnail
-    add_color("msgFile2Bg", colors["color2"].clone());
+    entries.add_untested("msgFile2Bg", palette(2));
     // \/ [UNTESTED]: green shared files without image download circle background
-    add_color("msgFile2BgDark", colors["color2"].borrow().darker(30));
+    entries.add_untested("msgFile2BgDark", palette(2).darker(30));
     // \/ [UNTESTED]: green shared files without image download circle background with mouse over
-    add_color("msgFile2BgOver", colors["color2"].borrow().lighter(40));
+    entries.add_untested("msgFile2BgOver", palette(2).lighter(40));
     // \/ [UNTESTED]: green shared files without image download circle background if file is selected
-    add_color("msgFile2BgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_untested("msgFile2BgSelected", palette(7).lighter(40));
     // \/ [UNTESTED]: red shared links / shared files without image square thumbnail
-    add_color("msgFile3Bg", colors["color3"].clone());
+    entries.add_untested("msgFile3Bg", palette(3));
     // \/ [UNTESTED]: red shared files without image download circle background
-    add_color("msgFile3BgDark", colors["color7"].borrow().darker(30));
+    entries.add_untested("msgFile3BgDark", palette(7).darker(30));
     // \/ [UNTESTED]: red shared files without image download circle background with mouse over
-    add_color("msgFile3BgOver", colors["color7"].borrow().lighter(40));
+    entries.add_untested("msgFile3BgOver", palette(7).lighter(40));
     // \/ [UNTESTED]: red shared files without image download circle background if file is selected
-    add_color("msgFile3BgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_untested("msgFile3BgSelected", palette(7).lighter(40));
     // \/ [UNTESTED]: yellow shared links / shared files without image square thumbnail
-    add_color("msgFile4Bg", colors["color3"].clone());
+    entries.add_untested("msgFile4Bg", palette(3));
 
     // \/ FIXME: Seems to be gone  [UNTESTED]: yellow shared files without image download circle background"
-    add_color("msgFile4BgDark", colors["color3"].borrow().darker(30));
+    entries.add_untested("msgFile4BgDark", palette(3).darker(30));
     // \/ FIXME: Seems to be gone  [UNTESTED]: yellow shared files without image download circle background with mouse over"),
-    add_color("msgFile4BgOver", colors["color3"].borrow().lighter(40));
+    entries.add_untested("msgFile4BgOver", palette(3).lighter(40));
 
     // \/ [UNTESTED]: yellow shared files without image download circle background if file is selected
-    add_color("msgFile4BgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_untested("msgFile4BgSelected", palette(7).lighter(40));
     // \/ inbox ondina audio inactive unselected
-    add_color("msgWaveformInActive", get_element("windowBgActive"));
+    entries.add_color("msgWaveformInActive", key("windowBgActive"));
     // \/ inbox wave audio inactive selected
-    add_color("msgWaveformInActiveSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("msgWaveformInActiveSelected", palette(7).lighter(40));
     // \/ inbox wave active audio not selected
-    add_color("msgWaveformInInactive", colors["color7"].borrow().darker(30));
+    entries.add_color("msgWaveformInInactive", palette(7).darker(30));
     // \/ inbox wave active audio selected
-    add_color("msgWaveformInInactiveSelected", colors["color2"].borrow().lighter(40));
+    entries.add_color("msgWaveformInInactiveSelected", accent(palette(2)).lighter(40));
     // \/ outbox ondina audio inactive unchecked
-    add_color("msgWaveformOutActive", colors["color2"].clone());
+    entries.add_color("msgWaveformOutActive", accent(palette(2)));
     // \/ outbox wave audio inactive selected
-    add_color("msgWaveformOutActiveSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("msgWaveformOutActiveSelected", palette(7).lighter(40));
     // \/ outbox wave active audio not selected
-    add_color("msgWaveformOutInactive", colors["color7"].borrow().darker(30));
+    entries.add_color("msgWaveformOutInactive", palette(7).darker(30));
     // \/ outbox wave active audio selected
-    add_color("msgWaveformOutInactiveSelected", colors["color2"].borrow().lighter(40));
+    entries.add_color("msgWaveformOutInactiveSelected", accent(palette(2)).lighter(40));
     // \/ [UNTESTED]: this is painted over a bot inline keyboard button (which has msgServiceBg background) when mouse is over that button
-    add_color("msgBotKbOverBgAdd", colors["color7"].borrow().alpha(05));
+    entries.add_untested("msgBotKbOverBgAdd", palette(7).alpha(5));
     // \/ [UNTESTED]: bot inline keyboard button icon in the top-right corner (like in @vote bot when a poll is ready to be shared)
-    add_color("msgBotKbIconFg", get_element("msgServiceFg"));
+    entries.add_untested("msgBotKbIconFg", key("msgServiceFg"));
     // \/ [UNTESTED]: bot inline keyboard button ripple effect
-    add_color("msgBotKbRippleBg", colors["color1"].borrow().alpha(05));
+    entries.add_untested("msgBotKbRippleBg", palette(1).alpha(5));
 
-    add_comment("Download animations");
+    entries.add_comment("Download animations");
     // \/ inbox file download arrow not selected
-    add_color("historyFileInIconFg", colors["color0"].clone());
+    entries.add_color("historyFileInIconFg", palette(0));
     // \/ inbox download arrow selected file
-    add_color("historyFileInIconFgSelected", colors["color10"].clone());
+    entries.add_color("historyFileInIconFgSelected", accent(palette(10)));
     // \/ inbox particle animation download file not selected
-    add_color("historyFileInRadialFg", colors["color0"].clone());
+    entries.add_color("historyFileInRadialFg", palette(0));
     // \/ inbox particle animation download selected file
-    add_color("historyFileInRadialFgSelected", get_element("historyFileInIconFgSelected"));
+    entries.add_color("historyFileInRadialFgSelected", key("historyFileInIconFgSelected"));
     // \/ outbox file download arrow not selected
-    add_color("historyFileOutIconFg", colors["color0"].clone());
+    entries.add_color("historyFileOutIconFg", palette(0));
     // \/ outbox download arrow selected file
-    add_color("historyFileOutIconFgSelected", colors["color10"].clone());
+    entries.add_color("historyFileOutIconFgSelected", accent(palette(10)));
     // \/ outbox particle animation file download not selected
-    add_color("historyFileOutRadialFg", get_element("historyFileOutIconFg"));
+    entries.add_color("historyFileOutRadialFg", key("historyFileOutIconFg"));
     // \/ outbox particle animation download selected file
-    add_color("historyFileOutRadialFgSelected", colors["color10"].clone());
+    entries.add_color("historyFileOutRadialFgSelected", accent(palette(10)));
     // \/ fg photo/video download arrow not selected
-    add_color("historyFileThumbIconFg", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyFileThumbIconFg", palette(7).lighter(40));
     // \/ fg photo/video download arrow selected
-    add_color("historyFileThumbIconFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyFileThumbIconFgSelected", palette(7).lighter(40));
     // \/ fg particle animation photo/video download not selected
-    add_color("historyFileThumbRadialFg", get_element("historyFileThumbIconFg"));
+    entries.add_color("historyFileThumbRadialFg", key("historyFileThumbIconFg"));
     // \/ fg particle animation download selected photo/video
-    add_color("historyFileThumbRadialFgSelected", colors["color7"].borrow().lighter(40));
+    entries.add_color("historyFileThumbRadialFgSelected", palette(7).lighter(40));
     // \/ [UNTESTED]: radial playback progress in round video messages
-    add_color("historyVideoMessageProgressFg", get_element("historyFileThumbIconFg"));
+    entries.add_untested("historyVideoMessageProgressFg", key("historyFileThumbIconFg"));
 
-    add_comment("YouTube");
+    entries.add_comment("YouTube");
     // \/ [UNTESTED]: youtube play icon background (when a link to a youtube video with a webpage preview is sent)
-    add_color("youtubePlayIconBg", Color::from_hex("83131c").unwrap().alpha(50));
+    entries.add_untested("youtubePlayIconBg", key("colorError").darker(50).alpha(50));
     // \/ [UNTESTED]: youtube play icon arrow (when a link to a youtube video with a webpage preview is sent)
-    add_color("youtubePlayIconFg", get_element("windowFgActive"));
+    entries.add_untested("youtubePlayIconFg", key("windowFgActive"));
 
-    add_comment("Video");
+    entries.add_comment("Video");
     // \/ [UNTESTED]: other video play icon background (like when a link to a vimeo video with a webpage preview is sent)
-    add_color("videoPlayIconBg", colors["color0"].borrow().alpha(45));
+    entries.add_untested("videoPlayIconBg", palette(0).alpha(45));
     // \/ [UNTESTED]: other video play icon arrow (like when a link to a vimeo video with a webpage preview is sent)
-    add_color("videoPlayIconFg", colors["color7"].borrow().lighter(40));
+    entries.add_untested("videoPlayIconFg", palette(7).lighter(40));
 
-    add_comment("Toast");
+    entries.add_comment("Toast");
     // \/ [UNTESTED]: toast notification background (like when you click on your t.me link when editing your username)
-    add_color("toastBg", colors["color0"].borrow().alpha(65));
+    entries.add_untested("toastBg", palette(0).alpha(65));
     // \/ [UNTESTED]: toast notification text (like when you click on your t.me link when editing your username)
-    add_color("toastFg", get_element("windowFgActive"));
+    entries.add_untested("toastFg", key("windowFgActive"));
 
-    add_comment("Report");
+    entries.add_comment("Report");
     // \/ [UNTESTED]: report spam panel background (like a non contact user writes your for the first time)
-    add_color("reportSpamBg", colors["color0"].clone());
+    entries.add_untested("reportSpamBg", palette(0));
     // \/ [UNTESTED]: report spam panel text (when you send a report from that panel)
-    add_color("reportSpamFg", get_element("windowFg"));
+    entries.add_untested("reportSpamFg", key("windowFg"));
 
-    add_comment("Composition area");
+    entries.add_comment("Composition area");
     // \/ bg arrow button to scroll to the bottom of the chat
-    add_color("historyToDownBg", colors["color0"].clone());
+    entries.add_color("historyToDownBg", overlays.over_background(palette(overlays.role)));
     // \/ bg arrow button to scroll to the bottom of the chat with cursor above
-    add_color("historyToDownBgOver", colors["color0"].borrow().lighter(40));
+    entries.add_color("historyToDownBgOver", overlays.over_background(palette(overlays.role).lighter(40)));
     // \/ bg arrow button to scroll to the bottom of the selected chat
-    add_color("historyToDownBgRipple", colors["color0"].borrow().lighter(60));
+    entries.add_color("historyToDownBgRipple", overlays.over_background(palette(overlays.role).lighter(60)));
     // \/ fg arrow button to scroll to the bottom of the chat
-    add_color("historyToDownFg", colors["color7"].clone());
+    entries.add_color("historyToDownFg", overlays.text_or(palette(7)));
     // \/ fg arrow button to scroll to the bottom of the chat with cursor above
-    add_color("historyToDownFgOver", get_element("menuIconFgOver"));
+    entries.add_color("historyToDownFgOver", key("menuIconFgOver"));
     // \/ button shadow
-    add_color("historyToDownShadow", colors["color0"].borrow().alpha(25));
+    entries.add_color("historyToDownShadow", palette(0).alpha(25));
     // \/ bg composition area at the bottom right of the home screen
-    add_color("historyComposeAreaBg", colors["color0"].clone());
+    entries.add_color("historyComposeAreaBg", palette(0));
     // \/ fg of the area just mentioned
-    add_color("historyComposeAreaFg", get_element("historyTextInFg"));
+    entries.add_color("historyComposeAreaFg", key("historyTextInFg"));
     // \/ mex text selected in the composition area
-    add_color("historyComposeAreaFgService", get_element("msgInDateFg"));
+    entries.add_color("historyComposeAreaFgService", key("msgInDateFg"));
     // \/ fg composition area icons
-    add_color("historyComposeIconFg", get_element("menuIconFg"));
+    entries.add_color("historyComposeIconFg", key("menuIconFg"));
     // \/ fg composition area icons with cursor above
-    add_color("historyComposeIconFgOver", get_element("menuIconFgOver"));
+    entries.add_color("historyComposeIconFgOver", key("menuIconFgOver"));
     // \/ fg send message icon
-    add_color("historySendIconFg", get_element("windowBgActive"));
+    entries.add_color("historySendIconFg", key("windowBgActive"));
     // \/ fg message sending icon with cursor above
-    add_color("historySendIconFgOver", get_element("windowBgActive"));
+    entries.add_color("historySendIconFgOver", key("windowBgActive"));
     // \/ [UNTESTED]: pinned message area background
-    add_color("historyPinnedBg", get_element("historyComposeAreaBg"));
+    entries.add_untested("historyPinnedBg", key("historyComposeAreaBg"));
     // \/ bg area reply, forward, edit mex
-    add_color("historyReplyBg", get_element("historyComposeAreaBg"));
+    entries.add_color("historyReplyBg", key("historyComposeAreaBg"));
     // \/ fg left arrow icon in reply, forward, edit message area
-    add_color("historyReplyIconFg", get_element("windowBgActive"));
+    entries.add_color("historyReplyIconFg", key("windowBgActive"));
     // \/ fg cross icon in reply, forward, edit message area
-    add_color("historyReplyCancelFg", get_element("cancelIconFg"));
+    entries.add_color("historyReplyCancelFg", key("cancelIconFg"));
     // \/ fg cross icon in reply, forward, edit message area with cursor above
-    add_color("historyReplyCancelFgOver", get_element("cancelIconFgOver"));
+    entries.add_color("historyReplyCancelFgOver", key("cancelIconFgOver"));
     // \/ [UNTESTED]: unblock / join channel / mute channel button background
-    add_color("historyComposeButtonBg", get_element("historyComposeAreaBg"));
+    entries.add_untested("historyComposeButtonBg", key("historyComposeAreaBg"));
     // \/ [UNTESTED]: unblock / join channel / mute channel button background with mouse over
-    add_color("historyComposeButtonBgOver", colors["color0"].borrow().lighter(40));
+    entries.add_untested("historyComposeButtonBgOver", palette(0).lighter(40));
     // \/ [UNTESTED]: unblock / join channel / mute channel button ripple effect
-    add_color("historyComposeButtonBgRipple", colors["color0"].borrow().lighter(60));
+    entries.add_untested("historyComposeButtonBgRipple", palette(0).lighter(60));
 
-    add_comment("Overview");
+    entries.add_comment("Overview");
     // \/ [UNTESTED]: shared files / links checkbox background for not selected rows when some rows are selected
-    add_color("overviewCheckBg", colors["color0"].borrow().alpha(25));
+    entries.add_untested("overviewCheckBg", palette(0).alpha(25));
     // \/ [UNTESTED]: shared files / links checkbox icon for not selected rows when some rows are selected
-    add_color("overviewCheckFg", colors["color7"].borrow().lighter(40));
+    entries.add_untested("overviewCheckFg", palette(7).lighter(40));
     // \/ [UNTESTED]: shared files / links checkbox icon for selected rows
-    add_color("overviewCheckFgActive", colors["color7"].borrow().lighter(40));
+    entries.add_untested("overviewCheckFgActive", palette(7).lighter(40));
     // \/ [UNTESTED]: shared photos / videos / links fill for selected rows
-    add_color("overviewPhotoSelectOverlay", colors["color1"].borrow().alpha(10));
+    entries.add_untested("overviewPhotoSelectOverlay", palette(1).alpha(10));
 
-    add_comment("Profile");
+    entries.add_comment("Profile");
     // \/ [UNTESTED]: group members list in group profile user last seen text with mouse over
-    add_color("profileStatusFgOver", colors["color1"].clone());
+    entries.add_untested("profileStatusFgOver", palette(1));
     // \/ [UNTESTED]: profile verified check icon background
-    add_color("profileVerifiedCheckBg", get_element("windowBgActive"));
+    entries.add_untested("profileVerifiedCheckBg", key("windowBgActive"));
     // \/ [UNTESTED]: profile verified check icon tick
-    add_color("profileVerifiedCheckFg", get_element("windowFgActive"));
+    entries.add_untested("profileVerifiedCheckFg", key("windowFgActive"));
     // \/ [UNTESTED]: group members list admin star icon
-    add_color("profileAdminStartFg", get_element("windowBgActive"));
+    entries.add_untested("profileAdminStartFg", key("windowBgActive"));
 
-    add_comment("Notifications");
+    entries.add_comment("Notifications");
     // \/ [UNTESTED]: custom notifications settings box monitor color
-    add_color("notificationsBoxMonitorFg", get_element("windowFg"));
+    entries.add_untested("notificationsBoxMonitorFg", key("windowFg"));
     // \/ [UNTESTED]: #6389a8, // custom notifications settings box monitor screen background
-    add_color("notificationsBoxScreenBg", get_element("dialogsBgActive"));
+    entries.add_untested("notificationsBoxScreenBg", key("dialogsBgActive"));
     // \/ [UNTESTED]: custom notifications settings box small sample userpic placeholder
-    add_color("notificationSampleUserpicFg", get_element("windowBgActive"));
+    entries.add_untested("notificationSampleUserpicFg", key("windowBgActive"));
     // \/ [UNTESTED]: custom notifications settings box small sample close button placeholder
-    add_color("notificationSampleCloseFg", colors["color7"].clone());
+    entries.add_untested("notificationSampleCloseFg", palette(7));
     // \/ [UNTESTED]: custom notifications settings box small sample text placeholder
-    add_color("notificationSampleTextFg", colors["color7"].clone());
+    entries.add_untested("notificationSampleTextFg", palette(7));
     // \/ [UNTESTED]: custom notifications settings box small sample name placeholder
-    add_color("notificationSampleNameFg", colors["color0"].borrow().lighter(40));
+    entries.add_untested("notificationSampleNameFg", palette(0).lighter(40));
 
-    add_comment("Change");
+    entries.add_comment("Change");
     // \/ [UNTESTED]: change phone number box left simcard icon
-    add_color("changePhoneSimcardFrom", get_element("notificationSampleTextFg"));
+    entries.add_untested("changePhoneSimcardFrom", key("notificationSampleTextFg"));
     // \/ [UNTESTED]: change phone number box right simcard and plane icons
-    add_color("changePhoneSimcardTo", get_element("notificationSampleNameFg"));
+    entries.add_untested("changePhoneSimcardTo", key("notificationSampleNameFg"));
 
-    add_comment("Main");
+    entries.add_comment("Main");
     // \/ bg menu on the left
-    add_color("mainMenuBg", get_element("windowBg"));
+    entries.add_color("mainMenuBg", key("windowBg"));
     // \/ bg top cover menu on the left (top part)
-    add_color("mainMenuCoverBg", colors["color2"].clone());
+    entries.add_color("mainMenuCoverBg", accent(palette(2)));
     // \/ fg top cover menu on the left
-    add_color("mainMenuCoverFg", get_element("windowFgActive"));
+    entries.add_color("mainMenuCoverFg", key("windowFgActive"));
     // \/ fg speech bubble icon in the left menu
-    add_color("mainMenuCloudFg", colors["color7"].borrow().lighter(40));
+    entries.add_color("mainMenuCloudFg", palette(7).lighter(40));
     // \/ bg speech bubble icon in the left menu
-    add_color("mainMenuCloudBg", colors["color4"].clone());
+    entries.add_color("mainMenuCloudBg", palette(4));
 
-    add_comment("Media");
+    entries.add_comment("Media");
     // \/ inbox status text (type weight of the audio file) not selected
-    add_color("mediaInFg", get_element("msgInDateFg"));
+    entries.add_color("mediaInFg", key("msgInDateFg"));
     // \/ inbox status text (type weight of the audio file) selected
-    add_color("mediaInFgSelected", get_element("msgInDateFgSelected"));
+    entries.add_color("mediaInFgSelected", key("msgInDateFgSelected"));
     // \/ status text outbox (type weight of the audio file) not selected
-    add_color("mediaOutFg", get_element("msgOutDateFg"));
+    entries.add_color("mediaOutFg", key("msgOutDateFg"));
     // \/ status text outbox (type weight of the audio file) selected
-    add_color("mediaOutFgSelected", get_element("msgOutDateFgSelected"));
+    entries.add_color("mediaOutFgSelected", key("msgOutDateFgSelected"));
     // \/ [UNTESTED]: audio file player background
-    add_color("mediaPlayerBg", get_element("windowBg"));
+    entries.add_untested("mediaPlayerBg", key("windowBg"));
     // \/ [UNTESTED]: audio file player playback progress already played part
-    add_color("mediaPlayerActiveFg", get_element("windowBgActive"));
+    entries.add_untested("mediaPlayerActiveFg", key("windowBgActive"));
     // \/ [UNTESTED]: audio file player playback progress upcoming (not played yet) part with mouse over
-    add_color("mediaPlayerInactiveFg", get_element("sliderBgInactive"));
+    entries.add_untested("mediaPlayerInactiveFg", key("sliderBgInactive"));
     // \/ [UNTESTED]: audio file player loading progress (when you're playing an audio file and switch to the previous one which is not loaded yet)
-    add_color("mediaPlayerDisabledFg", colors["color1"].clone());
+    entries.add_untested("mediaPlayerDisabledFg", palette(1));
+    // \/ [UNTESTED]: song cover overlay
+    entries.add_untested("songCoverOverlayFg", hex("00000066"));
 
-    add_comment("Mediaview");
+    entries.add_comment("Mediaview");
     // \/ [UNTESTED]: file rectangle background (when you view a png file in Media Viewer and go to a previous, not loaded yet, file)
-    add_color("mediaviewFileBg", get_element("windowBg"));
+    entries.add_untested("mediaviewFileBg", key("windowBg"));
     // \/ [UNTESTED]: file name in file rectangle
-    add_color("mediaviewFileNameFg", get_element("windowFg"));
+    entries.add_untested("mediaviewFileNameFg", key("windowFg"));
     // \/ [UNTESTED]: file size text in file rectangle
-    add_color("mediaviewFileSizeFg", get_element("windowSubTextFg"));
+    entries.add_untested("mediaviewFileSizeFg", key("windowSubTextFg"));
     // \/ [UNTESTED]: red file thumbnail placeholder corner in file rectangle (for a file without thumbnail, like .pdf)
-    add_color("mediaviewFileRedCornerFg", colors["color1"].clone());
+    entries.add_untested("mediaviewFileRedCornerFg", key("colorError"));
     // \/ [UNTESTED]: yellow file thumbnail placeholder corner in file rectangle (for a file without thumbnail, like .zip)
-    add_color("mediaviewFileYellowCornerFg", colors["color2"].clone());
+    entries.add_untested("mediaviewFileYellowCornerFg", mapping.status.get(Status::Warning));
     // \/ [UNTESTED]: green file thumbnail placeholder corner in file rectangle (for a file without thumbnail, like .exe)
-    add_color("mediaviewFileGreenCornerFg", colors["color3"].clone());
+    entries.add_untested("mediaviewFileGreenCornerFg", key("colorSuccess"));
     // \/ [UNTESTED]: blue file thumbnail placeholder corner in file rectangle (for a file without thumbnail, like .dmg)
-    add_color("mediaviewFileBlueCornerFg", colors["color4"].clone());
+    entries.add_untested("mediaviewFileBlueCornerFg", mapping.status.get(Status::Info));
     // \/ [UNTESTED]: file extension text in file thumbnail placeholder in file rectangle
-    add_color("mediaviewFileExtFg", get_element("activeButtonFg"));
+    entries.add_untested("mediaviewFileExtFg", key("activeButtonFg"));
     // \/ [UNTESTED]: context menu in Media Viewer background
-    add_color("mediaviewMenuBg", colors["color0"].clone());
+    entries.add_untested("mediaviewMenuBg", palette(0));
     // \/ [UNTESTED]: context menu item background with mouse over
-    add_color("mediaviewMenuBgOver", colors["color0"].borrow().lighter(40));
+    entries.add_untested("mediaviewMenuBgOver", palette(0).lighter(40));
     // \/ [UNTESTED]: context menu item ripple effect
-    add_color("mediaviewMenuBgRipple", colors["color0"].borrow().lighter(60));
+    entries.add_untested("mediaviewMenuBgRipple", palette(0).lighter(60));
     // \/ [UNTESTED]: context menu item text
-    add_color("mediaviewMenuFg", get_element("windowFgActive"));
+    entries.add_untested("mediaviewMenuFg", key("windowFgActive"));
     // \/ [UNTESTED]: media viewer background
-    add_color("mediaviewBg", colors["color0"].borrow().darker(30));
+    entries.add_untested("mediaviewBg", palette(0).darker(30));
     // \/ [UNTESTED]: media viewer background when viewing a video in full screen
-    add_color("mediaviewVideoBg", get_element("imageBg"));
+    entries.add_untested("mediaviewVideoBg", key("imageBg"));
     // \/ [UNTESTED]: controls background (like next photo / previous photo)
-    add_color("mediaviewControlBg", colors["color0"].borrow().darker(50));
+    entries.add_untested("mediaviewControlBg", palette(0).darker(50));
     // \/ [UNTESTED]: controls icon (like next photo / previous photo)
-    add_color("mediaviewControlFg", get_element("windowFgActive"));
+    entries.add_untested("mediaviewControlFg", key("windowFgActive"));
     // \/ [UNTESTED]: caption text background (when viewing photo with caption)
-    add_color("mediaviewCaptionBg", colors["color0"].borrow().darker(50));
+    entries.add_untested("mediaviewCaptionBg", palette(0).darker(50));
     // \/ [UNTESTED]: caption text
-    add_color("mediaviewCaptionFg", get_element("mediaviewControlFg"));
+    entries.add_untested("mediaviewCaptionFg", key("mediaviewControlFg"));
     // \/ [UNTESTED]: caption text link
-    add_color("mediaviewTextLinkFg", colors["color7"].clone());
+    entries.add_untested("mediaviewTextLinkFg", palette(7));
     // \/ [UNTESTED]: save to file toast message background in Media Viewer
-    add_color("mediaviewSaveMsgBg", get_element("toastBg"));
+    entries.add_untested("mediaviewSaveMsgBg", key("toastBg"));
     // \/ [UNTESTED]: save to file toast message text
-    add_color("mediaviewSaveMsgFg", get_element("toastFg"));
+    entries.add_untested("mediaviewSaveMsgFg", key("toastFg"));
     // \/ [UNTESTED]: video playback progress already played part
-    add_color("mediaviewPlaybackActive", colors["color7"].clone());
+    entries.add_untested("mediaviewPlaybackActive", palette(7));
     // \/ [UNTESTED]: video playback progress upcoming (not played yet) part
-    add_color("mediaviewPlaybackInactive", colors["color7"].borrow().darker(50));
+    entries.add_untested("mediaviewPlaybackInactive", palette(7).darker(50));
     // \/ [UNTESTED]: video playback progress already played part with mouse over
-    add_color("mediaviewPlaybackActiveOver", colors["color7"].borrow().lighter(40));
+    entries.add_untested("mediaviewPlaybackActiveOver", palette(7).lighter(40));
     // \/ [UNTESTED]: video playback progress upcoming (not played yet) part with mouse over
-    add_color("mediaviewPlaybackInactiveOver", colors["color7"].borrow().darker(30));
+    entries.add_untested("mediaviewPlaybackInactiveOver", palette(7).darker(30));
     // \/ [UNTESTED]: video playback progress text
-    add_color("mediaviewPlaybackProgressFg", colors["color7"].borrow().lighter(40));
+    entries.add_untested("mediaviewPlaybackProgressFg", palette(7).lighter(40));
     // \/ [UNTESTED]: video playback controls icon
-    add_color("mediaviewPlaybackIconFg", get_element("mediaviewPlaybackActive"));
+    entries.add_untested("mediaviewPlaybackIconFg", key("mediaviewPlaybackActive"));
     // \/ [UNTESTED]: video playback controls icon with mouse over
-    add_color("mediaviewPlaybackIconFgOver", get_element("mediaviewPlaybackActiveOver"));
+    entries.add_untested("mediaviewPlaybackIconFgOver", key("mediaviewPlaybackActiveOver"));
     // \/ [UNTESTED]: transparent filling part (when viewing a transparent .png file in Media Viewer)
-    add_color("mediaviewTransparentBg", colors["color7"].borrow().lighter(40));
+    entries.add_untested("mediaviewTransparentBg", palette(7).lighter(40));
     // \/ [UNTESTED]: another transparent filling part
-    add_color("mediaviewTransparentFg", colors["color7"].clone());
+    entries.add_untested("mediaviewTransparentFg", palette(7));
     // \/ [UNTESTED]: custom notification window background
-    add_color("notificationBg", get_element("windowBg"));
+    entries.add_untested("notificationBg", key("windowBg"));
 
-    add_comment("Call");
+    entries.add_comment("Call");
     // \/ [UNTESTED]: phone call popup background
-    add_color("callBg", colors["color0"].clone());
+    entries.add_untested("callBg", palette(0));
     // \/ [UNTESTED]: phone call popup name text
-    add_color("callNameFg", colors["color7"].borrow().lighter(40));
+    entries.add_untested("callNameFg", palette(7).lighter(40));
     // \/ [UNTESTED]: phone call popup emoji fingerprint background
-    add_color("callFingerprintBg", colors["color0"].borrow().alpha(40));
+    entries.add_untested("callFingerprintBg", palette(0).alpha(40));
     // \/ [UNTESTED]: phone call popup status text
-    add_color("callStatusFg", colors["color7"].clone());
+    entries.add_untested("callStatusFg", palette(7));
     // \/ [UNTESTED]: phone call popup answer, hangup and mute mic icon
-    add_color("callIconFg", colors["color7"].borrow().lighter(40));
+    entries.add_untested("callIconFg", palette(7).lighter(40));
     // \/ [UNTESTED]: phone call popup answer button background
-    add_color("callAnswerBg", colors["color2"].clone());
+    entries.add_untested("callAnswerBg", palette(2));
     // \/ [UNTESTED]: phone call popup answer button ripple effect
-    add_color("callAnswerRipple", colors["color2"].borrow().darker(30));
+    entries.add_untested("callAnswerRipple", palette(2).darker(30));
     // \/ [UNTESTED]: phone call popup answer button outer ripple effect
-    add_color("callAnswerBgOuter", colors["color2"].borrow().lighter(30));
+    entries.add_untested("callAnswerBgOuter", palette(2).lighter(30));
     // \/ [UNTESTED]: phone call popup hangup button background
-    add_color("callHangupBg", colors["color1"].clone());
+    entries.add_untested("callHangupBg", palette(1));
     // \/ [UNTESTED]: phone call popup hangup button ripple effect
-    add_color("callHangupRipple", colors["color1"].borrow().darker(30));
+    entries.add_untested("callHangupRipple", palette(1).darker(30));
     // \/ [UNTESTED]: phone call popup line busy cancel button background
-    add_color("callCancelBg", colors["color7"].borrow().lighter(40));
+    entries.add_untested("callCancelBg", palette(7).lighter(40));
     // \/ [UNTESTED]: phone call popup line busy cancel button icon
-    add_color("callCancelFg", colors["color7"].borrow().darker(40));
+    entries.add_untested("callCancelFg", palette(7).darker(40));
     // \/ [UNTESTED]: phone call popup line busy cancel button ripple effect
-    add_color("callCancelRipple", colors["color7"].borrow().lighter(40));
+    entries.add_untested("callCancelRipple", palette(7).lighter(40));
     // \/ [UNTESTED]: youtube play icon background (when a link to a youtube video with a webpage preview is sent)
-    add_color("youtubePlayIconBg", Color::from_hex("83131c").unwrap().alpha(50));
+    entries.add_untested("youtubePlayIconBg", key("colorError").darker(50).alpha(50));
     // \/ [UNTESTED]: phone call popup mute mic ripple effect
-    add_color("callMuteRipple", Color::from_hex("ffffff").unwrap().alpha(05));
+    entries.add_untested("callMuteRipple", hex("ffffff").alpha(5));
     // \/ [UNTESTED]: active phone call bar background
-    add_color("callBarBg", get_element("dialogsBgActive"));
+    entries.add_untested("callBarBg", key("dialogsBgActive"));
     // \/ [UNTESTED]: active phone call bar mute and hangup button ripple effect
-    add_color("callBarMuteRipple", get_element("dialogsRippleBgActive"));
+    entries.add_untested("callBarMuteRipple", key("dialogsRippleBgActive"));
     // \/ [UNTESTED]: phone call bar with muted mic background
-    add_color("callBarBgMuted", colors["color0"].borrow().lighter(40));
+    entries.add_untested("callBarBgMuted", palette(0).lighter(40));
     // \/ [UNTESTED]: phone call bar with muted mic mute and hangup button ripple effect
-    add_color("callBarUnmuteRipple", colors["color0"].borrow().lighter(40));
+    entries.add_untested("callBarUnmuteRipple", palette(0).lighter(40));
     // \/ [UNTESTED]: phone call bar text and icons
-    add_color("callBarFg", get_element("dialogsNameFgActive"));
+    entries.add_untested("callBarFg", key("dialogsNameFgActive"));
 
-    add_comment("Important");
+    entries.add_comment("Important");
     // \/ [UNTESTED]:
-    add_color("importantTooltipBg", get_element("toastBg"));
+    entries.add_untested("importantTooltipBg", key("toastBg"));
     // \/ [UNTESTED]:
-    add_color("importantTooltipFg", get_element("toastFg"));
+    entries.add_untested("importantTooltipFg", key("toastFg"));
     // \/ [UNTESTED]:
-    add_color("importantTooltipFgLink", colors["color2"].clone());
+    entries.add_untested("importantTooltipFgLink", accent(palette(2)));
 
-    add_comment("Bot");
+    entries.add_comment("Bot");
     // \/ [UNTESTED]:
-    add_color("botKbBg", colors["color0"].clone());
+    entries.add_untested("botKbBg", palette(0));
     // \/ [UNTESTED]:
-    add_color("botKbDownBg", colors["color0"].borrow().lighter(40));
+    entries.add_untested("botKbDownBg", palette(0).lighter(40));
 
-    add_comment("Overview");
+    entries.add_comment("Overview");
     // \/ [UNTESTED]:
-    add_color("overviewCheckBorder", colors["color2"].clone());
-
-    add_comment("Sidebar");
-    add_color("sideBarBg", colors["color0"].clone());
-    add_color("sideBarBgActive", colors["color2"].clone());
-    add_color("sideBarBgRipple", colors["color1"].clone());
-    add_color("sideBarTextFg", colors["color1"].clone());
-    add_color("sideBarTextFgActive", colors["color7"].clone());
-    add_color("sideBarIconFg", colors["color7"].clone());
-    add_color("sideBarIconFgActive", colors["color7"].borrow().lighter(40));
-    add_color("sideBarBadgeBg", colors["color1"].clone());
-    add_color("sideBarBadgeBgMuted", colors["color7"].borrow().darker(40));
-    add_color("sideBarBadgeFg", colors["color7"].borrow().lighter(40));
-
-    add_comment("DUNNO");
+    entries.add_untested("overviewCheckBorder", accent(palette(2)));
+
+    entries.add_comment("Sidebar");
+    entries.add_color("sideBarBg", palette(0));
+    entries.add_color("sideBarBgActive", accent(palette(2)));
+    entries.add_color("sideBarBgRipple", palette(1));
+    entries.add_color("sideBarTextFg", palette(1));
+    entries.add_color("sideBarTextFgActive", palette(7));
+    entries.add_color("sideBarIconFg", palette(7));
+    entries.add_color("sideBarIconFgActive", palette(7).lighter(40));
+    entries.add_color("sideBarBadgeBg", accent(palette(1)));
+    entries.add_color("sideBarBadgeBgMuted", palette(7).darker(40));
+    entries.add_color("sideBarBadgeFg", palette(7).lighter(40));
+
+    entries.add_comment("DUNNO");
     // \/ [UNTESTED]:
-    add_color("profileOtherAdminStarFg", colors["color7"].clone());
+    entries.add_untested("profileOtherAdminStarFg", palette(7));
 
-    return ret.borrow().clone();
+    entries.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::{fixtures, Mode};
+
+    // The last definition of a key is the one Telegram uses
+    fn color(lines: &[ThemeLine], name: &str) -> String {
+        lines.iter().rev()
+            .find_map(|line| line.color.as_ref().filter(|_| line.name == name))
+            .unwrap_or_else(|| panic!("'{name}' is not in the theme"))
+            .to_hex()
+    }
+
+    #[test]
+    fn only_the_palette_entries_are_helpers() {
+        assert!(is_helper_key("color0") && is_helper_key("color15"));
+        for name in ["color", "colorPink", "colorError", "colorWarning", "color1a", "windowBg"] {
+            assert!(!is_helper_key(name), "'{name}' should not be a helper");
+        }
+    }
+
+    #[test]
+    fn every_written_key_is_a_telegram_key() {
+        let canonical: HashSet<String> = canonical::bundled_keys().into_iter().map(|key| key.name).collect();
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+        let mappings = [
+            Mapping::default(),
+            Mapping { status: status_colors(&palette, &[]), accent: Some(pick_accent(&palette, &"saturated".parse().unwrap())), ..Mapping::default() },
+        ];
+
+        for mapping in mappings {
+            let entries = theme_entries(fixtures::DARK.len(), &mapping);
+            let unknown: Vec<&str> = entries_keys(&entries).into_iter()
+                .filter(|name| !canonical.contains(*name) && !is_helper_key(name))
+                .collect();
+            assert!(unknown.is_empty(), "Keys Telegram doesn't define: {unknown:?}");
+        }
+    }
+
+    // Contrast of the outgoing text on its bubble, translucent bubbles are seen over the background
+    fn out_text_contrast(palette: &HashMap<String, Rc<RefCell<Color>>>, bubbles: Bubbles, background: &Color) -> f32 {
+        let lines = compute_telegram_colors(palette, &Mapping { bubbles, ..Mapping::default() });
+        let color = |name: &str| Color::from_hex(&color(&lines, name)).unwrap();
+        let bubble = color("msgOutBg").over(background);
+        color("historyTextOutFg").over(&bubble).get_contrast(&bubble)
+    }
+
+    #[test]
+    fn auto_out_bubbles_keep_the_text_readable() {
+        for mode in [Mode::Dark, Mode::Light] {
+            let palette = fixtures::prepared(&fixtures::DARK, mode);
+            for background in ["000000", "ffffff", "808080", "1a1b26", "f7768e", "7aa2f7", "c0caf5"] {
+                let background = Color::from_hex(background).unwrap();
+                for opacity in [100, 70] {
+                    let chosen = out_text_contrast(&palette, Bubbles { out_role: auto_out_role(&palette, &background, opacity), opacity }, &background);
+                    let best = (0..palette.len())
+                        .map(|out_role| out_text_contrast(&palette, Bubbles { out_role, opacity }, &background))
+                        .fold(0.0, f32::max);
+
+                    // Readable whenever a palette entry can be, the most readable otherwise
+                    let case = format!("over {} in {mode:?} mode at {opacity}%", background.to_hex());
+                    if best >= 4.5 {
+                        assert!(chosen >= 4.5, "{chosen:.2}:1 {case}");
+                    }
+                    else {
+                        assert_eq!(chosen, best, "{case}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_bubble_shadows_follow_the_bubbles() {
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+        let shadow = |out_role: usize| {
+            let lines = compute_telegram_colors(&palette, &Mapping { bubbles: Bubbles { out_role, opacity: 100 }, ..Mapping::default() });
+            color(&lines, "msgOutShadow")
+        };
+
+        assert_ne!(shadow(1), shadow(4));
+    }
+
+    // Chroma of an evaluated color, the greys are below 0.02
+    fn chroma(hex: &str) -> f32 {
+        Color::from_hex(hex).unwrap().to_oklch()[1]
+    }
+
+    #[test]
+    fn the_strategies_avoid_the_greys() {
+        let palette = fixtures::prepared(&fixtures::GREY, Mode::Dark);
+        let default = compute_telegram_colors(&palette, &Mapping::default());
+        assert!(chroma(&color(&default, "activeButtonBg")) < 0.02);
+
+        for strategy in ["saturated", "contrast", "hue:40", "hue:250", "3", "#d75f00"] {
+            let accent = pick_accent(&palette, &strategy.parse().unwrap()).eval(&palette, &HashMap::new());
+            assert!(accent.to_oklch()[1] > 0.05, "'{strategy}' picked #{}", accent.to_hex());
+        }
+    }
+
+    #[test]
+    fn the_strategies_pick_the_expected_entries() {
+        let palette = fixtures::prepared(&fixtures::GREY, Mode::Dark);
+        let pick = |strategy: &str| pick_accent(&palette, &strategy.parse().unwrap()).eval(&palette, &HashMap::new()).to_hex();
+
+        assert_eq!(pick("saturated"), "ff8700ff");
+        assert_eq!(pick("hue:250"), "5f87afff");
+        assert_eq!(pick("#123456"), "123456ff");
+    }
+
+    #[test]
+    fn the_accent_reaches_every_accent_key() {
+        let palette = fixtures::prepared(&fixtures::GREY, Mode::Dark);
+        let accent = pick_accent(&palette, &Accent::Saturated);
+        let expected = accent.eval(&palette, &HashMap::new()).to_hex();
+        let lines = compute_telegram_colors(&palette, &Mapping { accent: Some(accent), ..Mapping::default() });
+
+        for name in ["activeButtonBg", "activeLineFg", "historyLinkInFg", "historyLinkOutFg", "dialogsUnreadBg", "sideBarBgActive", "msgServiceBgSelected"] {
+            assert_eq!(color(&lines, name), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn high_contrast_text_reaches_aaa() {
+        // Same targets as the high-contrast preset of the settings
+        let mapping = Mapping { text_contrast: Some(7.0), secondary_contrast: Some(7.0), high_contrast: true, ..Mapping::default() };
+        for hexes in [&fixtures::DARK, &fixtures::GREY] {
+            for mode in [Mode::Dark, Mode::Light] {
+                let lines = compute_telegram_colors(&fixtures::prepared(hexes, mode), &mapping);
+
+                for (fg, bg, ratio) in audit_text_contrast(&lines) {
+                    assert!(ratio >= 7.0, "{fg} on {bg} at {ratio:.2}:1 in {mode:?} mode");
+                    assert!(color(&lines, &fg).ends_with("ff"), "{fg} is translucent in {mode:?} mode");
+                }
+                // The borders stand out and the states differ by more than their hue
+                for (fg, bg) in BORDER_PAIRS.iter().chain(STATE_PAIRS.iter()) {
+                    let [fg_color, bg_color] = [fg, bg].map(|name| Color::from_hex(&color(&lines, name)).unwrap());
+                    let ratio = fg_color.over(&bg_color).get_contrast(&bg_color);
+                    assert!(ratio >= 3.0, "{fg} on {bg} at {ratio:.2}:1 in {mode:?} mode");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_text_key_has_its_background() {
+        let lines = compute_telegram_colors(&fixtures::prepared(&fixtures::DARK, Mode::Dark), &Mapping::default());
+        let pairs: HashMap<String, String> = text_pairs(&lines).into_iter().collect();
+
+        for (fg, bg) in [
+            ("dialogsTextFgService", "dialogsBg"), ("dialogsDraftFgActive", "dialogsBgActive"), ("searchedBarFg", "searchedBarBg"),
+            ("historyFileNameInFg", "msgInBg"), ("historyLinkOutFgSelected", "msgOutBgSelected"), ("msgInDateFgSelected", "msgInBgSelected"),
+            ("mediaInFg", "msgInBg"), ("importantTooltipFg", "importantTooltipBg"), ("boxTextFgError", "boxBg"), ("boxTextFgGood", "boxBg"),
+            ("emojiPanHeaderFg", "emojiPanHeaderBg"), ("titleFg", "titleBg"), ("historyPeer1NameFg", "msgInBg"), ("placeholderFgActive", "windowBg"),
+        ] {
+            assert_eq!(pairs.get(fg).map(String::as_str), Some(bg), "{fg}");
+        }
+        for key in ["shadowFg", "windowShadowFg", "activeLineFg", "mediaviewFileRedCornerFg"] {
+            assert!(!pairs.contains_key(key), "{key}");
+        }
+
+        // Every other written text key is paired
+        let written: HashSet<&str> = lines.iter().filter(|line| line.color.is_some()).map(|line| line.name.as_str()).collect();
+        for key in written.iter().filter(|key| key.contains("Fg")) {
+            let excluded = NON_TEXT_KEYS.contains(key) || BORDER_PAIRS.iter().any(|(fg, _)| fg == key);
+            assert!(excluded || pairs.get(*key).is_some_and(|bg| written.contains(bg.as_str())), "{key}");
+        }
+    }
+
+    #[test]
+    fn warning_and_info_are_not_written() {
+        let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+        let mapping = Mapping { status: status_colors(&palette, &[]), ..Mapping::default() };
+        let lines = compute_telegram_colors(&palette, &mapping);
+
+        assert!(lines.iter().all(|line| line.name != "colorWarning" && line.name != "colorInfo"));
+        let status = |status: Status| mapping.status.get(status).eval(&palette, &HashMap::new()).to_hex();
+        assert_eq!(color(&lines, "mediaviewFileYellowCornerFg"), status(Status::Warning));
+        assert_eq!(color(&lines, "mediaviewFileBlueCornerFg"), status(Status::Info));
+    }
 }
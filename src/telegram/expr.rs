@@ -0,0 +1,117 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::color::Color;
+
+// How the color of a Telegram key is derived
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    // Palette entry colorN
+    Palette(usize),
+    // Key defined earlier in the theme
+    Key(String),
+    // Fixed color
+    Hex(String),
+    Darker(Box<Expr>, u8),
+    Lighter(Box<Expr>, u8),
+    Alpha(Box<Expr>, u8),
+    // Palette color at this rank once sorted by contrast with the color, 0 is the most contrasted
+    Contrast(Box<Expr>, usize),
+    // Color set in the config overrides
+    Override(String),
+    // Pushed away from the color of the key until reaching the contrast ratio
+    MinContrast(Box<Expr>, String, f32),
+    // Pushed away from black or white until reaching the contrast ratio, so that the text key,
+    // which was on the other side, can reach it by switching to that extreme
+    Flip(Box<Expr>, String, String, f32),
+    // Blended over the color of the key, what is seen once the transparency is removed
+    Opaque(Box<Expr>, String),
+}
+
+pub fn palette(index: usize) -> Expr {
+    Expr::Palette(index)
+}
+
+pub fn key(name: &str) -> Expr {
+    Expr::Key(String::from(name))
+}
+
+pub fn hex(hex: &str) -> Expr {
+    Expr::Hex(String::from(hex.trim_start_matches('#')))
+}
+
+impl Expr {
+    pub fn darker(self, percentage: u8) -> Expr {
+        Expr::Darker(Box::new(self), percentage)
+    }
+
+    pub fn lighter(self, percentage: u8) -> Expr {
+        Expr::Lighter(Box::new(self), percentage)
+    }
+
+    pub fn alpha(self, percentage: u8) -> Expr {
+        Expr::Alpha(Box::new(self), percentage)
+    }
+
+    pub fn contrast(self, index: usize) -> Expr {
+        Expr::Contrast(Box::new(self), index)
+    }
+
+    // Compute the color
+    // - keys are the colors of the keys defined so far
+    pub fn eval(&self, palette: &HashMap<String, Rc<RefCell<Color>>>, keys: &HashMap<String, Color>) -> Color {
+        match self {
+            Expr::Palette(index) => {
+                palette.get(format!("color{index}").as_str())
+                    .unwrap_or_else(|| panic!("The palette has no 'color{index}'"))
+                    .borrow().clone()
+            }
+            Expr::Key(name) => {
+                keys.get(name)
+                    .unwrap_or_else(|| panic!("Could not find '{name}' in the previously defined elements"))
+                    .clone()
+            }
+            Expr::Hex(hex) | Expr::Override(hex) => {
+                let color = Color::from_hex(hex).unwrap_or_else(|| panic!("Unexpected error, '{hex}' is not a valid color"));
+                // So that the colors derived from it can be picked in the palette
+                crate::color::with_palette_contrasts(&color, palette)
+            }
+            Expr::Darker(expr, percentage) => expr.eval(palette, keys).darker(*percentage).borrow().clone(),
+            Expr::Lighter(expr, percentage) => expr.eval(palette, keys).lighter(*percentage).borrow().clone(),
+            Expr::Alpha(expr, percentage) => expr.eval(palette, keys).alpha(*percentage).borrow().clone(),
+            Expr::Contrast(expr, index) => expr.eval(palette, keys).contrast(*index).borrow().clone(),
+            Expr::MinContrast(expr, background, target) => {
+                let background = Expr::Key(background.clone()).eval(palette, keys);
+                expr.eval(palette, keys).with_min_contrast(&background, *target).borrow().clone()
+            }
+            Expr::Flip(expr, _, extreme, target) => {
+                let extreme = Expr::Hex(extreme.clone()).eval(palette, keys);
+                expr.eval(palette, keys).with_min_contrast(&extreme, *target).borrow().clone()
+            }
+            Expr::Opaque(expr, background) => {
+                let background = Expr::Key(background.clone()).eval(palette, keys);
+                expr.eval(palette, keys).over(&background)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Palette(index) => write!(f, "palette.color{index}"),
+            Expr::Key(name) => write!(f, "{name}"),
+            Expr::Hex(hex) => write!(f, "#{hex}"),
+            Expr::Darker(expr, percentage) => write!(f, "{expr}.darker({percentage})"),
+            Expr::Lighter(expr, percentage) => write!(f, "{expr}.lighter({percentage})"),
+            Expr::Alpha(expr, percentage) => write!(f, "{expr}.alpha({percentage})"),
+            Expr::Contrast(expr, index) => write!(f, "{expr}.contrast({index})"),
+            Expr::Override(hex) => write!(f, "override(#{hex})"),
+            Expr::MinContrast(expr, background, target) => write!(f, "{expr}.min_contrast({background}, {target})"),
+            Expr::Flip(expr, text, extreme, target) => write!(f, "{expr}.flip({text}, #{extreme}, {target})"),
+            Expr::Opaque(expr, background) => write!(f, "{expr}.opaque({background})"),
+        }
+    }
+}
@@ -1,2 +1,4 @@
+pub mod canonical;
 pub mod colors;
+pub mod expr;
 pub mod theme;
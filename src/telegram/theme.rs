@@ -6,57 +6,24 @@ use core::cell::RefCell;
 
 use crate::color::Color;
 
-fn process_output(output_str: &Option<&str>) -> std::path::PathBuf {
-    let default_filename = "wal_telegram.tdesktop-theme";
-    let mut default_path = std::path::PathBuf::new();
+pub static THEME_EXTENSION: &str = "tdesktop-theme";
 
-    if let Ok(xdg_dirs) = xdg::BaseDirectories::new() {
-        default_path.push(xdg_dirs.get_cache_home());
-        default_path.push("wal-telegram");
-    }
-    else {
-        // Problem initializing xdg_dirs, try manually
-        default_path.push("~/.cache/wal-telegram");
+// Modification time of the zip entries, SOURCE_DATE_EPOCH when set or the earliest zip date
+fn entries_time() -> zip::DateTime {
+    let (year, month, day, hour, minute, second) = match crate::metadata::source_date_epoch() {
+        Some(epoch) => crate::metadata::utc_date(epoch),
+        None => { return zip::DateTime::default(); }
     };
 
-    default_path.push(default_filename);
-
-    let mut output_path;
-
-    if let Some(output_str) = output_str {
-        output_path = std::path::PathBuf::new();
-        output_path.push(output_str);
-
-        // If it's a dir create the theme file in there with default filename
-        if output_path.as_path().is_dir() {
-            output_path.push(default_filename);
-        }
+    // Zip dates go from 1980 to 2107
+    if !(1980..=2107).contains(&year) {
+        return zip::DateTime::default();
     }
-    else {
-        // If it's None use default
-        output_path = default_path;
-    }
-
-    // Will fail if permissions are denied
-    output_path.try_exists().expect(format!("The output path {} cannot be probed, permissions are probably the issue.", output_path.display()).as_str());
-
-    // Warning prompt
-    if output_path.exists() {
-        if output_path.is_dir() {
-            panic!("The output path {} already exists as a directory", output_path.display());
-        }
-        else {
-            if std::env::var("WAL_TELEGRAM_YES_ALL").is_ok_and(|var| { var == "TRUE" }) {
-                // TODO: Panic if no
-                println!("Prompt it's gonna delete you sure ?");
-            }
-        }
-    }
-
-    return output_path;
+    zip::DateTime::from_date_and_time(year as u16, month as u8, day as u8, hour as u8, minute as u8, second as u8)
+        .unwrap_or_default()
 }
 
-fn write_entire_file(writer: &mut zip::ZipWriter<std::fs::File>, data: &[u8]) -> std::io::Result<()> {
+fn write_entire_file<W: Write + std::io::Seek>(writer: &mut zip::ZipWriter<W>, data: &[u8]) -> std::io::Result<()> {
     let mut bytes_written = 0;
 
     while bytes_written < data.len() {
@@ -74,25 +41,26 @@ fn write_entire_file(writer: &mut zip::ZipWriter<std::fs::File>, data: &[u8]) ->
 // be assumed as existing)
 // - input_palette is the HashMap precomputed with the palette colors from either wal or the custom
 // palette
-pub fn package_theme(tmp_dir: &std::path::PathBuf, output: Option<&str>, input_palette: &HashMap<String, Rc<RefCell<Color>>>) {
+// - mapping holds the options of the palette to Telegram colors mapping
+// - profile is the name of the config profile the theme is built for, if any
+// - writing tells what to do with an existing theme
+// - metadata is added to the theme when given
+// Returns the path of the theme, or None if an existing theme was kept
+pub fn package_theme(tmp_dir: &crate::tmp_dir::TmpDir, output: Option<&str>, input_palette: &HashMap<String, Rc<RefCell<Color>>>, mapping: &super::colors::Mapping, profile: Option<&str>, writing: &crate::output::Writing, metadata: Option<&crate::metadata::Metadata>) -> Option<std::path::PathBuf> {
 
-    let output_path = process_output(&output);
+    let output_path = crate::output::process_output(&output, profile, THEME_EXTENSION);
 
     // Tmp files
-    let mut tmp_bg = tmp_dir.clone();
+    let mut tmp_bg = tmp_dir.to_path_buf();
     tmp_bg.push(super::super::background::BG_FILENAME);
 
-    let mut tmp_palette = tmp_dir.clone();
+    let mut tmp_palette = tmp_dir.to_path_buf();
     tmp_palette.push(super::super::palette::PALETTE_FILENAME);
 
-    let mut tmp_theme = tmp_dir.clone();
-    tmp_theme.push("wal_telegram.tdesktop-theme");
-
     // Bg bytes to write to zip
-    let bg_contents;
-    match std::fs::read(&tmp_bg) {
+    let bg_contents = match std::fs::read(&tmp_bg) {
         Ok(contents) => {
-            bg_contents = contents;
+            contents
         }
         Err(err) => {
             eprintln!("Error trying to read contents of bg file '{}'", &tmp_bg.display());
@@ -101,85 +69,82 @@ pub fn package_theme(tmp_dir: &std::path::PathBuf, output: Option<&str>, input_p
     };
 
     // Generate the colors
-    let telegram_colors = super::colors::get_telegram_colors(&input_palette);
-    // TODO: Write in tmp file when debugging only
-    match std::fs::write(&tmp_palette, &telegram_colors) {
-        Ok(_) => {}
-        Err(err) => {
+    let telegram_colors = super::colors::get_telegram_colors(input_palette, mapping);
+    // Only kept for debugging
+    if tmp_dir.keep() {
+        if let Err(err) = std::fs::write(&tmp_palette, &telegram_colors) {
             eprintln!("Error trying to write the temporary telegram palette to '{}'", &tmp_palette.display());
             panic!("Error: {}", err);
         }
     }
 
-    // Zip it in theme
-    match std::fs::File::create(&tmp_theme) {
-        Ok(file) => {
-            let mut zip_writer = zip::ZipWriter::new(file);
-
-            let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated).unix_permissions(0o755);
-
-            match zip_writer.start_file(tmp_bg.file_name().unwrap().to_str().unwrap(), options) {
-                Ok(_) => { 
-                    match write_entire_file(&mut zip_writer, bg_contents.as_slice()) {
-                        Ok(_) => { }
-                        Err(err) => {
-                            eprintln!("Error trying to write '{}' to the temporary telegram theme zip '{}'", &tmp_bg.display(), &tmp_theme.display());
-                            panic!("Error: {}", err);
-                        }
-                    };
-                }
-                Err(err) => {
-                    eprintln!("Error trying to start file '{}' in the temporary telegram theme zip '{}'", &tmp_bg.display(), &tmp_theme.display());
-                    panic!("Error: {}", err);
-                }
-            };
-
-            match zip_writer.start_file(tmp_palette.file_name().unwrap().to_str().unwrap(), options) {
-                Ok(_) => {
-                    match write_entire_file(&mut zip_writer, telegram_colors.as_bytes()) {
-                    // match zip_writer.write(telegram_colors.as_bytes()) {
-                        Ok(_) => { }
-                        Err(err) => {
-                            eprintln!("Error trying to write '{}' to the temporary telegram theme zip '{}'", &tmp_palette.display(), &tmp_theme.display());
-                            panic!("Error: {}", err);
-                        }
-                    };
-                }
-                Err(err) => {
-                    eprintln!("Error trying to start file '{}' in the temporary telegram theme zip '{}'", &tmp_palette.display(), &tmp_theme.display());
-                    panic!("Error: {}", err);
-                }
-            };
-
-            match zip_writer.finish() {
-                Ok(_) => { }
-                Err(err) => {
-                    eprintln!("Error finishing writing to the temporary telegram theme zip '{}'", &tmp_theme.display());
-                    panic!("Error: {}", err);
-                }
-            };
-        }
-
-        Err(err) => {
-            eprintln!("Error trying creating the temporary telegram theme zip file '{}'", &tmp_theme.display());
-            eprintln!("Error: {}", err);
+    // Zip it in memory
+    let mut zip_writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    // Nothing depends on the time of the build so the same palette always gives the same bytes
+    let options = zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .compression_level(Some(6))
+        .last_modified_time(entries_time())
+        .unix_permissions(0o755);
+
+    let metadata = metadata.map(|metadata| metadata.to_json());
+    let mut entries = vec![
+        (super::super::background::BG_FILENAME, bg_contents.as_slice()),
+        (super::super::palette::PALETTE_FILENAME, telegram_colors.as_bytes()),
+    ];
+    if let Some(metadata) = &metadata {
+        entries.push((crate::metadata::METADATA_FILENAME, metadata.as_bytes()));
+    }
+    for (filename, contents) in entries {
+        let written = zip_writer.start_file(filename, options)
+            .map_err(std::io::Error::from)
+            .and_then(|_| write_entire_file(&mut zip_writer, contents));
+        if let Err(err) = written {
+            eprintln!("Error trying to write '{}' to the telegram theme zip", filename);
+            panic!("Error: {}", err);
         }
-    };
+    }
 
-    // Copy the zip to output_path
-    match std::fs::create_dir_all(&output_path.parent().unwrap()) {
-        Ok(_) => {}
-        Err(err) => {
-            eprintln!("Error trying to create directories to '{}'", &output_path.parent().unwrap().display());
-            panic!("Error: {}", err);
+    let theme_contents = match zip_writer.finish() {
+        Ok(cursor) => {
+            cursor.into_inner()
         }
-    };
-    match std::fs::copy(&tmp_theme, &output_path) {
-        Ok(_) => { }
         Err(err) => {
-            eprintln!("Error trying to copy '{}' to '{}'", &tmp_theme.display(), &output_path.display());
+            eprintln!("Error finishing writing the telegram theme zip");
             panic!("Error: {}", err);
         }
     };
+
+    // Replace output_path with the zip
+    if !crate::output::write_output(&output_path, &theme_contents, writing) {
+        return None;
+    }
+
+    println!("Theme written to '{}'", output_path.display());
+    Some(output_path)
 }
 
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::palette::{fixtures, Mode};
+
+    #[test]
+    fn the_same_palette_gives_the_same_bytes() {
+        let tmp_dir = crate::tmp_dir::create_tmp_dir(false);
+        let writing = crate::output::Writing { overwrite: crate::output::Overwrite::Yes, backups: 0 };
+
+        let build = |name: &str| {
+            // Every build has its own hash maps, so their own iteration order
+            let palette = fixtures::prepared(&fixtures::DARK, Mode::Dark);
+            crate::background::create_bg(&tmp_dir, &palette["color0"].borrow());
+            let output = tmp_dir.join(name);
+            package_theme(&tmp_dir, output.to_str(), &palette, &super::super::colors::Mapping::default(), None, &writing, None).unwrap();
+            Sha256::digest(std::fs::read(output).unwrap())
+        };
+
+        assert_eq!(build("first.tdesktop-theme"), build("second.tdesktop-theme"));
+    }
+}
@@ -1,13 +1,67 @@
 use rand::RngCore;
 
-pub fn create_tmp_dir() -> std::path::PathBuf {
-    // The directory where to manipulate the theme before final output
-    let mut tmp_dir = std::env::temp_dir();
-    tmp_dir.push("wal-telegram/");
+#[cfg(test)]
+thread_local! {
+    // Where the tests of this thread put their tmp dirs instead, to check what is left in it
+    static TEST_ROOT: std::cell::RefCell<Option<std::path::PathBuf>> = const { std::cell::RefCell::new(None) };
+}
+
+// The directory holding all the tmp dirs
+fn tmp_root() -> std::path::PathBuf {
+    #[cfg(test)]
+    if let Some(root) = TEST_ROOT.with_borrow(|root| root.clone()) {
+        return root;
+    }
+
+    let mut tmp_root = std::env::temp_dir();
+    tmp_root.push("wal-telegram/");
+
+    tmp_root
+}
+
+// The directory where to manipulate the theme before final output, it is deleted when dropped,
+// panics included, unless it is kept for debugging
+pub struct TmpDir {
+    path: std::path::PathBuf,
+    keep: bool,
+}
+
+impl TmpDir {
+    pub fn keep(&self) -> bool {
+        self.keep
+    }
+}
+
+impl std::ops::Deref for TmpDir {
+    type Target = std::path::Path;
+
+    fn deref(&self) -> &Self::Target {
+        &self.path
+    }
+}
+
+impl Drop for TmpDir {
+    fn drop(&mut self) {
+        if self.keep {
+            eprintln!("Temporary files kept in '{}'", self.path.display());
+            return;
+        }
+
+        if let Err(err) = std::fs::remove_dir_all(&self.path) {
+            eprintln!("WARNING! Couldn't remove the temporary directory '{}': {}", self.path.display(), err);
+        }
+        // Only removed when no other run is using it
+        std::fs::remove_dir(tmp_root()).ok();
+    }
+}
+
+// - keep leaves the directory and its intermediate files behind
+pub fn create_tmp_dir(keep: bool) -> TmpDir {
+    let mut tmp_dir = tmp_root();
 
     let mut rand_folder = String::new();
     if let Ok(epoch_time) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
-        rand_folder.push_str((String::from(epoch_time.as_secs().to_string()) + "_").as_str());
+        rand_folder.push_str((epoch_time.as_secs().to_string() + "_").as_str());
     }
     rand_folder.push_str(rand::thread_rng().next_u32().to_string().as_str());
 
@@ -20,5 +74,106 @@ pub fn create_tmp_dir() -> std::path::PathBuf {
         }
     };
 
-    return tmp_dir;
+    TmpDir { path: tmp_dir, keep }
+}
+
+// Remove the tmp dirs left by previous runs that are older than max_age
+// Returns the number of removed directories
+pub fn clean_tmp_dirs(max_age: std::time::Duration) -> usize {
+    let entries = match std::fs::read_dir(tmp_root()) {
+        Ok(entries) => entries,
+        // Nothing was ever left behind
+        Err(_) => { return 0; }
+    };
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let age = entry.metadata().and_then(|metadata| metadata.modified())
+            .ok().and_then(|modified| modified.elapsed().ok());
+
+        if !path.is_dir() || age.is_none_or(|age| age < max_age) {
+            continue;
+        }
+
+        match std::fs::remove_dir_all(&path) {
+            Ok(_) => {
+                println!("Removed '{}'", path.display());
+                removed += 1;
+            }
+            Err(err) => {
+                eprintln!("WARNING! Couldn't remove '{}': {}", path.display(), err);
+            }
+        }
+    }
+    std::fs::remove_dir(tmp_root()).ok();
+
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Build a theme with a custom background with the tmp dirs in their own root, the files used
+    // by the build are kept out of it. Returns the panic message of a failed build and the root
+    fn build_with_background(background: impl FnOnce(&std::path::Path)) -> (Option<String>, std::path::PathBuf, TmpDir) {
+        let files = create_tmp_dir(false);
+        let background_path = files.join("wallpaper.png");
+        background(&background_path);
+        let root = files.join("tmp");
+
+        let settings = crate::config::Settings::try_from(crate::config::Options {
+            output: Some(vec![files.join("theme.tdesktop-theme").to_str().unwrap().to_string()]),
+            background: Some(crate::cli::Background::Custom),
+            background_path: Some(background_path.to_str().unwrap().to_string()),
+            ..crate::config::Options::default()
+        }).unwrap();
+        let palette = crate::palette::fixtures::parsed(&crate::palette::fixtures::DARK);
+        let writing = crate::output::Writing { overwrite: crate::output::Overwrite::Yes, backups: 0 };
+
+        TEST_ROOT.set(Some(root.clone()));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            crate::generate(&settings, &palette, &mut crate::background::ImageCache::default(), &writing, false, &crate::Action::Theme)
+        }));
+        TEST_ROOT.set(None);
+
+        let panic = result.err().map(|payload| payload.downcast_ref::<String>().cloned().unwrap_or_default());
+        (panic, root, files)
+    }
+
+    fn is_empty(root: &std::path::Path) -> bool {
+        std::fs::read_dir(root).map_or(true, |mut entries| entries.next().is_none())
+    }
+
+    #[test]
+    fn nothing_remains_after_success() {
+        let (panic, root, files) = build_with_background(|path| {
+            image::RgbImage::from_fn(64, 64, |x, y| image::Rgb([x as u8 * 4, y as u8 * 4, 128])).save(path).unwrap();
+        });
+
+        assert_eq!(panic, None);
+        assert!(files.join("theme.tdesktop-theme").is_file());
+        assert!(is_empty(&root));
+    }
+
+    #[test]
+    fn nothing_remains_after_failure() {
+        // The build fails once the tmp dir is created, on the background
+        let (panic, root, _files) = build_with_background(|path| std::fs::write(path, b"not an image").unwrap());
+
+        assert!(panic.unwrap().contains("as an image"));
+        assert!(is_empty(&root));
+    }
+
+    #[test]
+    fn kept_directories_remain() {
+        let tmp_dir = create_tmp_dir(true);
+        std::fs::write(tmp_dir.join("background.jpg"), b"jpeg").unwrap();
+        let path = tmp_dir.to_path_buf();
+
+        drop(tmp_dir);
+        assert!(path.join("background.jpg").is_file());
+        std::fs::remove_dir_all(path).unwrap();
+    }
 }